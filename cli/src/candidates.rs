@@ -0,0 +1,78 @@
+use anyhow::{bail, Result};
+use hashassin_core::{
+    mask::{self as masks, Mask},
+    prince::PrinceOptions,
+    rules::RuleSet,
+};
+use std::{
+    fs::File,
+    io::{BufRead, BufReader},
+    num::NonZeroUsize,
+    path::Path,
+};
+use tracing::{debug, info};
+
+/// Loads a rule file, or the built in set when given its name
+pub(crate) fn load_rules(rules: &str) -> Result<RuleSet> {
+    let rule_set = match rules {
+        "best64" => RuleSet::best64()?,
+        path => RuleSet::parse(BufReader::new(File::open(path)?))?,
+    };
+    info!("Loaded {} rules", rule_set.len());
+    Ok(rule_set)
+}
+
+/// Streams the lines of a wordlist, skipping lines that are not UTF-8
+pub(crate) fn wordlist_lines<R: BufRead>(reader: R) -> impl Iterator<Item = Result<String>> {
+    reader.split(b'\n').filter_map(|line| match line {
+        Ok(mut line) => {
+            if line.last() == Some(&b'\r') {
+                line.pop();
+            }
+            match String::from_utf8(line) {
+                Ok(word) => Some(Ok(word)),
+                Err(_) => {
+                    debug!("Skipping wordlist line that is not UTF-8");
+                    None
+                }
+            }
+        }
+        Err(error) => Some(Err(error.into())),
+    })
+}
+
+/// Reads a whole wordlist, skipping lines that are not UTF-8
+pub(crate) fn read_wordlist(path: &Path) -> Result<Vec<String>> {
+    let words = wordlist_lines(BufReader::new(File::open(path)?)).collect::<Result<Vec<_>>>()?;
+    info!("Read {} words from {}", words.len(), path.display());
+    Ok(words)
+}
+
+/// Parses a mask with custom charsets given on the command line
+pub(crate) fn parse_mask(mask: &str, custom_charsets: &[String]) -> Result<Mask> {
+    let custom = custom_charsets
+        .iter()
+        .map(|charset| masks::custom_charset(charset))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Mask::parse(mask, &custom)?)
+}
+
+/// PRINCE options from the command line, defaults for bounds not given
+pub(crate) fn prince_options(
+    min_char: Option<NonZeroUsize>,
+    max_char: Option<NonZeroUsize>,
+    min_elements: NonZeroUsize,
+    max_elements: NonZeroUsize,
+) -> Result<PrinceOptions> {
+    let default = PrinceOptions::default();
+    let options = PrinceOptions {
+        min_elements: min_elements.get(),
+        max_elements: max_elements.get(),
+        min_len: min_char.map_or(default.min_len, usize::from),
+        max_len: max_char.map_or(default.max_len, usize::from),
+    };
+    if options.min_elements > options.max_elements || options.min_len > options.max_len {
+        bail!("PRINCE minimums must not be above the maximums");
+    }
+    Ok(options)
+}
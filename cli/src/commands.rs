@@ -4,5 +4,10 @@ pub mod gen_passwords;
 
 pub mod rainbow_table;
 
+pub mod convert_table;
 pub mod crack;
+pub mod potfile;
 pub mod server;
+pub mod strength;
+pub mod train_markov;
+pub mod train_pcfg;
@@ -0,0 +1,62 @@
+use anyhow::{bail, Result};
+use clap::Args;
+use hashassin_core::{
+    table::{self, TableFile, TableFormat},
+    RainbowTable,
+};
+use std::{fs::File, io::BufWriter, num::NonZeroUsize, path::PathBuf};
+use tracing::{info, trace, warn};
+
+/// Options for rainbow table conversion
+#[derive(Args, Debug)]
+pub(crate) struct ConvertTableOpts {
+    /// This is the path to the text rainbow table
+    #[clap(long)]
+    in_path: PathBuf,
+
+    /// This is the path to write the binary rainbow table to
+    #[clap(long)]
+    out_path: PathBuf,
+
+    /// This is the number of links in each chain, for tables without a header
+    #[clap(long, default_value = "5")]
+    num_links: NonZeroUsize,
+
+    /// This is the length of the passwords, for tables without a header
+    #[clap(long, default_value = "4")]
+    password_length: NonZeroUsize,
+
+    /// This is the hashing algorithm, for tables without a header
+    #[clap(long, default_value = "Md5")]
+    algorithm: String,
+}
+
+/// Converts a text rainbow table to the binary format
+pub(crate) fn do_convert_table(opts: ConvertTableOpts) -> Result<()> {
+    trace!("Convert table");
+    let table = TableFile::open(&opts.in_path)?;
+    let header = match table.header {
+        Some(header) if header.format == TableFormat::Binary => {
+            bail!("{} is already binary", opts.in_path.display())
+        }
+        Some(header) => header,
+        None => {
+            warn!(
+                "{} has no header, converting with the command line parameters",
+                opts.in_path.display()
+            );
+            RainbowTable::new(opts.num_links, opts.password_length, opts.algorithm)?
+                .with_legacy_reduction()?
+                .header(0)
+        }
+    };
+
+    let mut writer = BufWriter::new(File::create(&opts.out_path)?);
+    let written = table::write_binary(&header, table.chains.pairs(), &mut writer)?;
+    info!(
+        "Wrote {written} of {} chains to {}",
+        table.chains.len(),
+        opts.out_path.display()
+    );
+    Ok(())
+}
@@ -1,23 +1,103 @@
-use anyhow::{anyhow, Result};
-use clap::Args;
-use hashassin_core::Crack;
+use anyhow::{anyhow, bail, Result};
+use clap::{Args, Parser, ValueEnum};
+use hashassin_core::{
+    association,
+    combinator::{Combinator, Hybrid},
+    encoding::{Encoding, Normalization},
+    keyspace::{self, Keyspace},
+    markov::{MarkovModel, MarkovOptions},
+    mask::MaskPosition,
+    pcfg::{Pcfg, PcfgOptions},
+    potfile::Potfile,
+    prince::Prince,
+    rules::RuleSet,
+    session::{Session, Watermark},
+    table::TableFile,
+    targets::{Target, TargetSet},
+    Crack, CrackOutcome,
+};
+
+use crate::candidates::{load_rules, parse_mask, prince_options, read_wordlist, wordlist_lines};
+use crate::outcome::{OutcomeFormat, OutcomeWriter};
+use crate::progress::{Progress, ProgressOpts, Reporter};
 
 use std::{
-    collections::HashMap,
-    fs::File,
-    io::{self, BufRead, BufReader, Read, Stdout, Write},
+    collections::HashSet,
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, ErrorKind, Read, Stdout, Write},
     num::NonZeroUsize,
-    path::PathBuf,
-    thread,
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        mpsc::Receiver,
+        Arc, Mutex, PoisonError,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+use tracing::{debug, info, trace, warn};
+
+/// Candidates sent to a worker at once
+const BATCH_SIZE: usize = 1024;
+
+/// Potfile used when none is given
+pub(crate) const DEFAULT_POTFILE: &str = "hashassin.potfile";
+
+/// Rainbow table parameters assumed for tables without a header
+const DEFAULT_NUM_LINKS: NonZeroUsize = match NonZeroUsize::new(5) {
+    Some(num_links) => num_links,
+    None => NonZeroUsize::MIN,
+};
+const DEFAULT_PASSWORD_LENGTH: NonZeroUsize = match NonZeroUsize::new(4) {
+    Some(password_length) => password_length,
+    None => NonZeroUsize::MIN,
 };
-use tracing::{debug, info};
+const DEFAULT_ALGORITHM: &str = "Md5";
+
+/// How often a named session is checkpointed
+const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How the targets are attacked
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Attack {
+    /// Look hashes up in a rainbow table
+    Rainbow,
+    /// Hash every word of a wordlist
+    Dictionary,
+    /// Join every word of the wordlist with every word of the right wordlist
+    Combinator,
+    /// Add every candidate of the mask to each word of the wordlist
+    Hybrid,
+    /// Chain words of the wordlist into passphrases, shortest first
+    Prince,
+    /// Try every candidate of a mask, like ?u?l?l?l?d?d
+    Mask,
+    /// Try words made from each user's name against only that user's hash
+    Association,
+    /// Try the guesses of a Markov model, most likely first
+    Markov,
+    /// Try the guesses of a PCFG grammar, most likely first
+    Pcfg,
+}
+
+/// How the target hashes are stored
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum TargetFormat {
+    /// Concatenated raw 16 byte digests, as written by gen-hashes
+    Binary,
+    /// One `hash` or `hash:salt` per line, the hash in hex and the salt raw or as $HEX[...]
+    Hex,
+    /// One `user:hash` or `user:hash:salt` per line, the hash in hex and the salt raw or as $HEX[...]
+    User,
+}
 
 /// Options for password generation
 #[derive(Args, Debug)]
 pub(crate) struct CrackOpts {
-    // Number of links in each chain
-    #[clap(long, default_value = "5")]
-    num_links: NonZeroUsize,
+    // Number of links in each chain, read from the rainbow table header when it has one
+    #[clap(long)]
+    num_links: Option<NonZeroUsize>,
 
     // Threads used to generate rainbow table
     #[clap(long, default_value = "1")]
@@ -27,20 +107,142 @@ pub(crate) struct CrackOpts {
     #[clap(long)]
     out_path: Option<PathBuf>,
 
-    // Length of passwords for rainbow table
-    #[clap(long, default_value = "4")]
-    password_length: NonZeroUsize,
+    // Length of passwords for rainbow table, read from the rainbow table header when it has one
+    #[clap(long)]
+    password_length: Option<NonZeroUsize>,
 
-    // Hashing algorithm used
-    #[clap(long, default_value = "Md5")]
-    algorithm: String,
+    // Hashing algorithm used, Md5 unless the rainbow table header says otherwise
+    #[clap(long)]
+    algorithm: Option<String>,
 
     // Input path to passwords
-    #[clap(long)]
-    in_path: PathBuf,
+    #[clap(long, required_unless_present("restore"))]
+    in_path: Option<PathBuf>,
     // Input path to rainbow table
+    #[clap(long, required_if_eq("attack", "rainbow"))]
+    rainbow_path: Option<PathBuf>,
+
+    /// Attack mode
+    #[clap(long, value_enum, default_value = "rainbow")]
+    attack: Attack,
+
+    /// Wordlist for the dictionary attack (- for stdin), or the left wordlist for combinator and hybrid
+    #[clap(long, required_if_eq_any([("attack", "dictionary"), ("attack", "combinator"), ("attack", "hybrid"), ("attack", "prince")]))]
+    wordlist: Option<PathBuf>,
+
+    /// Wordlist whose words go second in the combinator attack
+    #[clap(long, required_if_eq("attack", "combinator"))]
+    right_wordlist: Option<PathBuf>,
+
+    /// Put between the two words in the combinator attack
+    #[clap(long, default_value = "")]
+    separator: String,
+
+    /// Mask like ?u?l?l?l?d?d for the mask attack, or ?d?d for the hybrid attack
+    #[clap(long, required_if_eq_any([("attack", "hybrid"), ("attack", "mask")]))]
+    mask: Option<String>,
+
+    /// Side of each word the hybrid mask goes on: append or prepend
+    #[clap(long, default_value = "append")]
+    mask_position: MaskPosition,
+
+    /// Custom charset like ?l?d, used in masks as ?1, ?2, ... in the order given
+    #[clap(long)]
+    custom_charset: Vec<String>,
+
+    /// Shortest PRINCE, Markov or PCFG candidate
+    #[clap(long)]
+    min_char: Option<NonZeroUsize>,
+
+    /// Longest PRINCE, Markov or PCFG candidate
     #[clap(long)]
-    rainbow_path: PathBuf,
+    max_char: Option<NonZeroUsize>,
+
+    /// Fewest words in a PRINCE chain
+    #[clap(long, default_value = "1")]
+    min_elements: NonZeroUsize,
+
+    /// Most words in a PRINCE chain
+    #[clap(long, default_value = "8")]
+    max_elements: NonZeroUsize,
+
+    /// Markov model (from train-markov) for the Markov attack
+    #[clap(long, required_if_eq("attack", "markov"))]
+    markov: Option<PathBuf>,
+
+    /// PCFG grammar (from train-pcfg) for the PCFG attack
+    #[clap(long, required_if_eq("attack", "pcfg"))]
+    pcfg: Option<PathBuf>,
+
+    /// Lowest probability a Markov or PCFG candidate can have
+    #[clap(long, default_value = "0")]
+    threshold: f64,
+
+    /// Most candidates the Markov or PCFG attack tries, all of them above the threshold if not given
+    #[clap(long)]
+    limit: Option<NonZeroUsize>,
+
+    /// Rule file applied to every dictionary, PRINCE, Markov, PCFG or association word, or best64 for the built in set
+    #[clap(long)]
+    rules: Option<String>,
+
+    /// Potfile cracked hashes are read from and appended to
+    #[clap(long, default_value = DEFAULT_POTFILE)]
+    potfile: PathBuf,
+
+    /// Neither read nor write the potfile
+    #[clap(long)]
+    no_potfile: bool,
+
+    /// Format of the target hashes in in_path
+    #[clap(long, value_enum, default_value = "binary")]
+    target_format: TargetFormat,
+
+    /// Unicode normalization the targets were hashed with: none, nfc or nfkc
+    #[clap(long, default_value = "none")]
+    normalization: Normalization,
+
+    /// Byte encoding the targets were hashed in: utf8, utf16le or latin1
+    #[clap(long, default_value = "utf8")]
+    encoding: Encoding,
+
+    /// Name of a session checkpointed to <name>.session, so the attack can be restored later
+    ///
+    /// The rainbow attack checkpoints the targets it has not looked up yet.
+    #[clap(long)]
+    session: Option<String>,
+
+    /// Resume a stopped session, with the options it was started with
+    #[clap(long, conflicts_with("session"))]
+    restore: Option<String>,
+
+    /// How rainbow table lookups are written: text or json
+    #[clap(long, value_enum, default_value = "text")]
+    outcome_format: OutcomeFormat,
+
+    #[clap(flatten)]
+    progress: ProgressOpts,
+}
+
+/// Parses the options a session was started with
+#[derive(Parser, Debug)]
+struct RestoredOpts {
+    #[clap(flatten)]
+    opts: CrackOpts,
+}
+
+impl CrackOpts {
+    /// Hashing algorithm for attacks that do not read it from a table
+    fn algorithm(&self) -> String {
+        self.algorithm
+            .clone()
+            .unwrap_or_else(|| DEFAULT_ALGORITHM.to_string())
+    }
+}
+
+/// File a named session is checkpointed to
+fn session_path(name: &str) -> PathBuf {
+    PathBuf::from(format!("{name}.session"))
 }
 /// Output destination
 enum Writer {
@@ -50,42 +252,561 @@ enum Writer {
     Stdout(Stdout),
 }
 
-pub(crate) fn do_cracks(opts: CrackOpts) -> Result<()> {
-    let mut rainbow_table = HashMap::new();
-    let rainbow_file = File::open(opts.rainbow_path)?;
-    let reader = BufReader::new(rainbow_file);
-    for line in reader.lines() {
-        match line {
-            Ok(line) => {
-                if let Some(tab_index) = line.find('\t') {
-                    let (before_tab, after_tab) = line.split_at(tab_index);
-                    let after_tab = &after_tab[1..];
-                    rainbow_table.insert(after_tab.to_string(), before_tab.to_string());
-                } else {
-                    panic!("No tab character found in line: {}", line);
+impl Write for Writer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Writer::File(file) => file.write(buf),
+            Writer::Stdout(stdout) => stdout.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Writer::File(file) => file.flush(),
+            Writer::Stdout(stdout) => stdout.flush(),
+        }
+    }
+}
+
+/// A target and the password that cracked it
+struct Cracked {
+    /// User the target belongs to, for user:hash targets
+    user: Option<String>,
+    target: Target,
+    password: String,
+}
+
+/// Where cracks go: the output and the potfile
+struct Output {
+    writer: Writer,
+    potfile: Option<Potfile>,
+}
+
+impl Output {
+    /// Writes a crack as `[user:]hash[:salt]<TAB>password` and appends it to the potfile
+    fn record(&mut self, cracked: &Cracked) -> Result<()> {
+        if let Some(user) = &cracked.user {
+            write!(self.writer, "{user}:")?;
+        }
+        writeln!(self.writer, "{}\t{}", cracked.target, cracked.password)?;
+        if let Some(potfile) = &self.potfile {
+            potfile.append(&cracked.target, &cracked.password)?;
+        }
+        Ok(())
+    }
+
+    /// Writes a rainbow table lookup with `writer`, appending cracks to the potfile
+    ///
+    /// Returns whether the target was cracked.
+    fn record_outcome(
+        &mut self,
+        target: &Target,
+        outcome: &CrackOutcome,
+        writer: &dyn OutcomeWriter,
+    ) -> Result<bool> {
+        write!(self.writer, "{}", writer.format(&target.hash, outcome))?;
+        let CrackOutcome::Found { password, .. } = outcome else {
+            return Ok(false);
+        };
+        if let Some(potfile) = &self.potfile {
+            potfile.append(target, password)?;
+        }
+        Ok(true)
+    }
+
+    /// Records every crack until all workers hang up
+    fn spawn_printer(mut self, rx_printer: Receiver<Cracked>) -> JoinHandle<Result<()>> {
+        thread::spawn(move || -> Result<()> {
+            while let Ok(cracked) = rx_printer.recv() {
+                self.record(&cracked)?;
+            }
+            self.writer.flush()?;
+            debug!("Printer thread done");
+            Ok(())
+        })
+    }
+}
+
+/// Stops an attack on Ctrl-C and checkpoints how far it got
+///
+/// Positions count what the attack hands out in order: words for dictionary
+/// and PRINCE, keyspace indices for mask, combinator and hybrid, and users for
+/// association. The rainbow attack has no order worth keeping, so it only
+/// checkpoints the targets it has not looked up yet.
+struct Control {
+    /// Set on Ctrl-C, threads stop taking new work once it is
+    stop: Arc<AtomicBool>,
+    /// Position a restored session starts from
+    start: u128,
+    /// Session name, None when the run is not checkpointed
+    session: Option<String>,
+    /// Arguments the session was started with
+    args: Vec<String>,
+    watermark: Mutex<Watermark>,
+    last_saved: Mutex<Instant>,
+}
+
+impl Control {
+    fn new(session: Option<String>, args: Vec<String>, start: u128) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        // The first Ctrl-C lets work in progress finish, the second quits straight away
+        let handler_stop = Arc::clone(&stop);
+        tokio::spawn(async move {
+            while tokio::signal::ctrl_c().await.is_ok() {
+                if handler_stop.swap(true, Ordering::Relaxed) {
+                    std::process::exit(130);
                 }
+                info!("Stopping, press Ctrl-C again to quit without saving");
             }
-            Err(_err) => {
-                panic!("Error reading line");
+        });
+        Control {
+            stop,
+            start,
+            session,
+            args,
+            watermark: Mutex::new(Watermark::new(start)),
+            last_saved: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Whether Ctrl-C has been pressed
+    fn stopped(&self) -> bool {
+        self.stop.load(Ordering::Relaxed)
+    }
+
+    /// Marks a range of positions as fully tried
+    fn complete(&self, range: Range<u128>) {
+        self.watermark
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .complete(range);
+    }
+
+    /// Writes the session file, with the targets left if the attack tracks them
+    fn save(&self, pending: Option<&dyn Pending>) -> Result<()> {
+        let Some(name) = &self.session else {
+            return Ok(());
+        };
+        let session = Session {
+            args: self.args.clone(),
+            position: self
+                .watermark
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .position(),
+            remaining: pending.map(Pending::pending),
+        };
+        session.save(&session_path(name))?;
+        *self
+            .last_saved
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = Instant::now();
+        debug!("Checkpointed session {name} at {}", session.position);
+        Ok(())
+    }
+
+    /// Saves the session if the last checkpoint is old enough
+    fn tick(&self, pending: Option<&dyn Pending>) -> Result<()> {
+        let due = self
+            .last_saved
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .elapsed()
+            >= CHECKPOINT_INTERVAL;
+        if due {
+            self.save(pending)?;
+        }
+        Ok(())
+    }
+
+    /// Keeps the checkpoint of a stopped attack and removes it once the attack has run to the end
+    fn finish(&self, pending: Option<&dyn Pending>) -> Result<()> {
+        let finished = !self.stopped() || pending.is_some_and(Pending::is_done);
+        match (&self.session, finished) {
+            (Some(name), true) => match fs::remove_file(session_path(name)) {
+                Err(error) if error.kind() != ErrorKind::NotFound => return Err(error.into()),
+                _ => {}
+            },
+            (Some(name), false) => {
+                self.save(pending)?;
+                info!("Saved session, resume it with --restore {name}");
             }
+            (None, false) => warn!("Stopped before the attack finished"),
+            (None, true) => {}
         }
+        Ok(())
+    }
+}
+
+/// Targets an attack has not finished with, checkpointed so a restore skips the rest
+trait Pending {
+    /// Targets left to try
+    fn pending(&self) -> Vec<Target>;
+    /// Whether nothing is left
+    fn is_done(&self) -> bool;
+}
+
+impl Pending for TargetSet {
+    fn pending(&self) -> Vec<Target> {
+        self.remaining_targets()
     }
 
-    let mut input_file = File::open(opts.in_path)?;
-    let mut first_line = Vec::new();
-    let _ = input_file.read_to_end(&mut first_line);
-    //let mut first_line = [0_u8; 16];
-    //first_line[..16].copy_from_slice(&&first_line_step[..16]);
-    println!("{:?}", first_line);
-    let mut output_file: Writer = match opts.out_path {
+    fn is_done(&self) -> bool {
+        TargetSet::is_done(self)
+    }
+}
+
+/// Targets the rainbow attack has not looked up yet
+struct Unlooked(Mutex<HashSet<Target>>);
+
+impl Unlooked {
+    /// Marks a target as looked up, cracked or not
+    fn remove(&self, target: &Target) {
+        self.0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(target);
+    }
+}
+
+impl Pending for Unlooked {
+    fn pending(&self) -> Vec<Target> {
+        let unlooked = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        unlooked.iter().cloned().collect()
+    }
+
+    fn is_done(&self) -> bool {
+        self.0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .is_empty()
+    }
+}
+
+/// Drops targets the potfile already has a password for
+fn skip_cracked<T>(
+    items: Vec<T>,
+    potfile: Option<&Potfile>,
+    target: impl Fn(&T) -> &Target,
+) -> Vec<T> {
+    let Some(potfile) = potfile else {
+        return items;
+    };
+    let before = items.len();
+    let items: Vec<T> = items
+        .into_iter()
+        .filter(|item| !potfile.contains(target(item)))
+        .collect();
+    if items.len() < before {
+        info!(
+            "Skipping {} targets already in the potfile",
+            before - items.len()
+        );
+    }
+    items
+}
+
+/// Reads a rainbow table, with the parameters its header records
+///
+/// Parameters given on the command line must match the header. Tables from
+/// before headers existed are read with the command line parameters instead.
+pub(crate) fn load_rainbow_table(
+    path: &Path,
+    num_links: Option<NonZeroUsize>,
+    password_length: Option<NonZeroUsize>,
+    algorithm: Option<&str>,
+) -> Result<Crack> {
+    let table = TableFile::open(path)?;
+    info!("Loaded {} rainbow table chains", table.chains.len());
+    let Some(header) = &table.header else {
+        warn!(
+            "{} has no header, cracking with the command line parameters",
+            path.display()
+        );
+        return Ok(Crack::new(
+            num_links.unwrap_or(DEFAULT_NUM_LINKS),
+            password_length.unwrap_or(DEFAULT_PASSWORD_LENGTH),
+            algorithm.unwrap_or(DEFAULT_ALGORITHM).to_string(),
+            table.chains,
+        )?
+        .with_legacy_reduction()?);
+    };
+
+    let mismatches = [
+        (
+            "--num-links",
+            num_links.map(|num_links| num_links.to_string()),
+            header.chain_length.to_string(),
+        ),
+        (
+            "--password-length",
+            password_length.map(|password_length| password_length.to_string()),
+            header.password_length.to_string(),
+        ),
+        (
+            "--algorithm",
+            algorithm.map(str::to_string),
+            header.algorithm.clone(),
+        ),
+    ];
+    for (flag, given, built) in mismatches {
+        if let Some(given) = given.filter(|given| *given != built) {
+            bail!(
+                "{} was built with {flag} {built}, not {given}",
+                path.display()
+            );
+        }
+    }
+    debug!("Rainbow table header {header:?}");
+    Ok(Crack::from_header(header, table.chains)?)
+}
+
+/// Reads the hashes to crack in the chosen format
+pub(crate) fn load_targets(path: &Path, format: TargetFormat) -> Result<Vec<Target>> {
+    let mut input_file = File::open(path)?;
+    let targets = match format {
+        TargetFormat::Binary => {
+            let mut bytes = Vec::new();
+            input_file.read_to_end(&mut bytes)?;
+            Target::from_binary(&bytes)?
+        }
+        TargetFormat::Hex => Target::from_hex_lines(BufReader::new(input_file))?,
+        TargetFormat::User => Target::from_user_lines(BufReader::new(input_file))?
+            .into_iter()
+            .map(|(_, target)| target)
+            .collect(),
+    };
+    info!("Loaded {} targets", targets.len());
+    Ok(targets)
+}
+
+pub(crate) fn do_cracks(opts: CrackOpts) -> Result<()> {
+    // A restored session runs with the arguments it was started with
+    let (opts, args, restored) = match &opts.restore {
+        Some(name) => {
+            let session = Session::load(&session_path(name))?;
+            info!(
+                "Restoring session {name} from position {}",
+                session.position
+            );
+            let args = session.args.clone();
+            let restored = RestoredOpts::try_parse_from(
+                std::iter::once("crack".to_string()).chain(args.clone()),
+            )?;
+            (restored.opts, args, Some(session))
+        }
+        None => (opts, std::env::args().skip(2).collect(), None),
+    };
+    let Some(in_path) = opts.in_path.clone() else {
+        bail!("--in-path is required");
+    };
+
+    let output_file: Writer = match &opts.out_path {
+        // A restored session adds to the cracks it already wrote
+        Some(out_path) if restored.is_some() => Writer::File(
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(out_path)?,
+        ),
         Some(out_path) => {
             let file = File::create(out_path)?;
             Writer::File(file)
         }
         None => Writer::Stdout(io::stdout()),
     };
+    let control = Arc::new(Control::new(
+        opts.session.clone(),
+        args,
+        restored.as_ref().map_or(0, |session| session.position),
+    ));
+    let potfile = match opts.no_potfile {
+        true => None,
+        false => Some(Potfile::open(&opts.potfile)?),
+    };
+
+    // Association needs to know whose hash is whose, every other attack only needs the hashes
+    if opts.attack == Attack::Association {
+        if opts.target_format != TargetFormat::User {
+            bail!("The association attack needs --target-format user");
+        }
+        let users = Target::from_user_lines(BufReader::new(File::open(&in_path)?))?;
+        info!("Loaded {} user targets", users.len());
+        // Positions count users in the file, before any are skipped
+        let users: Vec<_> = (0..)
+            .zip(users)
+            .skip(usize::try_from(control.start).unwrap_or(usize::MAX))
+            .map(|(position, (user, target))| (position, user, target))
+            .collect();
+        let total = control.start + users.len() as u128;
+        let users = skip_cracked(users, potfile.as_ref(), |(_, _, target)| target);
+        // Users the potfile already has count as tried, so the checkpoint can move past them
+        let kept: HashSet<u128> = users.iter().map(|(position, _, _)| *position).collect();
+        for position in (control.start..total).filter(|position| !kept.contains(position)) {
+            control.complete(position..position + 1);
+        }
+        let output = Output {
+            writer: output_file,
+            potfile,
+        };
+        return association_attack(opts, users, output, control);
+    }
+    let mut targets = load_targets(&in_path, opts.target_format)?;
+    if let Some(remaining) = restored.and_then(|session| session.remaining) {
+        let remaining: HashSet<Target> = remaining.into_iter().collect();
+        targets.retain(|target| remaining.contains(target));
+        info!("{} targets left from the session", targets.len());
+    }
+    let targets = skip_cracked(targets, potfile.as_ref(), |target| target);
+    let output_file = Output {
+        writer: output_file,
+        potfile,
+    };
+
+    match opts.attack {
+        Attack::Rainbow => rainbow_attack(opts, targets, output_file, control),
+        Attack::Association => unreachable!("association is handled above"),
+        Attack::Dictionary => {
+            let Some(wordlist) = &opts.wordlist else {
+                bail!("--wordlist is required for the dictionary attack");
+            };
+            let reader: Box<dyn BufRead> = if wordlist.as_os_str() == "-" {
+                Box::new(io::stdin().lock())
+            } else {
+                Box::new(BufReader::new(File::open(wordlist)?))
+            };
+            dictionary_attack(
+                opts,
+                targets,
+                Box::new(wordlist_lines(reader)),
+                output_file,
+                control,
+            )
+        }
+        Attack::Prince => {
+            let Some(wordlist) = &opts.wordlist else {
+                bail!("--wordlist is required for the prince attack");
+            };
+            let prince = Arc::new(Prince::new(
+                read_wordlist(wordlist)?,
+                prince_options(
+                    opts.min_char,
+                    opts.max_char,
+                    opts.min_elements,
+                    opts.max_elements,
+                )?,
+            ));
+            dictionary_attack(
+                opts,
+                targets,
+                Box::new(prince.candidates().map(Ok)),
+                output_file,
+                control,
+            )
+        }
+        Attack::Markov => {
+            let Some(markov) = &opts.markov else {
+                bail!("--markov is required for the markov attack");
+            };
+            let model = Arc::new(MarkovModel::load(BufReader::new(File::open(markov)?))?);
+            let options = MarkovOptions {
+                min_len: opts.min_char.map_or(1, usize::from),
+                max_len: opts.max_char.map_or(model.max_len(), usize::from),
+                threshold: opts.threshold,
+            };
+            let limit = opts.limit.map_or(usize::MAX, usize::from);
+            dictionary_attack(
+                opts,
+                targets,
+                Box::new(model.ordered(options).take(limit).map(Ok)),
+                output_file,
+                control,
+            )
+        }
+        Attack::Pcfg => {
+            let Some(pcfg) = &opts.pcfg else {
+                bail!("--pcfg is required for the pcfg attack");
+            };
+            let grammar = Arc::new(Pcfg::load(BufReader::new(File::open(pcfg)?))?);
+            let options = PcfgOptions {
+                min_len: opts.min_char.map_or(1, usize::from),
+                max_len: opts.max_char.map_or(usize::MAX, usize::from),
+                threshold: opts.threshold,
+            };
+            let limit = opts.limit.map_or(usize::MAX, usize::from);
+            dictionary_attack(
+                opts,
+                targets,
+                Box::new(grammar.guesses(options).take(limit).map(Ok)),
+                output_file,
+                control,
+            )
+        }
+        Attack::Mask => {
+            let Some(mask) = &opts.mask else {
+                bail!("--mask is required for the mask attack");
+            };
+            let mask = parse_mask(mask, &opts.custom_charset)?;
+            if mask.size().bits() > 128 {
+                warn!(
+                    "{mask} has {} candidates, only the first 2^128 are tried",
+                    mask.size()
+                );
+            }
+            keyspace_attack(opts, targets, Arc::new(mask), output_file, control)
+        }
+        Attack::Combinator | Attack::Hybrid => {
+            let Some(wordlist) = &opts.wordlist else {
+                bail!("--wordlist is required for the {:?} attack", opts.attack);
+            };
+            let words = read_wordlist(wordlist)?;
+            let source: Arc<dyn Keyspace> =
+                match (&opts.right_wordlist, &opts.mask) {
+                    (Some(right), _) if opts.attack == Attack::Combinator => Arc::new(
+                        Combinator::new(words, read_wordlist(right)?, opts.separator.clone()),
+                    ),
+                    (_, Some(mask)) if opts.attack == Attack::Hybrid => Arc::new(Hybrid::new(
+                        words,
+                        parse_mask(mask, &opts.custom_charset)?,
+                        opts.mask_position,
+                    )),
+                    _ => bail!(
+                        "--right-wordlist or --mask is missing for the {:?} attack",
+                        opts.attack
+                    ),
+                };
+            keyspace_attack(opts, targets, source, output_file, control)
+        }
+    }
+}
+
+/// Builds the set of targets candidates are checked against
+fn target_set(opts: &CrackOpts, targets: Vec<Target>) -> Arc<TargetSet> {
+    Arc::new(
+        TargetSet::new(targets, opts.algorithm())
+            .with_normalization(opts.normalization)
+            .with_encoding(opts.encoding),
+    )
+}
+
+/// Looks every target up in a rainbow table
+fn rainbow_attack(
+    opts: CrackOpts,
+    targets: Vec<Target>,
+    output_file: Output,
+    control: Arc<Control>,
+) -> Result<()> {
+    trace!("Rainbow attack");
+    let Some(rainbow_path) = &opts.rainbow_path else {
+        bail!("--rainbow-path is required for the rainbow attack");
+    };
+    let cracker = Arc::new(load_rainbow_table(
+        rainbow_path,
+        opts.num_links,
+        opts.password_length,
+        opts.algorithm.as_deref(),
+    )?);
 
-    let reader = BufReader::new(input_file);
     let (tx_main, rx_main) = crossbeam_channel::unbounded();
     // used to print
     // multi producers (password_gen), single consumer (printer)
@@ -93,18 +814,14 @@ pub(crate) fn do_cracks(opts: CrackOpts) -> Result<()> {
 
     let mut threads = vec![];
 
-    // let mut first_line = String::new();
-    for number in reader.lines() {
-        let _number = number?;
-    }
-    let num_to_gen = first_line.len() / 16;
-    // Main thread loops 0..num_to_gen, sending to generator threads
-    // let i = 0;firs
-    for i in 0..num_to_gen {
-        let chunk = &first_line[i * 16..(i + 1) * 16];
-        //let hashed_bytes = <[u8; 16]>::from_hex(chunk)?;
-        let hashed_bytes: [u8; 16] = chunk.try_into()?;
-        let _ = tx_main.send(hashed_bytes);
+    let progress = Progress::new("targets", Some(targets.len() as u64));
+    progress.set_targets(targets.len() as u64);
+    let reporter = Reporter::start(&progress, &opts.progress);
+    let unlooked = Arc::new(Unlooked(Mutex::new(targets.iter().cloned().collect())));
+
+    // Main thread sends every target to the cracking threads
+    for target in targets {
+        let _ = tx_main.send(target);
     }
     debug!("Main thread done");
     drop(tx_main);
@@ -112,23 +829,16 @@ pub(crate) fn do_cracks(opts: CrackOpts) -> Result<()> {
     for thread_number in 0..opts.threads.into() {
         let rx_main = rx_main.clone();
         let tx_printer = tx_printer.clone();
-        let algorithm = opts.algorithm.clone();
-        let rainbow_table_clone = rainbow_table.clone();
+        let cracker = Arc::clone(&cracker);
+        let control = Arc::clone(&control);
         let thread = thread::spawn(move || -> Result<()> {
             // loop while main thread is still sending
-            let cracker = Crack::new(
-                opts.num_links,
-                opts.password_length,
-                algorithm,
-                rainbow_table_clone,
-            );
-            while let Ok(password) = rx_main.recv() {
-                let hashed = match cracker.crack(&password) {
-                    Ok(password) => password,
-                    Err(error) => error.to_string(),
-                };
-
-                tx_printer.send(hashed)?;
+            while let Ok(target) = rx_main.recv() {
+                if control.stopped() {
+                    break;
+                }
+                let outcome = cracker.crack(&target.hash);
+                tx_printer.send((target, outcome))?;
             }
             Ok(())
         });
@@ -143,24 +853,20 @@ pub(crate) fn do_cracks(opts: CrackOpts) -> Result<()> {
     drop(rx_main);
     drop(tx_printer);
 
+    let mut output_file = output_file;
+    let writer = opts.outcome_format.writer();
+    let printer_progress = Arc::clone(&progress);
+    let printer_unlooked = Arc::clone(&unlooked);
+    let printer_control = Arc::clone(&control);
     let printer_thread = thread::spawn(move || -> Result<()> {
-        while let Ok(password_result) = rx_printer.recv() {
-            match output_file {
-                Writer::File(ref mut to_write) => {
-                    //write!(to_write, "{:?}", &password);
-                    //for element in &password {
-                    //    write!(to_write, "{}", element)?
-                    //}
-                    write!(to_write, "{}", password_result)?;
-                }
-
-                Writer::Stdout(ref mut to_write) => {
-                    // for element in &password {
-                    //     write!(to_write, "{}", element)?
-                    // }
-                    write!(to_write, "{}", password_result)?;
-                }
-            };
+        while let Ok((target, outcome)) = rx_printer.recv() {
+            if output_file.record_outcome(&target, &outcome, writer.as_ref())? {
+                printer_progress.add_cracked(1);
+            }
+            printer_progress.add(1);
+            // Only once the outcome is written, so a checkpoint never loses a lookup
+            printer_unlooked.remove(&target);
+            printer_control.tick(Some(&*printer_unlooked))?;
         }
 
         drop(rx_printer);
@@ -179,10 +885,340 @@ pub(crate) fn do_cracks(opts: CrackOpts) -> Result<()> {
     }
 
     debug!("All threads joined");
+    reporter.finish();
+    info!("{} false alarms skipped", cracker.false_alarms());
+
+    control.finish(Some(&*unlooked))
+}
+
+/// Hashes every word, mangled by each rule, and checks it against all targets at once
+///
+/// Works for any algorithm HashGenerator supports, salted or not, since the
+/// candidates are hashed rather than looked up.
+fn dictionary_attack(
+    opts: CrackOpts,
+    targets: Vec<Target>,
+    words: Box<dyn Iterator<Item = Result<String>>>,
+    output_file: Output,
+    control: Arc<Control>,
+) -> Result<()> {
+    trace!("Dictionary attack");
+    let rules = Arc::new(match &opts.rules {
+        Some(rules) => load_rules(rules)?,
+        None => RuleSet::noop(),
+    });
+    let target_set = target_set(&opts, targets);
+    // The number of words is not known until the wordlist has been read
+    let progress = Progress::new("candidates", None);
+    progress.set_targets(target_set.len() as u64);
+    let reporter = Reporter::start(&progress, &opts.progress);
+
+    // Bounded so a huge wordlist is streamed instead of read into memory
+    // Batches carry the position of their first word, so finished batches can be checkpointed
+    let (tx_main, rx_main) =
+        crossbeam_channel::bounded::<(u128, Vec<String>)>(opts.threads.get() * 4);
+    // multi producers (hashers), single consumer (printer)
+    let (tx_printer, rx_printer) = std::sync::mpsc::channel();
+
+    let mut threads = vec![];
+
+    for thread_number in 0..opts.threads.into() {
+        let rx_main = rx_main.clone();
+        let tx_printer = tx_printer.clone();
+        let target_set = Arc::clone(&target_set);
+        let rules = Arc::clone(&rules);
+        let control = Arc::clone(&control);
+        let progress = Arc::clone(&progress);
+        let thread = thread::spawn(move || -> Result<()> {
+            'batches: while let Ok((start, batch)) = rx_main.recv() {
+                let len = batch.len() as u128;
+                for word in batch {
+                    // A batch cut short is not checkpointed, so it is tried again on restore
+                    if control.stopped() {
+                        continue 'batches;
+                    }
+                    let mut tried = 0;
+                    for candidate in rules.apply(&word) {
+                        tried += 1;
+                        for target in target_set.check(&candidate)? {
+                            progress.add_cracked(1);
+                            tx_printer.send(Cracked {
+                                user: None,
+                                target,
+                                password: candidate.clone(),
+                            })?;
+                        }
+                    }
+                    progress.add(tried);
+                }
+                control.complete(start..start + len);
+            }
+            debug!("Thread {thread_number} done");
+            Ok(())
+        });
+
+        threads.push(thread);
+    }
+
+    drop(rx_main);
+    drop(tx_printer);
+
+    let printer_thread = output_file.spawn_printer(rx_printer);
+
+    // Main thread streams the words in batches until they run out, everything is cracked or Ctrl-C
+    let mut position = control.start;
+    let mut batch = Vec::with_capacity(BATCH_SIZE);
+    for word in words.skip(usize::try_from(control.start).unwrap_or(usize::MAX)) {
+        batch.push(word?);
+        if batch.len() == BATCH_SIZE {
+            if target_set.is_done() || control.stopped() {
+                batch.clear();
+                break;
+            }
+            let len = batch.len() as u128;
+            // Workers only stop early on error, which the join below reports
+            if tx_main
+                .send((position, std::mem::take(&mut batch)))
+                .is_err()
+            {
+                break;
+            }
+            position += len;
+            control.tick(Some(&*target_set))?;
+        }
+    }
+    if !batch.is_empty() && !control.stopped() {
+        let _ = tx_main.send((position, batch));
+    }
+    drop(tx_main);
+    debug!("Main thread done");
+
+    threads.push(printer_thread);
+
+    for thread in threads {
+        thread
+            .join()
+            .map_err(|_e| anyhow!("Failed to join a thread"))??;
+    }
+    reporter.finish();
+
+    info!(
+        "Cracked {} of {} targets",
+        target_set.len() - target_set.remaining(),
+        target_set.len()
+    );
+    if target_set.unencodable() > 0 {
+        info!(
+            "Skipped {} candidates the encoding cannot represent",
+            target_set.unencodable()
+        );
+    }
+
+    control.finish(Some(&*target_set))
+}
+
+/// Hashes every candidate of an indexed keyspace and checks it against all targets at once
+///
+/// The main thread hands out index ranges, so threads share the work evenly
+/// whatever the candidates cost to make.
+fn keyspace_attack(
+    opts: CrackOpts,
+    targets: Vec<Target>,
+    source: Arc<dyn Keyspace>,
+    output_file: Output,
+    control: Arc<Control>,
+) -> Result<()> {
+    trace!("Keyspace attack");
+    let target_set = target_set(&opts, targets);
+    let total = source.keyspace();
+    info!("Keyspace is {total} candidates");
+    // Keyspaces past u64 show as unknown rather than wrapping to a wrong total
+    let progress = Progress::new("candidates", u64::try_from(total).ok());
+    progress.resume(u64::try_from(control.start).unwrap_or(u64::MAX));
+    progress.set_targets(target_set.len() as u64);
+    let reporter = Reporter::start(&progress, &opts.progress);
+
+    let (tx_main, rx_main) = crossbeam_channel::bounded::<Range<u128>>(opts.threads.get() * 4);
+    // multi producers (hashers), single consumer (printer)
+    let (tx_printer, rx_printer) = std::sync::mpsc::channel();
+
+    let mut threads = vec![];
+
+    for thread_number in 0..opts.threads.into() {
+        let rx_main = rx_main.clone();
+        let tx_printer = tx_printer.clone();
+        let target_set = Arc::clone(&target_set);
+        let source = Arc::clone(&source);
+        let progress = Arc::clone(&progress);
+        let control = Arc::clone(&control);
+        let thread = thread::spawn(move || -> Result<()> {
+            'ranges: while let Ok(range) = rx_main.recv() {
+                // Ranges already queued are dropped once there is nothing left to crack
+                if target_set.is_done() {
+                    continue;
+                }
+                let len = (range.end - range.start) as u64;
+                for candidate in source.candidates(range.clone()) {
+                    // A range cut short is not checkpointed, so it is tried again on restore
+                    if control.stopped() {
+                        continue 'ranges;
+                    }
+                    for target in target_set.check(&candidate)? {
+                        progress.add_cracked(1);
+                        tx_printer.send(Cracked {
+                            user: None,
+                            target,
+                            password: candidate.clone(),
+                        })?;
+                    }
+                }
+                // Counted a whole range at a time
+                progress.add(len);
+                control.complete(range);
+            }
+            debug!("Thread {thread_number} done");
+            Ok(())
+        });
+
+        threads.push(thread);
+    }
+
+    drop(rx_main);
+    drop(tx_printer);
+
+    let printer_thread = output_file.spawn_printer(rx_printer);
+
+    // Main thread hands out index ranges until the keyspace runs out, everything is cracked or Ctrl-C
+    for range in keyspace::chunks(control.start..total, BATCH_SIZE as u128) {
+        if target_set.is_done() || control.stopped() || tx_main.send(range).is_err() {
+            break;
+        }
+        control.tick(Some(&*target_set))?;
+    }
+    drop(tx_main);
+    debug!("Main thread done");
+
+    threads.push(printer_thread);
+
+    for thread in threads {
+        thread
+            .join()
+            .map_err(|_e| anyhow!("Failed to join a thread"))??;
+    }
+    reporter.finish();
+
+    info!(
+        "Cracked {} of {} targets",
+        target_set.len() - target_set.remaining(),
+        target_set.len()
+    );
+    if target_set.unencodable() > 0 {
+        info!(
+            "Skipped {} candidates the encoding cannot represent",
+            target_set.unencodable()
+        );
+    }
+
+    control.finish(Some(&*target_set))
+}
+
+/// Cracks each user's hash with candidates built from that user's own name
+///
+/// Every user is hashed with their own salt only, so this stays fast on salted
+/// and slow hashes where global attacks are not.
+fn association_attack(
+    opts: CrackOpts,
+    users: Vec<(u128, String, Target)>,
+    output_file: Output,
+    control: Arc<Control>,
+) -> Result<()> {
+    trace!("Association attack");
+    let rules = Arc::new(match &opts.rules {
+        Some(rules) => load_rules(rules)?,
+        None => RuleSet::noop(),
+    });
+
+    let (tx_main, rx_main) = crossbeam_channel::unbounded();
+    // multi producers (hashers), single consumer (printer)
+    let (tx_printer, rx_printer) = std::sync::mpsc::channel();
+
+    let mut threads = vec![];
+
+    let total = users.len();
+    let progress = Progress::new("users", Some(total as u64));
+    progress.set_targets(total as u64);
+    let reporter = Reporter::start(&progress, &opts.progress);
+    let unencodable = Arc::new(AtomicU64::new(0));
+
+    for user in users {
+        tx_main.send(user)?;
+    }
+    drop(tx_main);
+    debug!("Main thread done");
+
+    for thread_number in 0..opts.threads.into() {
+        let rx_main = rx_main.clone();
+        let tx_printer = tx_printer.clone();
+        let rules = Arc::clone(&rules);
+        let progress = Arc::clone(&progress);
+        let control = Arc::clone(&control);
+        let unencodable = Arc::clone(&unencodable);
+        let algorithm = opts.algorithm();
+        let (normalization, encoding) = (opts.normalization, opts.encoding);
+        let thread = thread::spawn(move || -> Result<()> {
+            'users: while let Ok((position, user, target)) = rx_main.recv() {
+                let target_set = TargetSet::new([target], algorithm.clone())
+                    .with_normalization(normalization)
+                    .with_encoding(encoding);
+                'candidates: for word in association::candidates(&user) {
+                    // A user cut short is not checkpointed, so it is tried again on restore
+                    if control.stopped() {
+                        continue 'users;
+                    }
+                    for candidate in rules.apply(&word) {
+                        if let Some(target) = target_set.check(&candidate)?.pop() {
+                            tx_printer.send(Cracked {
+                                user: Some(user.clone()),
+                                target,
+                                password: candidate,
+                            })?;
+                            progress.add_cracked(1);
+                            break 'candidates;
+                        }
+                    }
+                }
+                unencodable.fetch_add(target_set.unencodable(), Ordering::Relaxed);
+                progress.add(1);
+                control.complete(position..position + 1);
+                control.tick(None)?;
+            }
+            debug!("Thread {thread_number} done");
+            Ok(())
+        });
+
+        threads.push(thread);
+    }
+
+    drop(rx_main);
+    drop(tx_printer);
+
+    let printer_thread = output_file.spawn_printer(rx_printer);
+
+    threads.push(printer_thread);
+
+    for thread in threads {
+        thread
+            .join()
+            .map_err(|_e| anyhow!("Failed to join a thread"))??;
+    }
+
+    reporter.finish();
+
+    info!("Cracked {} of {total} users", progress.cracked());
+    let unencodable = unencodable.load(Ordering::Relaxed);
+    if unencodable > 0 {
+        info!("Skipped {unencodable} candidates the encoding cannot represent");
+    }
 
-    Ok(())
-    // create rainbow table struct (num_links, pass_len, algo)
-    // gen rainbow table
-    // main thread gives lines to worker threads
-    // worker thread gives line to printer thread
+    control.finish(None)
 }
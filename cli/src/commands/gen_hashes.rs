@@ -1,14 +1,20 @@
 use anyhow::{anyhow, Result};
 use clap::Args;
-use hashassin_core::HashGenerator;
+use hashassin_core::{
+    encoding::{Encoding, Normalization},
+    HashErrors, HashGenerator,
+};
 use std::{
     fs::File,
     io::{self, prelude::*, BufReader, Stdout, Write},
     num::NonZeroUsize,
     path::PathBuf,
+    sync::Arc,
     thread,
 };
-use tracing::{debug, info, trace};
+use tracing::{debug, info, trace, warn};
+
+use crate::progress::{Progress, ProgressOpts, Reporter};
 enum Writer {
     /// Write to file
     File(File),
@@ -38,6 +44,19 @@ pub(crate) struct HashesOpts {
 
     #[clap(long, default_value = "Md5")]
     pub algorithm: String,
+
+    /// This is the Unicode normalization applied before hashing: none, nfc or nfkc
+
+    #[clap(long, default_value = "none")]
+    pub normalization: Normalization,
+
+    /// This is the byte encoding passwords are hashed in: utf8, utf16le or latin1
+
+    #[clap(long, default_value = "utf8")]
+    pub encoding: Encoding,
+
+    #[clap(flatten)]
+    pub progress: ProgressOpts,
 }
 
 /// This function calls hash functions from lib.rs
@@ -61,10 +80,16 @@ pub(crate) fn gen_hashes(opts: HashesOpts) -> Result<()> {
 
     let mut threads = vec![];
 
+    let progress = Progress::new("hashes", None);
+    let reporter = Reporter::start(&progress, &opts.progress);
+
     // Main thread loops 0..num_to_gen, sending to generator threads
+    let mut total = 0;
     for passwords in reader.lines() {
         tx_main.send(passwords)?;
+        total += 1;
     }
+    progress.set_total(total);
     debug!("Main thread done");
     drop(tx_main);
 
@@ -75,13 +100,19 @@ pub(crate) fn gen_hashes(opts: HashesOpts) -> Result<()> {
         let thread = thread::spawn(move || -> Result<()> {
             // loop while main thread is still sending
             while let Ok(password) = rx_main.recv() {
-                let hash_generator = HashGenerator::new(password?, algorithm.clone());
+                let password = password?;
+                let hash_generator = HashGenerator::new(password.clone(), algorithm.clone())
+                    .with_normalization(opts.normalization)
+                    .with_encoding(opts.encoding);
                 let hashed_password = match hash_generator.hash_password() {
-                    Ok(password) => password,
-                    Err(error) => panic!("Error: {error}"),
+                    Ok(hashed_password) => hashed_password,
+                    // One password the encoding cannot represent is no reason to stop
+                    Err(HashErrors::Encoding(error)) => {
+                        warn!("Skipping {password:?}: {error}");
+                        continue;
+                    }
+                    Err(error) => return Err(error.into()),
                 };
-                print!("{:?}", hashed_password);
-
                 tx_printer.send(hashed_password)?;
             }
             Ok(())
@@ -97,8 +128,10 @@ pub(crate) fn gen_hashes(opts: HashesOpts) -> Result<()> {
     drop(rx_main);
     drop(tx_printer);
 
+    let printer_progress = Arc::clone(&progress);
     let printer_thread = thread::spawn(move || -> Result<()> {
         while let Ok(password_result) = rx_printer.recv() {
+            printer_progress.add(1);
             match output_file {
                 Writer::File(ref mut to_write) => {
                     //write!(to_write, "{:?}", &password);
@@ -109,11 +142,12 @@ pub(crate) fn gen_hashes(opts: HashesOpts) -> Result<()> {
                     to_write.write_all(&password_result)?;
                 }
 
-                Writer::Stdout(ref mut _to_write) => {
+                Writer::Stdout(ref mut to_write) => {
                     // for element in &password {
                     //     write!(to_write, "{}", element)?
                     // }
                     //write!(to_write, "{}", password_result)?;
+                    to_write.write_all(&password_result)?;
                 }
             };
         }
@@ -134,6 +168,7 @@ pub(crate) fn gen_hashes(opts: HashesOpts) -> Result<()> {
     }
 
     debug!("All threads joined");
+    reporter.finish();
 
     Ok(())
 }
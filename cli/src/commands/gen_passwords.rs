@@ -1,17 +1,48 @@
-use anyhow::{anyhow, Result};
-use clap::Args;
+use anyhow::{anyhow, bail, Result};
+use clap::{Args, ValueEnum};
 
-use hashassin_core::PasswordGeneratorBuilder;
+use hashassin_core::{
+    charset::{build_charset, CharRange, Script},
+    combinator::{Combinator, Hybrid},
+    keyboard::{KeyboardWalks, Layout, WalkOptions},
+    keyspace::{self, Keyspace},
+    markov::{MarkovModel, MarkovOptions},
+    mask::MaskPosition,
+    pcfg::{Pcfg, PcfgOptions},
+    prince::Prince,
+    pronounceable::{PronounceableGenerator, PronounceableGeneratorBuilder},
+    rules::RuleSet,
+    unique::UniqueFilter,
+    PasswordGenerator, PasswordGeneratorBuilder, PasswordPolicy,
+};
+use rand::thread_rng;
+
+use crate::candidates::{load_rules, parse_mask, prince_options, read_wordlist, wordlist_lines};
+use crate::progress::{Progress, ProgressOpts, Reporter};
 use std::{
     borrow::BorrowMut,
-    fs::File,
-    io::{self, Stdout, Write},
+    fs::{self, File},
+    io::{self, BufReader, Stdout, Write},
     num::NonZeroUsize,
     path::PathBuf,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
     thread,
 };
 use tracing::{debug, info, trace};
 
+/// Sources other than random generation, which ignore the policy and charset flags
+const OTHER_SOURCES: [&str; 6] = [
+    "markov",
+    "pcfg",
+    "pronounceable",
+    "keyboard_walk",
+    "wordlist",
+    "mask",
+];
+
 /// Options for password generation
 #[derive(Args, Debug)]
 pub(crate) struct PasswordsOpts {
@@ -34,6 +65,206 @@ pub(crate) struct PasswordsOpts {
     /// This is the number of passwords to generate
     #[clap(long)]
     num_to_gen: NonZeroUsize,
+
+    /// This is the minimum number of uppercase letters in each password
+    #[clap(long, default_value = "0", conflicts_with_all = OTHER_SOURCES)]
+    min_upper: usize,
+
+    /// This is the minimum number of lowercase letters in each password
+    #[clap(long, default_value = "0", conflicts_with_all = OTHER_SOURCES)]
+    min_lower: usize,
+
+    /// This is the minimum number of digits in each password
+    #[clap(long, default_value = "0", conflicts_with_all = OTHER_SOURCES)]
+    min_digit: usize,
+
+    /// This is the minimum number of symbols in each password
+    #[clap(long, default_value = "0", conflicts_with_all = OTHER_SOURCES)]
+    min_symbol: usize,
+
+    /// This is the longest run of the same character allowed in a password
+    #[clap(long, conflicts_with_all = OTHER_SOURCES)]
+    max_repeat: Option<NonZeroUsize>,
+
+    /// Leave out characters that are easy to confuse, like 0/O and l/1
+    #[clap(long, conflicts_with_all = OTHER_SOURCES)]
+    exclude_ambiguous: bool,
+
+    /// This is the path to a list of words (one per line) that passwords must not contain
+    #[clap(long, conflicts_with_all = OTHER_SOURCES)]
+    dictionary: Option<PathBuf>,
+
+    /// This is the path to a Markov model (from train-markov) to draw passwords from
+    #[clap(long, conflicts_with_all = ["pcfg", "pronounceable", "keyboard_walk", "wordlist"])]
+    markov: Option<PathBuf>,
+
+    /// This is how passwords are drawn from the Markov model
+    #[clap(long, value_enum, default_value = "ordered")]
+    markov_mode: MarkovMode,
+
+    /// This is the path to a PCFG grammar (from train-pcfg) to draw passwords from, most likely first
+    #[clap(long, conflicts_with_all = ["pronounceable", "keyboard_walk", "wordlist"])]
+    pcfg: Option<PathBuf>,
+
+    /// This is the lowest probability a Markov or PCFG password can have
+    #[clap(long, default_value = "0")]
+    threshold: f64,
+
+    /// Never output the same password twice, across all threads
+    #[clap(long)]
+    unique: bool,
+
+    /// These are the scripts to draw characters from: ascii, cyrillic, cjk, latin-accented
+    #[clap(long, value_delimiter = ',', conflicts_with_all = OTHER_SOURCES)]
+    script: Vec<Script>,
+
+    /// These are extra Unicode code point ranges to draw characters from, like 0400-04FF
+    #[clap(long, value_delimiter = ',', conflicts_with_all = OTHER_SOURCES)]
+    char_range: Vec<CharRange>,
+
+    /// Generate pronounceable passwords made of syllables
+    #[clap(long, conflicts_with_all = ["keyboard_walk", "wordlist"])]
+    pronounceable: bool,
+
+    /// Capitalize the first letter of pronounceable passwords
+    #[clap(long)]
+    capitalize: bool,
+
+    /// This is the number of digits appended to pronounceable passwords
+    #[clap(long, default_value = "0")]
+    digits: usize,
+
+    /// Output keyboard walks like qwerty and 1qaz2wsx
+    #[clap(long, conflicts_with = "wordlist")]
+    keyboard_walk: bool,
+
+    /// This is the keyboard layout walks are made on: qwerty, azerty or qwertz
+    #[clap(long, default_value = "qwerty")]
+    layout: Layout,
+
+    /// This is the number of keys in each stroke of a walk
+    #[clap(long, default_value = "4")]
+    walk_length: NonZeroUsize,
+
+    /// This is the most changes of direction allowed in each stroke
+    #[clap(long, default_value = "1")]
+    max_turns: usize,
+
+    /// This is the number of parallel strokes in a walk, 2 gives walks like 1qaz2wsx
+    #[clap(long, default_value = "1")]
+    strokes: NonZeroUsize,
+
+    /// Also output every walk typed with shift held down
+    #[clap(long)]
+    shifted: bool,
+
+    /// This is the path to a wordlist to output, mangled by --rules
+    #[clap(long)]
+    wordlist: Option<PathBuf>,
+
+    /// This is a rule file applied to every wordlist word, or best64 for the built in set
+    #[clap(long, requires = "wordlist", conflicts_with_all = ["right_wordlist", "mask"])]
+    rules: Option<String>,
+
+    /// This is a second wordlist, every wordlist word is joined to each of its words
+    #[clap(long, requires = "wordlist", conflicts_with = "mask")]
+    right_wordlist: Option<PathBuf>,
+
+    /// This is put between the two words of a combined password
+    #[clap(long, default_value = "")]
+    separator: String,
+
+    /// This is a mask like ?u?l?l?d, added to every wordlist word or used on its own
+    #[clap(long, conflicts_with_all = ["markov", "pcfg", "pronounceable", "keyboard_walk"])]
+    mask: Option<String>,
+
+    /// This is the side of each wordlist word the mask goes on: append or prepend
+    #[clap(long, default_value = "append")]
+    mask_position: MaskPosition,
+
+    /// This is a custom charset like ?l?d, used in masks as ?1, ?2, ... in the order given
+    #[clap(long)]
+    custom_charset: Vec<String>,
+
+    /// Chain wordlist words into passphrases with PRINCE, shortest first
+    #[clap(long, requires = "wordlist", conflicts_with_all = ["right_wordlist", "mask"])]
+    prince: bool,
+
+    /// This is the fewest words in a PRINCE chain
+    #[clap(long, default_value = "1")]
+    min_elements: NonZeroUsize,
+
+    /// This is the most words in a PRINCE chain
+    #[clap(long, default_value = "8")]
+    max_elements: NonZeroUsize,
+
+    #[clap(flatten)]
+    progress: ProgressOpts,
+}
+
+/// How passwords are drawn from a Markov model
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum MarkovMode {
+    /// Most likely first, without repeats
+    Ordered,
+    /// Randomly, following the model's probabilities
+    Sample,
+}
+
+/// Random draws that fail the threshold before giving up
+const MAX_SAMPLE_ATTEMPTS: usize = 1_000_000;
+
+/// Duplicates in a row before a unique run gives up on finding new passwords
+const MAX_DUPLICATE_ATTEMPTS: usize = 1_000_000;
+
+/// Number of lock shards per thread in the unique filter
+const SHARDS_PER_THREAD: usize = 16;
+
+/// Passwords queued between generating and printing before generating waits
+const CHANNEL_CAPACITY: usize = 4096;
+
+/// Where worker threads get their passwords from
+#[derive(Clone)]
+enum Source {
+    /// Random characters
+    Random(Box<PasswordGenerator>),
+    /// Random walks through a Markov model
+    MarkovSample(Arc<MarkovModel>, MarkovOptions),
+    /// Random syllables
+    Pronounceable(PronounceableGenerator),
+}
+
+impl Source {
+    /// Produces one password
+    fn generate(&self) -> Result<String> {
+        match self {
+            Source::Random(password_generator) => Ok(password_generator.generate()?),
+            Source::Pronounceable(password_generator) => Ok(password_generator.generate()),
+            Source::MarkovSample(model, options) => {
+                let mut rng = thread_rng();
+                for _ in 0..MAX_SAMPLE_ATTEMPTS {
+                    if let Some(password) = model.sample(&mut rng, options) {
+                        return Ok(password);
+                    }
+                }
+                bail!("No Markov password met the limits after {MAX_SAMPLE_ATTEMPTS} attempts")
+            }
+        }
+    }
+
+    /// Produces a password no thread has produced before
+    fn generate_unique(&self, seen: &UniqueFilter) -> Result<String> {
+        for _ in 0..MAX_DUPLICATE_ATTEMPTS {
+            let password = self.generate()?;
+            if seen.insert(&password) {
+                return Ok(password);
+            }
+        }
+        bail!(
+            "Only found {} unique passwords, the keyspace is likely exhausted",
+            seen.len()
+        )
+    }
 }
 
 /// Output destination
@@ -48,17 +279,16 @@ enum Writer {
 /// Will also distribute work as evenly as possible among threads
 pub(crate) fn do_passwords(opts: PasswordsOpts) -> Result<()> {
     trace!("Do passwords");
-    // Ensure that maximum possible length >= minimum possible length
-    let password_generator = PasswordGeneratorBuilder::default()
-        .with_min_char(opts.min_char)
-        .with_max_char(opts.max_char)
-        .build();
-
-    let password_generator = match password_generator {
-        Ok(password_generator) => password_generator,
-        Err(error) => panic!("Error: {error}"),
+    let model = match &opts.markov {
+        Some(path) => Some(Arc::new(MarkovModel::load(BufReader::new(File::open(
+            path,
+        )?))?)),
+        None => None,
+    };
+    let grammar = match &opts.pcfg {
+        Some(path) => Some(Arc::new(Pcfg::load(BufReader::new(File::open(path)?))?)),
+        None => None,
     };
-    let _password = password_generator.generate();
 
     // This is standard functionallity
     let mut out_file: Writer = match opts.out_path {
@@ -69,42 +299,140 @@ pub(crate) fn do_passwords(opts: PasswordsOpts) -> Result<()> {
         None => Writer::Stdout(io::stdout()),
     };
 
-    // (transmitter, receiver)
-    // single producer, multi consumer
-    let (tx_main, rx_main) = crossbeam_channel::unbounded();
-    // used to print
-    // multi producers (password_gen), single consumer (printer)
-    let (tx_printer, rx_printer) = std::sync::mpsc::channel();
-
-    let mut threads = vec![];
+    // Probability ordered passwords are one sequence, so the main thread walks them directly
+    let mut ordered: Option<Box<dyn Iterator<Item = Result<String>>>> = None;
+    // Indexed candidates are split into one even range per thread
+    let mut indexed: Option<Arc<dyn Keyspace>> = None;
+    let mut source = None;
+    if let Some(model) = model {
+        let options = MarkovOptions {
+            min_len: opts.min_char.map_or(1, usize::from),
+            max_len: opts.max_char.map_or(model.max_len(), usize::from),
+            threshold: opts.threshold,
+        };
+        match opts.markov_mode {
+            MarkovMode::Sample => source = Some(Source::MarkovSample(model, options)),
+            MarkovMode::Ordered => ordered = Some(Box::new(model.ordered(options).map(Ok))),
+        }
+    } else if let Some(grammar) = grammar {
+        let options = PcfgOptions {
+            min_len: opts.min_char.map_or(1, usize::from),
+            max_len: opts.max_char.map_or(usize::MAX, usize::from),
+            threshold: opts.threshold,
+        };
+        ordered = Some(Box::new(grammar.guesses(options).map(Ok)));
+    } else if let (Some(wordlist), Some(right_wordlist)) = (&opts.wordlist, &opts.right_wordlist) {
+        indexed = Some(Arc::new(Combinator::new(
+            read_wordlist(wordlist)?,
+            read_wordlist(right_wordlist)?,
+            opts.separator.clone(),
+        )));
+    } else if let Some(mask) = &opts.mask {
+        let mask = parse_mask(mask, &opts.custom_charset)?;
+        indexed = Some(match &opts.wordlist {
+            Some(wordlist) => Arc::new(Hybrid::new(
+                read_wordlist(wordlist)?,
+                mask,
+                opts.mask_position,
+            )),
+            None => Arc::new(mask),
+        });
+    } else if let Some(wordlist) = &opts.wordlist {
+        let rules = match &opts.rules {
+            Some(rules) => load_rules(rules)?,
+            None => RuleSet::noop(),
+        };
+        let words: Box<dyn Iterator<Item = Result<String>>> = if opts.prince {
+            let options = prince_options(
+                opts.min_char,
+                opts.max_char,
+                opts.min_elements,
+                opts.max_elements,
+            )?;
+            Box::new(
+                Arc::new(Prince::new(read_wordlist(wordlist)?, options))
+                    .candidates()
+                    .map(Ok),
+            )
+        } else {
+            Box::new(wordlist_lines(BufReader::new(File::open(wordlist)?)))
+        };
+        // A read error is passed through so it stops the run instead of ending the wordlist
+        ordered = Some(Box::new(words.flat_map(move |word| match word {
+            Ok(word) => rules.apply(&word).map(Ok).collect::<Vec<_>>(),
+            Err(error) => vec![Err(error)],
+        })));
+    } else if opts.keyboard_walk {
+        ordered = Some(Box::new(
+            KeyboardWalks::new(WalkOptions {
+                layout: opts.layout,
+                length: opts.walk_length,
+                max_turns: opts.max_turns,
+                strokes: opts.strokes,
+                shifted: opts.shifted,
+            })
+            .map(Ok),
+        ));
+    } else if opts.pronounceable {
+        let password_generator = PronounceableGeneratorBuilder::default()
+            .with_min_char(opts.min_char)
+            .with_max_char(opts.max_char)
+            .with_capitalize(opts.capitalize)
+            .with_digits(opts.digits)
+            .build()?;
+        source = Some(Source::Pronounceable(password_generator));
+    } else {
+        let banned_words = match &opts.dictionary {
+            Some(dictionary) => fs::read_to_string(dictionary)?
+                .lines()
+                .map(str::to_string)
+                .collect(),
+            None => Vec::new(),
+        };
+        let policy = PasswordPolicy {
+            min_upper: opts.min_upper,
+            min_lower: opts.min_lower,
+            min_digit: opts.min_digit,
+            min_symbol: opts.min_symbol,
+            max_repeat: opts.max_repeat,
+            exclude_ambiguous: opts.exclude_ambiguous,
+            banned_words,
+        };
 
-    // Main thread loops 0..num_to_gen, sending to generator threads
-    for password_number in 0..opts.num_to_gen.into() {
-        tx_main.send(password_number)?;
+        // Ensure that maximum possible length >= minimum possible length
+        let password_generator = PasswordGeneratorBuilder::default()
+            .with_min_char(opts.min_char)
+            .with_max_char(opts.max_char)
+            .with_policy(policy)
+            .with_charset(build_charset(&opts.script, &opts.char_range))
+            .build()?;
+        if opts.unique {
+            password_generator.check_unique(opts.num_to_gen.into())?;
+        }
+        source = Some(Source::Random(Box::new(password_generator)));
     }
-    drop(tx_main);
-    debug!("Main thread done");
 
-    for thread_number in 0..opts.threads.into() {
-        let rx_main = rx_main.clone();
-        let tx_printer = tx_printer.clone();
-        let thread = thread::spawn(move || -> Result<()> {
-            // loop while main thread is still sending
-            while let Ok(_password_number) = rx_main.recv() {
-                tx_printer.send(password_generator.generate())?;
-            }
-            Ok(())
-        });
+    // Wordlists, rules and models can all repeat, so every source remembers what it produced
+    let seen = opts
+        .unique
+        .then(|| Arc::new(UniqueFilter::new(opts.threads.get() * SHARDS_PER_THREAD)));
 
-        threads.push(thread);
-        debug!("Thread: {thread_number} done");
-    }
-    info!("Done generating passwords");
+    // Sources that run out early stop the count short of the total
+    let progress = Progress::new("passwords", Some(opts.num_to_gen.get() as u64));
+    let reporter = Reporter::start(&progress, &opts.progress);
 
-    drop(rx_main);
-    drop(tx_printer);
-    let printer_thread = thread::spawn(move || -> Result<()> {
+    // (transmitter, receiver)
+    // single producer, multi consumer
+    let (tx_main, rx_main) = crossbeam_channel::bounded(CHANNEL_CAPACITY);
+    // used to print, bounded so generating waits for the printer instead of piling up
+    // multi producers (password_gen), single consumer (printer)
+    let (tx_printer, rx_printer) = std::sync::mpsc::sync_channel::<String>(CHANNEL_CAPACITY);
+
+    // The printer is joined first so a failed write is reported over the senders it stopped
+    let printer_progress = Arc::clone(&progress);
+    let mut threads = vec![thread::spawn(move || -> Result<()> {
         while let Ok(password) = rx_printer.recv() {
+            printer_progress.add(1);
             match out_file.borrow_mut() {
                 Writer::File(to_write) => writeln!(to_write, "{password}")?,
                 Writer::Stdout(to_write) => writeln!(to_write, "{password}")?,
@@ -115,17 +443,122 @@ pub(crate) fn do_passwords(opts: PasswordsOpts) -> Result<()> {
         debug!("Printer thread done");
 
         Ok(())
-    });
+    })];
 
-    threads.push(printer_thread);
+    // Repeated words make repeated candidates, which are dropped rather than replaced
+    let duplicates = Arc::new(AtomicUsize::new(0));
+    let mut indexed_total = 0;
+    if let Some(indexed) = indexed {
+        let total = indexed.keyspace().min(opts.num_to_gen.get() as u128);
+        if total < opts.num_to_gen.get() as u128 {
+            info!("Keyspace ran out after {total} passwords");
+        }
+        indexed_total = total;
+        for range in keyspace::partition(total, opts.threads.get()) {
+            let indexed = Arc::clone(&indexed);
+            let tx_printer = tx_printer.clone();
+            let seen = seen.clone();
+            let duplicates = Arc::clone(&duplicates);
+            threads.push(thread::spawn(move || -> Result<()> {
+                for password in indexed.candidates(range) {
+                    if seen.as_ref().is_none_or(|seen| seen.insert(&password)) {
+                        tx_printer.send(password)?;
+                    } else {
+                        duplicates.fetch_add(1, Ordering::Relaxed);
+                    }
+                }
+                Ok(())
+            }));
+        }
+    }
+
+    if source.is_some() {
+        for thread_number in 0..opts.threads.into() {
+            let rx_main = rx_main.clone();
+            let tx_printer = tx_printer.clone();
+            let source = source.clone();
+            let seen = seen.clone();
+            let thread = thread::spawn(move || -> Result<()> {
+                // loop while main thread is still sending
+                while let Ok(_password_number) = rx_main.recv() {
+                    if let Some(source) = &source {
+                        let password = match &seen {
+                            Some(seen) => source.generate_unique(seen)?,
+                            None => source.generate()?,
+                        };
+                        tx_printer.send(password)?;
+                    }
+                }
+                Ok(())
+            });
+
+            threads.push(thread);
+            debug!("Thread: {thread_number} started");
+        }
+    }
+    drop(rx_main);
+
+    // A failed send means the printer or every worker stopped, their error comes out of join
+    // A read error stops generating, but is only returned once the printer has finished
+    let mut read_error = None;
+    if let Some(ordered) = ordered {
+        let mut generated: usize = 0;
+        for password in ordered {
+            if generated == opts.num_to_gen.get() {
+                break;
+            }
+            let password = match password {
+                Ok(password) => password,
+                Err(error) => {
+                    read_error = Some(error);
+                    break;
+                }
+            };
+            if seen.as_ref().is_some_and(|seen| !seen.insert(&password)) {
+                continue;
+            }
+            if tx_printer.send(password).is_err() {
+                break;
+            }
+            generated += 1;
+        }
+        if read_error.is_none() && generated < opts.num_to_gen.into() {
+            info!("Model ran out after {generated} passwords");
+        }
+    }
+
+    // Main thread loops 0..num_to_gen, sending to generator threads
+    if source.is_some() {
+        for password_number in 0..opts.num_to_gen.into() {
+            if tx_main.send(password_number).is_err() {
+                break;
+            }
+        }
+    }
+    drop(tx_main);
+    drop(tx_printer);
+    debug!("Main thread done");
 
     for thread in threads {
         thread
             .join()
             .map_err(|_e| anyhow!("Failed to join a thread"))??;
     }
+    if let Some(error) = read_error {
+        return Err(error);
+    }
+    let duplicates = duplicates.load(Ordering::Relaxed);
+    if duplicates > 0 {
+        info!(
+            "Dropped {duplicates} duplicate passwords, only {} of {} were generated",
+            indexed_total - duplicates as u128,
+            opts.num_to_gen
+        );
+    }
+    info!("Done generating passwords");
 
     debug! {"All threads joined"};
+    reporter.finish();
 
     Ok(())
 }
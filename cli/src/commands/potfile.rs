@@ -0,0 +1,96 @@
+use anyhow::Result;
+use clap::Args;
+use hashassin_core::{
+    potfile::{encode_plain, Potfile},
+    targets::Target,
+};
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, Write},
+    path::PathBuf,
+};
+use tracing::{info, trace};
+
+use super::crack::{load_targets, TargetFormat, DEFAULT_POTFILE};
+
+/// Options for listing cracked or uncracked targets
+#[derive(Args, Debug)]
+pub(crate) struct PotfileOpts {
+    /// This is the path to the target hashes
+    #[clap(long)]
+    in_path: PathBuf,
+
+    /// This is the format of the target hashes
+    #[clap(long, value_enum, default_value = "binary")]
+    target_format: TargetFormat,
+
+    /// This is the potfile holding the cracked hashes
+    #[clap(long, default_value = DEFAULT_POTFILE)]
+    potfile: PathBuf,
+
+    /// This is the path to write the list to, will default to stdout
+    #[clap(long)]
+    out_path: Option<PathBuf>,
+}
+
+/// Loads the targets with their users, if the format has them
+fn targets(opts: &PotfileOpts) -> Result<Vec<(Option<String>, Target)>> {
+    Ok(match opts.target_format {
+        TargetFormat::User => Target::from_user_lines(BufReader::new(File::open(&opts.in_path)?))?
+            .into_iter()
+            .map(|(user, target)| (Some(user), target))
+            .collect(),
+        format => load_targets(&opts.in_path, format)?
+            .into_iter()
+            .map(|target| (None, target))
+            .collect(),
+    })
+}
+
+/// Opens the output, stdout when no path is given
+fn writer(opts: &PotfileOpts) -> Result<Box<dyn Write>> {
+    Ok(match &opts.out_path {
+        Some(out_path) => Box::new(BufWriter::new(File::create(out_path)?)),
+        None => Box::new(io::stdout().lock()),
+    })
+}
+
+/// Prints `[user:]hash[:salt]:plain` for every target the potfile has cracked, like hashcat --show
+pub(crate) fn do_show(opts: PotfileOpts) -> Result<()> {
+    trace!("Do show");
+    let potfile = Potfile::read(&opts.potfile)?;
+    let mut out = writer(&opts)?;
+    let mut shown = 0;
+    for (user, target) in targets(&opts)? {
+        if let Some(plain) = potfile.get(&target) {
+            if let Some(user) = user {
+                write!(out, "{user}:")?;
+            }
+            writeln!(out, "{target}:{}", encode_plain(&plain))?;
+            shown += 1;
+        }
+    }
+    out.flush()?;
+    info!("{shown} targets cracked");
+    Ok(())
+}
+
+/// Prints `[user:]hash[:salt]` for every target the potfile has not cracked, like hashcat --left
+pub(crate) fn do_left(opts: PotfileOpts) -> Result<()> {
+    trace!("Do left");
+    let potfile = Potfile::read(&opts.potfile)?;
+    let mut out = writer(&opts)?;
+    let mut left = 0;
+    for (user, target) in targets(&opts)? {
+        if !potfile.contains(&target) {
+            if let Some(user) = user {
+                write!(out, "{user}:")?;
+            }
+            writeln!(out, "{target}")?;
+            left += 1;
+        }
+    }
+    out.flush()?;
+    info!("{left} targets left");
+    Ok(())
+}
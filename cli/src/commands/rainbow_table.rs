@@ -1,18 +1,23 @@
 use anyhow::{anyhow, Result};
 use clap::Args;
-use hashassin_core::RainbowTable;
+use hashassin_core::{
+    charset::{build_charset, CharRange, Script},
+    table::{self, TableFormat, TableHeader},
+    RainbowTable,
+};
 
 use std::{
     borrow::BorrowMut,
     fs::File,
     io::{self, BufRead, BufReader, Stdout, Write},
     num::NonZeroUsize,
-    path::{PathBuf},
+    path::PathBuf,
+    sync::Arc,
     thread,
 };
 use tracing::{debug, info, trace};
 
-
+use crate::progress::{Progress, ProgressOpts, Reporter};
 
 /// Options for password generation
 #[derive(Args, Debug)]
@@ -33,6 +38,10 @@ pub(crate) struct RainbowOpts {
     #[clap(long, default_value = "4")]
     password_length: NonZeroUsize,
 
+    /// Length of the shortest passwords, the table covers every length from this to the password length
+    #[clap(long)]
+    min_password_length: Option<NonZeroUsize>,
+
     // Hashing algorithm used
     #[clap(long, default_value = "Md5")]
     algorithm: String,
@@ -40,6 +49,25 @@ pub(crate) struct RainbowOpts {
     // Input path to passwords
     #[clap(long)]
     in_path: PathBuf,
+
+    /// Which table of a set this is, tables of one set use different reduction functions
+    #[clap(long, default_value = "0")]
+    table_index: u64,
+
+    /// These are the scripts passwords in the chains are made of: ascii, cyrillic, cjk, latin-accented
+    #[clap(long, value_delimiter = ',')]
+    script: Vec<Script>,
+
+    /// These are extra Unicode code point ranges passwords in the chains are made of, like 0400-04FF
+    #[clap(long, value_delimiter = ',')]
+    char_range: Vec<CharRange>,
+
+    /// How chains are stored: text, or binary for smaller tables with sorted endpoints
+    #[clap(long, default_value = "text")]
+    format: TableFormat,
+
+    #[clap(flatten)]
+    progress: ProgressOpts,
 }
 
 /// Output destination
@@ -50,8 +78,34 @@ enum Writer {
     Stdout(Stdout),
 }
 
+impl Writer {
+    /// The destination as a plain writer
+    fn as_write(&mut self) -> &mut dyn Write {
+        match self {
+            Writer::File(file) => file,
+            Writer::Stdout(stdout) => stdout,
+        }
+    }
+}
+
 pub(crate) fn do_rainbow(opts: RainbowOpts) -> Result<()> {
-    let rainbow_table = RainbowTable::new(opts.num_links, opts.password_length, opts.algorithm);
+    let mut rainbow_table =
+        RainbowTable::new(opts.num_links, opts.password_length, opts.algorithm)?
+            .with_table_index(opts.table_index);
+    // Printable ASCII unless told otherwise, like gen-passwords
+    if !opts.script.is_empty() || !opts.char_range.is_empty() {
+        rainbow_table =
+            rainbow_table.with_charset(build_charset(&opts.script, &opts.char_range))?;
+    }
+    if let Some(min_password_length) = opts.min_password_length {
+        rainbow_table = rainbow_table.with_min_length(min_password_length)?;
+    }
+    // Refuse a table that could not be written before spending time on its chains
+    TableHeader {
+        format: opts.format,
+        ..rainbow_table.header(0)
+    }
+    .check()?;
 
     let mut out_file: Writer = match opts.out_path {
         Some(out_path) => {
@@ -64,8 +118,6 @@ pub(crate) fn do_rainbow(opts: RainbowOpts) -> Result<()> {
     let input_file = File::open(opts.in_path)?;
     let reader = BufReader::new(input_file);
 
-    let _chain_file = File::create("data/chain_file.txt")?;
-
     // One prod, multi cons
     // Transmit passwords
     let (tx_main, rx_main) = crossbeam_channel::unbounded();
@@ -75,10 +127,16 @@ pub(crate) fn do_rainbow(opts: RainbowOpts) -> Result<()> {
 
     let mut threads = vec![];
 
+    let progress = Progress::new("chains", None);
+    let reporter = Reporter::start(&progress, &opts.progress);
+
     // Main tread loops throuhg input file, sending passwords
+    let mut total = 0;
     for passwords in reader.lines() {
         tx_main.send(passwords)?;
+        total += 1;
     }
+    progress.set_total(total);
     trace!("Main thread done");
     drop(tx_main);
 
@@ -103,16 +161,31 @@ pub(crate) fn do_rainbow(opts: RainbowOpts) -> Result<()> {
     drop(rx_main);
     drop(tx_printer);
 
+    // Every password has been sent, so the number of chains is known before the first is written
+    let header = rainbow_table.header(total);
+    let printer_progress = Arc::clone(&progress);
+    let format = opts.format;
     let printer_thread = thread::spawn(move || -> Result<()> {
+        // Binary chains are sorted by end, so they are only written once all are made
+        let mut sorted = Vec::new();
+        if format == TableFormat::Text {
+            header.write(out_file.as_write())?;
+        }
         while let Ok(chain_result) = rx_printer.recv() {
+            printer_progress.add(1);
             match chain_result {
+                Ok(chain) if format == TableFormat::Binary => sorted.push(chain.into_pair()),
                 Ok(chain) => match out_file.borrow_mut() {
                     Writer::File(to_write) => writeln!(to_write, "{chain}")?,
                     Writer::Stdout(to_write) => writeln!(to_write, "{chain}")?,
                 },
-                Err(error) => panic!("Error: {error}"),
+                Err(error) => return Err(error),
             };
         }
+        if format == TableFormat::Binary {
+            let written = table::write_binary(&header, sorted, out_file.as_write())?;
+            info!("Wrote {written} chains");
+        }
 
         drop(rx_printer);
 
@@ -129,6 +202,7 @@ pub(crate) fn do_rainbow(opts: RainbowOpts) -> Result<()> {
             .join()
             .map_err(|_e| anyhow!("Failed to join a thread"))??;
     }
+    reporter.finish();
 
     Ok(())
     // create rainbow table struct (num_links, pass_len, algo)
@@ -1,21 +1,26 @@
 use anyhow::Result;
 use clap::Args;
+use hashassin_core::{Crack, CrackOutcome};
 
-use tokio::io::AsyncReadExt;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
 
 use tokio::net::TcpListener;
 // use std::io::BufRead;
+use hex;
 use std::net::IpAddr;
 use std::net::SocketAddr;
 use std::num::NonZeroUsize;
 use std::path::PathBuf;
-use hex;
+use std::sync::Arc;
 use tokio::net::TcpStream;
 use tokio::sync::broadcast::Receiver;
 use tokio::sync::broadcast::Sender;
 // use std::{io::BufReader};
 use tracing::{debug, info};
 
+use super::crack::load_rainbow_table;
+use crate::outcome::{OutcomeFormat, OutcomeWriter};
+
 #[derive(Args, Debug, Clone)]
 pub(crate) struct ServerOpts {
     /// Port number
@@ -25,35 +30,45 @@ pub(crate) struct ServerOpts {
     /// The ip address to bind to
     #[clap(long, default_value = "127.0.0.1")]
     ip_address: std::net::IpAddr, // check this?
-                                  // could have a protocol type too
-    // Number of links in each chain
-    #[clap(long, default_value = "5")]
-    num_links: NonZeroUsize,
-    
+    // could have a protocol type too
+    // Number of links in each chain, read from the rainbow table header when it has one
+    #[clap(long)]
+    num_links: Option<NonZeroUsize>,
+
     // Threads used to generate rainbow table
     #[clap(long, default_value = "1")]
     threads: NonZeroUsize,
-    
-    // Length of passwords for rainbow table
-    #[clap(long, default_value = "4")]
-    password_length: NonZeroUsize,
-
-    // Hashing algorithm used
-    #[clap(long, default_value = "Md5")]
-    algorithm: String,
+
+    // Length of passwords for rainbow table, read from the rainbow table header when it has one
+    #[clap(long)]
+    password_length: Option<NonZeroUsize>,
+
+    // Hashing algorithm used, Md5 unless the rainbow table header says otherwise
+    #[clap(long)]
+    algorithm: Option<String>,
     // Input path to rainbow table
     #[clap(long)]
     rainbow_path: PathBuf,
 
+    /// How replies to crack are written: text or json
+    #[clap(long, value_enum, default_value = "text")]
+    outcome_format: OutcomeFormat,
 }
 
 pub struct Server {
     listener: TcpListener, // we want to listen for connections from clients
     broadcast_sender: Sender<(SocketAddr, String)>, // how to send messages to all connectd clients
+    cracker: Arc<Crack>,   // the rainbow table every connection cracks with
+    writer: Arc<dyn OutcomeWriter>, // formats every connection's replies
 }
 
 impl Server {
-    pub async fn new(ip_address: IpAddr, port: u16) -> Self {
+    pub async fn new(
+        ip_address: IpAddr,
+        port: u16,
+        cracker: Arc<Crack>,
+        writer: Arc<dyn OutcomeWriter>,
+    ) -> Self {
         // create a listener
         // let listener = TcpListener::bind((ip_address, port)).await.unwrap();
 
@@ -66,6 +81,8 @@ impl Server {
         Self {
             listener,
             broadcast_sender,
+            cracker,
+            writer,
         }
     }
 
@@ -89,6 +106,8 @@ impl Server {
                 remote_addr,
                 self.broadcast_sender.clone(),
                 self.broadcast_sender.subscribe(),
+                Arc::clone(&self.cracker),
+                Arc::clone(&self.writer),
             )
             .await;
             info!("connection2 established from {remote_addr}");
@@ -104,6 +123,8 @@ struct Connection {
     addr: SocketAddr,                      // the remote connections address
     broadcast_sender: BroadcastSender,     // send to other connections
     broadcast_receiver: BroadcastReceiver, // receive from other connections
+    cracker: Arc<Crack>,                   // the rainbow table to crack with
+    writer: Arc<dyn OutcomeWriter>,        // formats replies to crack
 }
 
 type BroadcastSender = Sender<(SocketAddr, String)>;
@@ -115,12 +136,23 @@ impl Connection {
         addr: SocketAddr,
         broadcast_sender: BroadcastSender,
         broadcast_receiver: BroadcastReceiver,
+        cracker: Arc<Crack>,
+        writer: Arc<dyn OutcomeWriter>,
     ) -> Connection {
         Self {
             stream,
             addr,
             broadcast_sender,
             broadcast_receiver,
+            cracker,
+            writer,
+        }
+    }
+
+    /// Answer our own client only
+    async fn reply(&mut self, msg: &str) {
+        if let Err(err) = self.stream.write_all(msg.as_bytes()).await {
+            debug!("Could not reply to {}: {err}", self.addr);
         }
     }
 
@@ -137,33 +169,19 @@ impl Connection {
     }
 }
 
-async fn do_stuff_async() {
-    //
-}
-
-async fn do_more_stuff() {
-    // whatever
-}
-
-async fn test_stuff() {
-    loop {
-        tokio::select! {
-            _x = do_stuff_async() => {
-                println!("async stuff")
-                
-            }
-            _y = do_more_stuff() => {
-                println!("do more async stuff")
-            }
-        }
-    }
-}
-
 /// Set up a socket and listen on it.
 pub(crate) async fn server(opts: ServerOpts) -> Result<()> {
     debug!("I'm running server()");
 
-    let server = Server::new(opts.ip_address, opts.port).await;
+    // Refuse an incompatible table before taking any connections
+    let cracker = Arc::new(load_rainbow_table(
+        &opts.rainbow_path,
+        opts.num_links,
+        opts.password_length,
+        opts.algorithm.as_deref(),
+    )?);
+    let writer = Arc::from(opts.outcome_format.writer());
+    let server = Server::new(opts.ip_address, opts.port, cracker, writer).await;
     info!("waiting for connection!");
 
     server.listen().await;
@@ -194,6 +212,11 @@ async fn handle_connection(mut connection: Connection) {
                     Ok(x) => x,
                     Err(x) => panic!("{x}"),
                 };
+                // The client hung up
+                if n_read == 0 {
+                    debug!("{} disconnected", connection.addr);
+                    break;
+                }
                 let message = &buf[0..n_read];
 
                 let string_message = match String::from_utf8(message.to_vec()){
@@ -203,26 +226,32 @@ async fn handle_connection(mut connection: Connection) {
 
                 if let Some(_space_index) = string_message.find("crack ") {
                     let split_message = (string_message.split(' ')).collect::<Vec<&str>>();
-                    
+
                     let hash = split_message[1].trim();
-                    
+
                     let hash_len = format!("{}\n",hash.len());
                     connection.say(&hash_len).await;
                     connection.say("\n").await;
                     // connection.say(hash).await;
-                    
-                    match hex::decode(hash){
-                        Ok(_x) => {
-                            //connection.say("hello from jizztown3\n").await;
-                            test_stuff().await;
+
+                    match hex::decode(hash).map(<[u8; 16]>::try_from){
+                        Ok(Ok(hash)) => {
+                            // Cracking walks whole chains, so keep it off the async threads
+                            let cracker = Arc::clone(&connection.cracker);
+                            let outcome = tokio::task::spawn_blocking(move || cracker.crack(&hash))
+                                .await
+                                .unwrap_or_else(|err| CrackOutcome::Error(err.into()));
+                            let reply = connection.writer.format(&hash, &outcome);
+                            connection.reply(&reply).await;
                         },
-                        Err(_x) => connection.say("Error {x}\n",).await,
+                        Ok(Err(_x)) => connection.reply("Error: hash must be 16 bytes\n").await,
+                        Err(x) => connection.reply(&format!("Error {x}\n")).await,
                     };
                     // test_stuff().await;
                 } else{
                     connection.say("ERRROR: Command must be in the format `crack <hash>`\n").await;
 
-                }          
+                }
 
 
             }
@@ -0,0 +1,132 @@
+use anyhow::{anyhow, Result};
+use clap::Args;
+use hashassin_core::strength::{self, StrengthEstimate};
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, Stdout, Write},
+    num::NonZeroUsize,
+    path::PathBuf,
+    thread,
+};
+use tracing::{debug, info, trace};
+
+/// Options for password strength estimation
+#[derive(Args, Debug)]
+pub(crate) struct StrengthOpts {
+    /// This is the path to read the plaintext passwords
+    #[clap(long)]
+    in_path: PathBuf,
+
+    /// This is the path to write the report to, will default to stdout
+    #[clap(long)]
+    out_path: Option<PathBuf>,
+
+    /// This is the number of threads to use to estimate the passwords
+    #[clap(long, default_value = "1")]
+    threads: NonZeroUsize,
+}
+
+/// Output destination
+enum Writer {
+    /// Write to file
+    File(File),
+    /// Write to stdout
+    Stdout(Stdout),
+}
+
+/// Formats one report line: password, entropy, log10 guesses, patterns and crack times, tab separated
+fn report_line(password: &str, estimate: &StrengthEstimate) -> String {
+    let patterns: Vec<String> = estimate
+        .patterns
+        .iter()
+        .map(|m| format!("{}({})", m.pattern, m.token))
+        .collect();
+    let crack_times: Vec<String> = estimate
+        .crack_times
+        .iter()
+        .map(|t| format!("{}={:.3e}s", t.algorithm, t.seconds))
+        .collect();
+
+    format!(
+        "{password}\t{:.1}\t{:.2}\t{}\t{}",
+        estimate.entropy_bits,
+        estimate.guesses_log10,
+        patterns.join(" "),
+        crack_times.join(" ")
+    )
+}
+
+/// Estimates the strength of every password in the input file
+/// Will also distribute work as evenly as possible among threads
+pub(crate) fn do_strength(opts: StrengthOpts) -> Result<()> {
+    trace!("Do strength");
+    let input_file = File::open(opts.in_path)?;
+    let reader = BufReader::new(input_file);
+
+    let mut out_file: Writer = match opts.out_path {
+        Some(out_path) => {
+            let file = File::create(out_path)?;
+            Writer::File(file)
+        }
+        None => Writer::Stdout(io::stdout()),
+    };
+
+    // Benchmark once up front so workers don't all wait on the first estimate
+    let throughput = strength::measured_throughput();
+    info!("Measured throughput for {} algorithms", throughput.len());
+
+    let (tx_main, rx_main) = crossbeam_channel::unbounded();
+    // multi producers (estimators), single consumer (printer)
+    let (tx_printer, rx_printer) = std::sync::mpsc::channel();
+
+    let mut threads = vec![];
+
+    // Main thread loops through the input file, sending to estimator threads
+    for password in reader.lines() {
+        tx_main.send(password)?;
+    }
+    drop(tx_main);
+    debug!("Main thread done");
+
+    for thread_number in 0..opts.threads.into() {
+        let rx_main = rx_main.clone();
+        let tx_printer = tx_printer.clone();
+        let thread = thread::spawn(move || -> Result<()> {
+            while let Ok(password) = rx_main.recv() {
+                let password = password?;
+                let estimate = strength::estimate_with(&password, throughput);
+                tx_printer.send(report_line(&password, &estimate))?;
+            }
+            Ok(())
+        });
+
+        threads.push(thread);
+        debug!("Thread: {thread_number} done");
+    }
+
+    drop(rx_main);
+    drop(tx_printer);
+    let printer_thread = thread::spawn(move || -> Result<()> {
+        while let Ok(line) = rx_printer.recv() {
+            match out_file {
+                Writer::File(ref mut to_write) => writeln!(to_write, "{line}")?,
+                Writer::Stdout(ref mut to_write) => writeln!(to_write, "{line}")?,
+            };
+        }
+
+        debug!("Printer thread done");
+        Ok(())
+    });
+
+    threads.push(printer_thread);
+
+    for thread in threads {
+        thread
+            .join()
+            .map_err(|_e| anyhow!("Failed to join a thread"))??;
+    }
+
+    debug!("All threads joined");
+
+    Ok(())
+}
@@ -0,0 +1,42 @@
+use anyhow::Result;
+use clap::Args;
+use hashassin_core::markov::MarkovModel;
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter},
+    path::PathBuf,
+};
+use tracing::{info, trace};
+
+/// Options for Markov model training
+#[derive(Args, Debug)]
+pub(crate) struct TrainMarkovOpts {
+    /// This is the path to the wordlist to learn from
+    #[clap(long)]
+    in_path: PathBuf,
+
+    /// This is the path to write the model to, will default to stdout
+    #[clap(long)]
+    out_path: Option<PathBuf>,
+
+    /// This is how many previous characters each transition depends on (1 to 3)
+    #[clap(long, default_value = "2")]
+    order: usize,
+}
+
+/// Learns a Markov model from a wordlist and saves it
+pub(crate) fn do_train_markov(opts: TrainMarkovOpts) -> Result<()> {
+    trace!("Do train markov");
+    let reader = BufReader::new(File::open(opts.in_path)?);
+    let words = reader.lines().collect::<io::Result<Vec<String>>>()?;
+
+    let model = MarkovModel::train(&words, opts.order)?;
+    info!("Trained on {} words", words.len());
+
+    match opts.out_path {
+        Some(out_path) => model.save(BufWriter::new(File::create(out_path)?))?,
+        None => model.save(io::stdout().lock())?,
+    }
+
+    Ok(())
+}
@@ -0,0 +1,38 @@
+use anyhow::Result;
+use clap::Args;
+use hashassin_core::pcfg::Pcfg;
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter},
+    path::PathBuf,
+};
+use tracing::{info, trace};
+
+/// Options for PCFG training
+#[derive(Args, Debug)]
+pub(crate) struct TrainPcfgOpts {
+    /// This is the path to the cracked passwords to learn from
+    #[clap(long)]
+    in_path: PathBuf,
+
+    /// This is the path to write the grammar to, will default to stdout
+    #[clap(long)]
+    out_path: Option<PathBuf>,
+}
+
+/// Learns a PCFG from cracked passwords and saves it
+pub(crate) fn do_train_pcfg(opts: TrainPcfgOpts) -> Result<()> {
+    trace!("Do train pcfg");
+    let reader = BufReader::new(File::open(opts.in_path)?);
+    let passwords = reader.lines().collect::<io::Result<Vec<String>>>()?;
+
+    let grammar = Pcfg::train(&passwords)?;
+    info!("Trained on {} passwords", passwords.len());
+
+    match opts.out_path {
+        Some(out_path) => grammar.save(BufWriter::new(File::create(out_path)?))?,
+        None => grammar.save(io::stdout().lock())?,
+    }
+
+    Ok(())
+}
@@ -2,14 +2,22 @@
 
 use anyhow::Result;
 use clap::{Parser, Subcommand};
+use commands::convert_table::ConvertTableOpts;
 use commands::crack::CrackOpts;
 use commands::gen_hashes::HashesOpts;
 use commands::gen_passwords::PasswordsOpts;
+use commands::potfile::PotfileOpts;
 use commands::rainbow_table::RainbowOpts;
 use commands::server::ServerOpts;
+use commands::strength::StrengthOpts;
+use commands::train_markov::TrainMarkovOpts;
+use commands::train_pcfg::TrainPcfgOpts;
 use dotenvy::dotenv;
 
+mod candidates;
 mod commands;
+mod outcome;
+mod progress;
 
 #[derive(Parser, Debug)]
 struct Opts {
@@ -28,8 +36,26 @@ enum Command {
 
     /// Create rainbow table
     GenRainbowTable(RainbowOpts),
+
+    /// Convert a text rainbow table to the binary format
+    ConvertRainbowTable(ConvertTableOpts),
     Crack(CrackOpts),
     Server(ServerOpts),
+
+    /// Estimate password strength
+    Strength(StrengthOpts),
+
+    /// Train a Markov model from a wordlist
+    TrainMarkov(TrainMarkovOpts),
+
+    /// Train a PCFG grammar from cracked passwords
+    TrainPcfg(TrainPcfgOpts),
+
+    /// Show targets the potfile has cracked
+    Show(PotfileOpts),
+
+    /// Show targets the potfile has not cracked
+    Left(PotfileOpts),
 }
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -45,8 +71,14 @@ async fn main() -> Result<()> {
         Command::GenPasswords(opts) => commands::gen_passwords::do_passwords(opts)?,
         Command::GenHashes(opts) => commands::gen_hashes::gen_hashes(opts)?,
         Command::GenRainbowTable(opts) => commands::rainbow_table::do_rainbow(opts)?,
+        Command::ConvertRainbowTable(opts) => commands::convert_table::do_convert_table(opts)?,
         Command::Crack(opts) => commands::crack::do_cracks(opts)?,
         Command::Server(opts) => commands::server::server(opts).await?,
+        Command::Strength(opts) => commands::strength::do_strength(opts)?,
+        Command::TrainMarkov(opts) => commands::train_markov::do_train_markov(opts)?,
+        Command::TrainPcfg(opts) => commands::train_pcfg::do_train_pcfg(opts)?,
+        Command::Show(opts) => commands::potfile::do_show(opts)?,
+        Command::Left(opts) => commands::potfile::do_left(opts)?,
     }
 
     Ok(())
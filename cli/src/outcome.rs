@@ -0,0 +1,166 @@
+use clap::ValueEnum;
+use hashassin_core::CrackOutcome;
+use hex::encode;
+
+/// How rainbow table lookups are written
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum OutcomeFormat {
+    /// `hash<TAB>password` for cracks, a message otherwise
+    Text,
+    /// One JSON object per line, for scripts
+    Json,
+}
+
+impl OutcomeFormat {
+    /// The writer for this format
+    pub(crate) fn writer(self) -> Box<dyn OutcomeWriter> {
+        match self {
+            OutcomeFormat::Text => Box::new(TextWriter),
+            OutcomeFormat::Json => Box::new(JsonWriter),
+        }
+    }
+}
+
+/// Turns the outcome of looking up `hash` into a line of output
+///
+/// Lines end in a newline, so they can be written or sent as they are.
+pub(crate) trait OutcomeWriter: Send + Sync {
+    fn format(&self, hash: &[u8; 16], outcome: &CrackOutcome) -> String;
+}
+
+/// Lines the crack command has always written
+pub(crate) struct TextWriter;
+
+impl OutcomeWriter for TextWriter {
+    fn format(&self, hash: &[u8; 16], outcome: &CrackOutcome) -> String {
+        match outcome {
+            CrackOutcome::Found { password, .. } => format!("{}\t{password}\n", encode(hash)),
+            CrackOutcome::NotFound => "Hash not Present in Rainbow Table\n".to_string(),
+            CrackOutcome::Error(error) => format!("Error {error}\n"),
+        }
+    }
+}
+
+/// One JSON object per line, with the chain position and false alarms of cracks
+pub(crate) struct JsonWriter;
+
+impl OutcomeWriter for JsonWriter {
+    fn format(&self, hash: &[u8; 16], outcome: &CrackOutcome) -> String {
+        let hash = encode(hash);
+        match outcome {
+            CrackOutcome::Found {
+                password,
+                chain_position,
+                false_alarms,
+                ..
+            } => format!(
+                "{{\"hash\":\"{hash}\",\"outcome\":\"found\",\"password\":{},\"chain_position\":{chain_position},\"false_alarms\":{false_alarms}}}\n",
+                json_string(password)
+            ),
+            CrackOutcome::NotFound => format!("{{\"hash\":\"{hash}\",\"outcome\":\"not_found\"}}\n"),
+            CrackOutcome::Error(error) => format!(
+                "{{\"hash\":\"{hash}\",\"outcome\":\"error\",\"error\":{}}}\n",
+                json_string(&error.to_string())
+            ),
+        }
+    }
+}
+
+/// Quotes `value` as a JSON string
+fn json_string(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+
+    const HASH: [u8; 16] = [0xab; 16];
+    const HEX: &str = "abababababababababababababababab";
+
+    fn found(password: &str) -> CrackOutcome {
+        CrackOutcome::Found {
+            hash: HASH,
+            password: password.to_string(),
+            chain_position: 3,
+            false_alarms: 2,
+        }
+    }
+
+    #[test]
+    fn text_lines() {
+        assert_eq!(
+            TextWriter.format(&HASH, &found("hunter2")),
+            format!("{HEX}\thunter2\n")
+        );
+        assert_eq!(
+            TextWriter.format(&HASH, &CrackOutcome::NotFound),
+            "Hash not Present in Rainbow Table\n"
+        );
+        assert_eq!(
+            TextWriter.format(&HASH, &CrackOutcome::Error(anyhow!("bad table"))),
+            "Error bad table\n"
+        );
+    }
+
+    #[test]
+    fn json_lines() {
+        assert_eq!(
+            JsonWriter.format(&HASH, &found("hunter2")),
+            format!(
+                "{{\"hash\":\"{HEX}\",\"outcome\":\"found\",\"password\":\"hunter2\",\"chain_position\":3,\"false_alarms\":2}}\n"
+            )
+        );
+        assert_eq!(
+            JsonWriter.format(&HASH, &CrackOutcome::NotFound),
+            format!("{{\"hash\":\"{HEX}\",\"outcome\":\"not_found\"}}\n")
+        );
+        assert_eq!(
+            JsonWriter.format(&HASH, &CrackOutcome::Error(anyhow!("line \"7\"\nis bad"))),
+            format!(
+                "{{\"hash\":\"{HEX}\",\"outcome\":\"error\",\"error\":\"line \\\"7\\\"\\nis bad\"}}\n"
+            )
+        );
+    }
+
+    #[test]
+    fn json_passwords_stay_on_one_line() {
+        let line = JsonWriter.format(&HASH, &found("a\"b\\c\nd"));
+        assert!(line.contains(r#""password":"a\"b\\c\nd""#), "{line}");
+        assert_eq!(line.matches('\n').count(), 1);
+    }
+
+    #[test]
+    fn json_strings_are_escaped() {
+        let cases = [
+            ("", r#""""#),
+            ("plain", r#""plain""#),
+            ("say \"hi\"", r#""say \"hi\"""#),
+            (r"C:\path", r#""C:\\path""#),
+            ("a\nb\rc\td", r#""a\nb\rc\td""#),
+            ("\u{0}\u{1f}", r#""\u0000\u001f""#),
+            ("\u{7f}\u{85}", r#""\u007f\u0085""#),
+            // Printable non ASCII is written as it is, JSON is UTF-8
+            ("pässwörd 日本 🔑", "\"pässwörd 日本 🔑\""),
+            ("/", r#""/""#),
+        ];
+        for (value, quoted) in cases {
+            assert_eq!(json_string(value), quoted, "{value:?}");
+        }
+    }
+}
@@ -0,0 +1,239 @@
+use clap::{Args, ValueEnum};
+use std::{
+    io::{self, Write},
+    num::NonZeroU64,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc::{self, RecvTimeoutError, Sender},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+use tracing::debug;
+
+/// How progress is reported on stderr
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum ProgressFormat {
+    /// A status line redrawn in place
+    Text,
+    /// One JSON object per line, for scripts and dashboards
+    Json,
+    /// Nothing
+    None,
+}
+
+/// Progress options shared by the long running commands
+#[derive(Args, Debug, Clone)]
+pub(crate) struct ProgressOpts {
+    /// How progress is reported on stderr: text, json or none
+    #[clap(long, value_enum, default_value = "text")]
+    progress: ProgressFormat,
+
+    /// Seconds between progress reports
+    #[clap(long, default_value = "1")]
+    progress_interval: NonZeroU64,
+}
+
+/// Counters worker threads update as they go
+///
+/// Every counter is atomic, so threads update them without locking and the
+/// reporter reads them whenever it wakes up.
+#[derive(Debug)]
+pub(crate) struct Progress {
+    /// What is being counted, like "hashes" or "candidates"
+    unit: &'static str,
+    /// Items done so far
+    done: AtomicU64,
+    /// Items in the whole job, 0 when unknown
+    total: AtomicU64,
+    /// Targets cracked, for crack
+    cracked: AtomicU64,
+    /// Targets to crack, 0 for commands that do not crack
+    targets: AtomicU64,
+    /// Items done before this run, left out of the rate
+    resumed: AtomicU64,
+    started: Instant,
+}
+
+impl Progress {
+    /// Counters for a job of `total` items, None if the total is not known up front
+    pub(crate) fn new(unit: &'static str, total: Option<u64>) -> Arc<Self> {
+        Arc::new(Progress {
+            unit,
+            done: AtomicU64::new(0),
+            total: AtomicU64::new(total.unwrap_or(0)),
+            cracked: AtomicU64::new(0),
+            targets: AtomicU64::new(0),
+            resumed: AtomicU64::new(0),
+            started: Instant::now(),
+        })
+    }
+
+    /// Counts items done
+    pub(crate) fn add(&self, items: u64) {
+        self.done.fetch_add(items, Ordering::Relaxed);
+    }
+
+    /// Sets the total once it is known
+    pub(crate) fn set_total(&self, total: u64) {
+        self.total.store(total, Ordering::Relaxed);
+    }
+
+    /// Starts from items a restored session already did
+    pub(crate) fn resume(&self, done: u64) {
+        self.done.store(done, Ordering::Relaxed);
+        self.resumed.store(done, Ordering::Relaxed);
+    }
+
+    /// Sets the number of targets being cracked
+    pub(crate) fn set_targets(&self, targets: u64) {
+        self.targets.store(targets, Ordering::Relaxed);
+    }
+
+    /// Counts cracked targets
+    pub(crate) fn add_cracked(&self, cracked: u64) {
+        self.cracked.fetch_add(cracked, Ordering::Relaxed);
+    }
+
+    /// Targets cracked so far
+    pub(crate) fn cracked(&self) -> u64 {
+        self.cracked.load(Ordering::Relaxed)
+    }
+
+    /// Reads every counter at once
+    fn snapshot(&self) -> Snapshot {
+        let done = self.done.load(Ordering::Relaxed);
+        let total = self.total.load(Ordering::Relaxed);
+        let elapsed = self.started.elapsed().as_secs_f64();
+        let rate = done.saturating_sub(self.resumed.load(Ordering::Relaxed)) as f64
+            / elapsed.max(f64::EPSILON);
+        let eta = (total > 0 && rate > 0.0).then(|| total.saturating_sub(done) as f64 / rate);
+        Snapshot {
+            done,
+            total: (total > 0).then_some(total),
+            rate,
+            elapsed,
+            eta,
+            cracked: self.cracked.load(Ordering::Relaxed),
+            targets: self.targets.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Counters at one point in time
+struct Snapshot {
+    done: u64,
+    total: Option<u64>,
+    /// Items per second
+    rate: f64,
+    /// Seconds since the job started
+    elapsed: f64,
+    /// Seconds left at the current rate
+    eta: Option<f64>,
+    cracked: u64,
+    targets: u64,
+}
+
+/// Writes seconds as 1h02m03s, 2m03s or 3s
+fn format_duration(seconds: f64) -> String {
+    let seconds = seconds.round() as u64;
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    match (hours, minutes) {
+        (0, 0) => format!("{seconds}s"),
+        (0, _) => format!("{minutes}m{seconds:02}s"),
+        _ => format!("{hours}h{minutes:02}m{seconds:02}s"),
+    }
+}
+
+impl Snapshot {
+    /// `done/total (percent), rate unit/s, ETA, cracked x/y`
+    fn text(&self, unit: &str) -> String {
+        let mut line = match self.total {
+            Some(total) => format!(
+                "{}/{total} {unit} ({:.1}%)",
+                self.done,
+                self.done as f64 / total as f64 * 100.0
+            ),
+            None => format!("{} {unit}", self.done),
+        };
+        line.push_str(&format!(", {:.0} {unit}/s", self.rate));
+        if let Some(eta) = self.eta {
+            line.push_str(&format!(", ETA {}", format_duration(eta)));
+        }
+        if self.targets > 0 {
+            line.push_str(&format!(", cracked {}/{}", self.cracked, self.targets));
+        }
+        line
+    }
+
+    /// One JSON object, unknown values as null
+    fn json(&self, unit: &str, finished: bool) -> String {
+        let optional = |value: Option<String>| value.unwrap_or_else(|| "null".to_string());
+        format!(
+            "{{\"unit\":\"{unit}\",\"done\":{},\"total\":{},\"rate\":{:.1},\"elapsed\":{:.1},\"eta\":{},\"cracked\":{},\"targets\":{},\"finished\":{finished}}}",
+            self.done,
+            optional(self.total.map(|total| total.to_string())),
+            self.rate,
+            self.elapsed,
+            optional(self.eta.map(|eta| format!("{eta:.1}"))),
+            self.cracked,
+            self.targets,
+        )
+    }
+}
+
+/// Reports progress from a background thread until finished
+pub(crate) struct Reporter {
+    /// Dropped to wake the thread up for the final report
+    tx_stop: Sender<()>,
+    thread: JoinHandle<()>,
+}
+
+impl Reporter {
+    /// Starts reporting the counters every interval
+    pub(crate) fn start(progress: &Arc<Progress>, opts: &ProgressOpts) -> Self {
+        let (tx_stop, rx_stop) = mpsc::channel::<()>();
+        let progress = Arc::clone(progress);
+        let (format, interval) = (
+            opts.progress,
+            Duration::from_secs(opts.progress_interval.get()),
+        );
+        let thread = thread::spawn(move || {
+            // Length of the last status line, so a shorter one can blank out what is left of it
+            let mut width = 0;
+            let mut report = |finished: bool| {
+                let snapshot = progress.snapshot();
+                let mut stderr = io::stderr().lock();
+                // A closed stderr is no reason to stop the job
+                let _ = match format {
+                    ProgressFormat::Text => {
+                        let line = snapshot.text(progress.unit);
+                        let padded = format!("\r{line:<width$}");
+                        width = line.len();
+                        match finished {
+                            true => writeln!(stderr, "{padded}"),
+                            false => write!(stderr, "{padded}"),
+                        }
+                    }
+                    ProgressFormat::Json => {
+                        writeln!(stderr, "{}", snapshot.json(progress.unit, finished))
+                    }
+                    ProgressFormat::None => Ok(()),
+                };
+            };
+            while let Err(RecvTimeoutError::Timeout) = rx_stop.recv_timeout(interval) {
+                report(false);
+            }
+            report(true);
+            debug!("Progress thread done");
+        });
+        Reporter { tx_stop, thread }
+    }
+
+    /// Writes the final report and waits for it
+    pub(crate) fn finish(self) {
+        drop(self.tx_stop);
+        let _ = self.thread.join();
+    }
+}
@@ -0,0 +1,97 @@
+use std::collections::HashSet;
+
+use tracing::trace;
+
+/// Symbols people tack onto the end of a password
+const SUFFIX_SYMBOLS: &[&str] = &["!", "@", "#", "$", ".", "1!", "123", "123!", "!!"];
+
+/// Years appended to names, like john1987
+const YEARS: std::ops::RangeInclusive<u32> = 1950..=2030;
+
+/// Words a user is likely to build a password from
+///
+/// The username itself, the local part of an email address, the name parts
+/// split on punctuation, digits and camel case, initials with the last name,
+/// and all of those reversed.
+pub fn base_words(username: &str) -> Vec<String> {
+    let local = username.split('@').next().unwrap_or(username);
+
+    // Split on punctuation, letter/digit changes and lower to upper changes
+    let mut parts: Vec<String> = Vec::new();
+    let mut current = String::new();
+    let mut previous: Option<char> = None;
+    for c in local.chars() {
+        let boundary = match previous {
+            _ if !c.is_alphanumeric() => true,
+            Some(p) => {
+                p.is_alphabetic() != c.is_alphabetic() || (p.is_lowercase() && c.is_uppercase())
+            }
+            None => false,
+        };
+        if boundary && !current.is_empty() {
+            parts.push(std::mem::take(&mut current));
+        }
+        if c.is_alphanumeric() {
+            current.push(c);
+        }
+        previous = Some(c);
+    }
+    if !current.is_empty() {
+        parts.push(current);
+    }
+    let letters: Vec<&String> = parts
+        .iter()
+        .filter(|part| part.chars().all(char::is_alphabetic))
+        .collect();
+
+    let mut words = vec![username.to_string(), local.to_string(), parts.concat()];
+    words.extend(letters.iter().map(|part| part.to_string()));
+    if let Some((last, rest)) = letters.split_last() {
+        if !rest.is_empty() {
+            // jsmith and smithj
+            let initials: String = rest.iter().filter_map(|part| part.chars().next()).collect();
+            words.push(format!("{initials}{last}"));
+            words.push(format!("{last}{initials}"));
+        }
+    }
+    let reversed: Vec<String> = words
+        .iter()
+        .map(|word| word.chars().rev().collect())
+        .collect();
+    words.extend(reversed);
+
+    let mut seen = HashSet::new();
+    words.retain(|word| !word.is_empty() && seen.insert(word.clone()));
+    words
+}
+
+/// Every candidate for a user: base words in common cases, alone or with digits, years and symbols added
+pub fn candidates(username: &str) -> Vec<String> {
+    trace!("Association candidates");
+    let suffixes: Vec<String> = std::iter::once(String::new())
+        .chain((0..10).map(|digit| digit.to_string()))
+        .chain((0..100).map(|number| format!("{number:02}")))
+        .chain(YEARS.map(|year| year.to_string()))
+        .chain(SUFFIX_SYMBOLS.iter().map(|symbol| symbol.to_string()))
+        .collect();
+
+    let mut seen = HashSet::new();
+    let mut candidates = Vec::new();
+    for word in base_words(username) {
+        let lower = word.to_lowercase();
+        let mut capitalized = lower.chars();
+        let capitalized: String = match capitalized.next() {
+            Some(first) => first.to_uppercase().chain(capitalized).collect(),
+            None => String::new(),
+        };
+        for case in [word.clone(), lower, capitalized, word.to_uppercase()] {
+            for suffix in &suffixes {
+                let candidate = format!("{case}{suffix}");
+                if seen.insert(candidate.clone()) {
+                    candidates.push(candidate);
+                }
+            }
+        }
+    }
+    candidates
+}
@@ -0,0 +1,121 @@
+use std::{fmt, str::FromStr};
+
+use thiserror::Error;
+
+/// Errors for charset parsing
+#[derive(Error, Debug)]
+pub enum CharsetError {
+    #[error("Unknown script {0}, expected ascii, cyrillic, cjk or latin-accented")]
+    UnknownScript(String),
+    #[error("Invalid code point range {0}, expected e.g. 0400-04FF")]
+    InvalidRange(String),
+}
+
+/// Named groups of characters passwords can be generated from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Script {
+    /// Printable ASCII, including punctuation and space
+    Ascii,
+    /// Russian Cyrillic letters, upper and lower case
+    Cyrillic,
+    /// CJK unified ideographs
+    Cjk,
+    /// Latin letters with accents from Latin-1
+    LatinAccented,
+}
+
+impl Script {
+    /// Every character in the script
+    pub fn chars(self) -> Vec<char> {
+        match self {
+            Script::Ascii => (' '..='~').collect(),
+            Script::Cyrillic => ('\u{0410}'..='\u{044F}')
+                .chain(['\u{0401}', '\u{0451}'])
+                .collect(),
+            Script::Cjk => ('\u{4E00}'..='\u{9FFF}').collect(),
+            // Skip the multiplication and division signs in the middle of the block
+            Script::LatinAccented => ('\u{00C0}'..='\u{00FF}')
+                .filter(|c| !matches!(c, '\u{00D7}' | '\u{00F7}'))
+                .collect(),
+        }
+    }
+}
+
+impl FromStr for Script {
+    type Err = CharsetError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "ascii" => Ok(Script::Ascii),
+            "cyrillic" => Ok(Script::Cyrillic),
+            "cjk" => Ok(Script::Cjk),
+            "latin-accented" => Ok(Script::LatinAccented),
+            _ => Err(CharsetError::UnknownScript(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for Script {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Script::Ascii => "ascii",
+            Script::Cyrillic => "cyrillic",
+            Script::Cjk => "cjk",
+            Script::LatinAccented => "latin-accented",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// Inclusive range of Unicode code points, written as hex like 0400-04FF
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CharRange {
+    /// First code point
+    pub start: u32,
+    /// Last code point
+    pub end: u32,
+}
+
+impl CharRange {
+    /// Every valid character in the range, surrogates are skipped
+    pub fn chars(self) -> Vec<char> {
+        (self.start..=self.end).filter_map(char::from_u32).collect()
+    }
+}
+
+impl FromStr for CharRange {
+    type Err = CharsetError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || CharsetError::InvalidRange(s.to_string());
+        let parse = |point: &str| {
+            let point = point.trim();
+            let point = point
+                .strip_prefix("U+")
+                .or_else(|| point.strip_prefix("u+"))
+                .unwrap_or(point);
+            u32::from_str_radix(point, 16).map_err(|_| invalid())
+        };
+
+        let (start, end) = match s.split_once('-') {
+            Some((start, end)) => (parse(start)?, parse(end)?),
+            None => (parse(s)?, parse(s)?),
+        };
+        if start > end || end > char::MAX as u32 {
+            return Err(invalid());
+        }
+        Ok(CharRange { start, end })
+    }
+}
+
+/// Sorted, deduplicated union of scripts and ranges
+pub fn build_charset(scripts: &[Script], ranges: &[CharRange]) -> Vec<char> {
+    let mut charset: Vec<char> = scripts
+        .iter()
+        .flat_map(|script| script.chars())
+        .chain(ranges.iter().flat_map(|range| range.chars()))
+        .collect();
+    charset.sort_unstable();
+    charset.dedup();
+    charset
+}
@@ -0,0 +1,82 @@
+use crate::{
+    keyspace::Keyspace,
+    mask::{Mask, MaskPosition},
+};
+
+/// Every word of one list joined to every word of another
+#[derive(Debug, Clone)]
+pub struct Combinator {
+    /// Words that go first
+    left: Vec<String>,
+    /// Words that go second
+    right: Vec<String>,
+    /// Put between the two words
+    separator: String,
+}
+
+impl Combinator {
+    /// Combines the lists, each left word with every right word in turn
+    pub fn new(left: Vec<String>, right: Vec<String>, separator: String) -> Self {
+        Combinator {
+            left,
+            right,
+            separator,
+        }
+    }
+}
+
+impl Keyspace for Combinator {
+    fn keyspace(&self) -> u128 {
+        self.left.len() as u128 * self.right.len() as u128
+    }
+
+    fn candidate(&self, index: u128) -> Option<String> {
+        let right_len = self.right.len() as u128;
+        let left = self
+            .left
+            .get(usize::try_from(index.checked_div(right_len)?).ok()?)?;
+        let right = &self.right[(index % right_len) as usize];
+        Some(format!("{left}{}{right}", self.separator))
+    }
+}
+
+/// Every word of a list with every candidate of a mask on one side
+#[derive(Debug, Clone)]
+pub struct Hybrid {
+    /// Dictionary words
+    words: Vec<String>,
+    /// Mask added to each word
+    mask: Mask,
+    /// Side of the word the mask goes on
+    position: MaskPosition,
+}
+
+impl Hybrid {
+    /// Adds every mask candidate to each word in turn
+    pub fn new(words: Vec<String>, mask: Mask, position: MaskPosition) -> Self {
+        Hybrid {
+            words,
+            mask,
+            position,
+        }
+    }
+}
+
+impl Keyspace for Hybrid {
+    /// Saturates instead of overflowing, since the indices past 128 bits could never be reached anyway
+    fn keyspace(&self) -> u128 {
+        (self.words.len() as u128).saturating_mul(self.mask.keyspace())
+    }
+
+    fn candidate(&self, index: u128) -> Option<String> {
+        let mask_len = self.mask.keyspace();
+        let word = self
+            .words
+            .get(usize::try_from(index.checked_div(mask_len)?).ok()?)?;
+        let mask = self.mask.candidate(index % mask_len)?;
+        Some(match self.position {
+            MaskPosition::Append => format!("{word}{mask}"),
+            MaskPosition::Prepend => format!("{mask}{word}"),
+        })
+    }
+}
@@ -0,0 +1,112 @@
+use std::{fmt, str::FromStr};
+
+use thiserror::Error;
+use unicode_normalization::UnicodeNormalization;
+
+/// Errors for text encoding
+#[derive(Error, Debug)]
+pub enum EncodingError {
+    #[error("Unknown normalization {0}, expected none, nfc or nfkc")]
+    UnknownNormalization(String),
+    #[error("Unknown encoding {0}, expected utf8, utf16le or latin1")]
+    UnknownEncoding(String),
+    #[error("Character {0:?} can not be encoded as Latin-1")]
+    NotLatin1(char),
+}
+
+/// Unicode normalization applied to a password before it is encoded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Normalization {
+    /// Leave the password as it is
+    #[default]
+    None,
+    /// Canonical composition, what most input methods produce
+    Nfc,
+    /// Compatibility composition, also folds look-alikes such as full width digits
+    Nfkc,
+}
+
+/// Byte encoding of a password fed to the hash function
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Encoding {
+    /// UTF-8, used by most Unix systems and web applications
+    #[default]
+    Utf8,
+    /// UTF-16 little endian, used by Windows (NTLM and friends)
+    Utf16Le,
+    /// ISO-8859-1, one byte per character, used by older systems
+    Latin1,
+}
+
+/// Normalizes and encodes a password into the bytes that get hashed
+pub fn encode_password(
+    password: &str,
+    normalization: Normalization,
+    encoding: Encoding,
+) -> Result<Vec<u8>, EncodingError> {
+    let normalized: String = match normalization {
+        Normalization::None => password.to_string(),
+        Normalization::Nfc => password.nfc().collect(),
+        Normalization::Nfkc => password.nfkc().collect(),
+    };
+
+    match encoding {
+        Encoding::Utf8 => Ok(normalized.into_bytes()),
+        Encoding::Utf16Le => Ok(normalized
+            .encode_utf16()
+            .flat_map(u16::to_le_bytes)
+            .collect()),
+        Encoding::Latin1 => normalized
+            .chars()
+            .map(|c| u8::try_from(c).map_err(|_| EncodingError::NotLatin1(c)))
+            .collect(),
+    }
+}
+
+impl FromStr for Normalization {
+    type Err = EncodingError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "none" => Ok(Normalization::None),
+            "nfc" => Ok(Normalization::Nfc),
+            "nfkc" => Ok(Normalization::Nfkc),
+            _ => Err(EncodingError::UnknownNormalization(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for Normalization {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Normalization::None => "none",
+            Normalization::Nfc => "nfc",
+            Normalization::Nfkc => "nfkc",
+        };
+        write!(f, "{name}")
+    }
+}
+
+impl FromStr for Encoding {
+    type Err = EncodingError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().replace('-', "").as_str() {
+            "utf8" => Ok(Encoding::Utf8),
+            "utf16le" => Ok(Encoding::Utf16Le),
+            "latin1" | "iso88591" => Ok(Encoding::Latin1),
+            _ => Err(EncodingError::UnknownEncoding(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Encoding::Utf8 => "utf8",
+            Encoding::Utf16Le => "utf16le",
+            Encoding::Latin1 => "latin1",
+        };
+        write!(f, "{name}")
+    }
+}
@@ -0,0 +1,278 @@
+use std::{fmt, num::NonZeroUsize, str::FromStr};
+
+use thiserror::Error;
+use tracing::trace;
+
+/// Errors for keyboard layouts
+#[derive(Error, Debug)]
+pub enum KeyboardError {
+    #[error("Unknown keyboard layout {0}, expected qwerty, azerty or qwertz")]
+    UnknownLayout(String),
+}
+
+/// Keyboard layouts walks can be made on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Layout {
+    /// US QWERTY
+    #[default]
+    Qwerty,
+    /// French AZERTY
+    Azerty,
+    /// German QWERTZ
+    Qwertz,
+}
+
+/// One keyboard row: keys without shift, keys with shift, and how far the row is shifted right
+type Row = (&'static str, &'static str, f64);
+
+/// Direction of a step between two adjacent keys: (rows down, columns right), each -1, 0 or 1
+pub type Direction = (i8, i8);
+
+impl Layout {
+    /// Rows from the number row down
+    fn rows(self) -> [Row; 4] {
+        match self {
+            Layout::Qwerty => [
+                ("`1234567890-=", "~!@#$%^&*()_+", 0.0),
+                ("qwertyuiop[]\\", "QWERTYUIOP{}|", 1.5),
+                ("asdfghjkl;'", "ASDFGHJKL:\"", 1.75),
+                ("zxcvbnm,./", "ZXCVBNM<>?", 2.25),
+            ],
+            Layout::Azerty => [
+                ("²&é\"'(-è_çà)=", "~1234567890°+", 0.0),
+                ("azertyuiop^$", "AZERTYUIOP¨£", 1.5),
+                ("qsdfghjklmù*", "QSDFGHJKLM%µ", 1.75),
+                ("<wxcvbn,;:!", ">WXCVBN?./§", 1.25),
+            ],
+            Layout::Qwertz => [
+                ("^1234567890ß´", "°!\"§$%&/()=?`", 0.0),
+                ("qwertzuiopü+", "QWERTZUIOPÜ*", 1.5),
+                ("asdfghjklöä#", "ASDFGHJKLÖÄ'", 1.75),
+                ("<yxcvbnm,.-", ">YXCVBNM;:_", 1.25),
+            ],
+        }
+    }
+
+    /// Row and horizontal position of a key, shifted or not
+    pub fn position(self, key: char) -> Option<(usize, f64)> {
+        self.rows()
+            .iter()
+            .enumerate()
+            .find_map(|(row, (plain, shifted, offset))| {
+                plain
+                    .chars()
+                    .position(|k| k == key)
+                    .or_else(|| shifted.chars().position(|k| k == key))
+                    .map(|column| (row, column as f64 + offset))
+            })
+    }
+
+    /// Direction from one key to another, None if they are not next to each other
+    pub fn step(self, from: char, to: char) -> Option<Direction> {
+        let (from_row, from_x) = self.position(from)?;
+        let (to_row, to_x) = self.position(to)?;
+        direction(from_row, from_x, to_row, to_x)
+    }
+
+    /// Keys without shift as (row, column)
+    fn keys(self) -> Vec<(usize, usize)> {
+        self.rows()
+            .iter()
+            .enumerate()
+            .flat_map(|(row, (plain, _, _))| (0..plain.chars().count()).map(move |c| (row, c)))
+            .collect()
+    }
+
+    /// Character on a key
+    fn key(self, (row, column): (usize, usize), shifted: bool) -> Option<char> {
+        let (plain, shift, _) = self.rows()[row];
+        let keys = if shifted { shift } else { plain };
+        keys.chars().nth(column)
+    }
+
+    /// Horizontal position of a key
+    fn x(self, (row, column): (usize, usize)) -> f64 {
+        column as f64 + self.rows()[row].2
+    }
+
+    /// Keys next to the given key with the direction of the step
+    fn neighbours(self, from: (usize, usize)) -> Vec<((usize, usize), Direction)> {
+        let from_x = self.x(from);
+        self.keys()
+            .into_iter()
+            .filter_map(|to| {
+                direction(from.0, from_x, to.0, self.x(to)).map(|direction| (to, direction))
+            })
+            .collect()
+    }
+}
+
+/// Direction between two key positions, None if they are not adjacent
+fn direction(from_row: usize, from_x: f64, to_row: usize, to_x: f64) -> Option<Direction> {
+    let rows = to_row as i64 - from_row as i64;
+    let dx = to_x - from_x;
+    let adjacent = match rows {
+        0 => (dx.abs() - 1.0).abs() < f64::EPSILON,
+        -1 | 1 => dx.abs() <= 1.0,
+        _ => false,
+    };
+    adjacent.then_some((rows as i8, dx.signum() as i8))
+}
+
+impl FromStr for Layout {
+    type Err = KeyboardError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "qwerty" => Ok(Layout::Qwerty),
+            "azerty" => Ok(Layout::Azerty),
+            "qwertz" => Ok(Layout::Qwertz),
+            _ => Err(KeyboardError::UnknownLayout(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for Layout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Layout::Qwerty => "qwerty",
+            Layout::Azerty => "azerty",
+            Layout::Qwertz => "qwertz",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// Shape of the keyboard walks to enumerate
+#[derive(Debug, Clone, Copy)]
+pub struct WalkOptions {
+    /// Layout to walk on
+    pub layout: Layout,
+    /// Keys pressed in one stroke
+    pub length: NonZeroUsize,
+    /// Most changes of direction allowed in a stroke
+    pub max_turns: usize,
+    /// Parallel strokes, each starting one key right of the last, like 1qaz2wsx
+    pub strokes: NonZeroUsize,
+    /// Also emit every walk with shift held down
+    pub shifted: bool,
+}
+
+/// Partial stroke waiting to be extended
+#[derive(Debug, Clone)]
+struct Partial {
+    /// Keys so far
+    keys: Vec<(usize, usize)>,
+    /// Direction of the last step
+    last: Option<Direction>,
+    /// Direction changes so far
+    turns: usize,
+}
+
+/// Iterator over every keyboard walk with the given shape
+///
+/// Walks are found depth first from every key in layout order, so the output
+/// is the same on every run.
+pub struct KeyboardWalks {
+    options: WalkOptions,
+    /// Strokes still to extend, last one first
+    stack: Vec<Partial>,
+    /// Walks found but not yet returned
+    pending: Vec<String>,
+}
+
+impl KeyboardWalks {
+    /// Starts enumerating walks
+    pub fn new(options: WalkOptions) -> Self {
+        trace!("New keyboard walks on {}", options.layout);
+        let stack = options
+            .layout
+            .keys()
+            .into_iter()
+            .rev()
+            .map(|key| Partial {
+                keys: vec![key],
+                last: None,
+                turns: 0,
+            })
+            .collect();
+        KeyboardWalks {
+            options,
+            stack,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Repeats a stroke one key to the right for every extra stroke, None if it runs off the keyboard
+    fn strokes(&self, keys: &[(usize, usize)]) -> Option<Vec<(usize, usize)>> {
+        let layout = self.options.layout;
+        let mut walk = Vec::with_capacity(keys.len() * self.options.strokes.get());
+        for stroke in 0..self.options.strokes.get() {
+            for &(row, column) in keys {
+                let key = (row, column + stroke);
+                layout.key(key, false)?;
+                walk.push(key);
+            }
+        }
+        Some(walk)
+    }
+}
+
+impl Iterator for KeyboardWalks {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        let layout = self.options.layout;
+        loop {
+            if let Some(walk) = self.pending.pop() {
+                return Some(walk);
+            }
+
+            let partial = self.stack.pop()?;
+            if partial.keys.len() == self.options.length.get() {
+                let Some(walk) = self.strokes(&partial.keys) else {
+                    continue;
+                };
+                // Shifted goes in first so it comes out second
+                if self.options.shifted {
+                    self.pending.push(
+                        walk.iter()
+                            .filter_map(|&key| layout.key(key, true))
+                            .collect(),
+                    );
+                }
+                self.pending.push(
+                    walk.iter()
+                        .filter_map(|&key| layout.key(key, false))
+                        .collect(),
+                );
+                continue;
+            }
+
+            let Some(&last_key) = partial.keys.last() else {
+                continue;
+            };
+            let mut children: Vec<Partial> = layout
+                .neighbours(last_key)
+                .into_iter()
+                .filter_map(|(key, direction)| {
+                    let turns = match partial.last {
+                        Some(last) if last != direction => partial.turns + 1,
+                        _ => partial.turns,
+                    };
+                    (turns <= self.options.max_turns).then(|| {
+                        let mut keys = partial.keys.clone();
+                        keys.push(key);
+                        Partial {
+                            keys,
+                            last: Some(direction),
+                            turns,
+                        }
+                    })
+                })
+                .collect();
+            children.reverse();
+            self.stack.extend(children);
+        }
+    }
+}
@@ -0,0 +1,44 @@
+use std::ops::Range;
+
+/// A finite candidate space where every candidate has an index
+///
+/// Indexing lets threads split the work into ranges instead of sharing one
+/// iterator, and lets an attack pick up again from an index.
+pub trait Keyspace: Send + Sync {
+    /// Number of candidates
+    fn keyspace(&self) -> u128;
+
+    /// Candidate at the index, None past the end
+    fn candidate(&self, index: u128) -> Option<String>;
+
+    /// Candidates in an index range, in index order
+    fn candidates(&self, range: Range<u128>) -> Box<dyn Iterator<Item = String> + '_> {
+        Box::new(range.map_while(move |index| self.candidate(index)))
+    }
+}
+
+/// Splits a range into consecutive ranges of at most `size` indices
+pub fn chunks(range: Range<u128>, size: u128) -> impl Iterator<Item = Range<u128>> {
+    let size = size.max(1);
+    let Range { start, end } = range;
+    (0..end.saturating_sub(start).div_ceil(size)).map(move |chunk| {
+        let chunk_start = start + chunk * size;
+        chunk_start..(chunk_start + size).min(end)
+    })
+}
+
+/// Splits 0..total into `parts` ranges whose lengths differ by at most one
+pub fn partition(total: u128, parts: usize) -> Vec<Range<u128>> {
+    let parts = parts.max(1) as u128;
+    let base = total / parts;
+    let extra = total % parts;
+    let mut start = 0;
+    (0..parts)
+        .map(|part| {
+            let len = base + u128::from(part < extra);
+            let range = start..start + len;
+            start += len;
+            range
+        })
+        .collect()
+}
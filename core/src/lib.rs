@@ -7,12 +7,43 @@ use hex::encode;
 use md5::{Digest, Md5};
 use rand::prelude::*;
 
-use std::collections::HashMap;
-
-use std::{fs::OpenOptions, io::Write, num::NonZeroUsize};
+use std::{
+    num::NonZeroUsize,
+    sync::atomic::{AtomicU64, Ordering},
+};
 
 use thiserror::Error;
 use tracing::{error, trace};
+
+use reduction::{Reducer, ReductionError};
+use table::{Chains, TableError, TableFormat, TableHeader};
+
+pub mod association;
+pub mod charset;
+pub mod combinator;
+pub mod encoding;
+pub mod keyboard;
+pub mod keyspace;
+pub mod markov;
+pub mod mask;
+pub mod pcfg;
+pub mod policy;
+pub mod potfile;
+pub mod prince;
+pub mod pronounceable;
+pub mod reduction;
+pub mod rules;
+pub mod session;
+pub mod strength;
+pub mod table;
+pub mod targets;
+pub mod unique;
+
+use charset::Script;
+use encoding::{encode_password, Encoding, EncodingError, Normalization};
+pub use policy::PasswordPolicy;
+use policy::{CompositionSampler, AMBIGUOUS_CHARS};
+
 /// Builder for password generator
 #[derive(Default, Debug)]
 pub struct PasswordGeneratorBuilder {
@@ -20,6 +51,10 @@ pub struct PasswordGeneratorBuilder {
     min_char: Option<NonZeroUsize>,
     /// Max chars for password
     max_char: Option<NonZeroUsize>,
+    /// Composition rules for the password
+    policy: PasswordPolicy,
+    /// Characters to build passwords from, printable ASCII if empty
+    charset: Vec<char>,
 }
 
 /// Implements builder for password generator
@@ -27,84 +62,191 @@ impl PasswordGeneratorBuilder {
     /// Set the minimum number of characters in the password
     pub fn with_min_char(self, min_char: Option<NonZeroUsize>) -> Self {
         trace!("With min char");
-        Self {
-            min_char,
-            max_char: self.max_char,
-        }
+        Self { min_char, ..self }
     }
 
     /// Set the maximum number of characters in the password
     pub fn with_max_char(self, max_char: Option<NonZeroUsize>) -> Self {
         trace!("With max char");
-        Self {
-            min_char: self.min_char,
-            max_char,
-        }
+        Self { max_char, ..self }
+    }
+
+    /// Set the composition policy every password has to satisfy
+    pub fn with_policy(self, policy: PasswordPolicy) -> Self {
+        trace!("With policy");
+        Self { policy, ..self }
+    }
+
+    /// Set the characters passwords are built from, see charset::build_charset for scripts and ranges
+    pub fn with_charset(self, charset: Vec<char>) -> Self {
+        trace!("With charset");
+        Self { charset, ..self }
     }
 
     /// Build a PasswordGenerator
     pub fn build(self) -> std::result::Result<PasswordGenerator, PasswordGeneratorError> {
         trace!("Build");
-        let password_generator = PasswordGenerator {
-            min_char: match self.min_char {
-                Some(min_char) => min_char.into(),
-                None => PasswordGenerator::default().min_char,
-            },
-
-            max_char: match self.max_char {
-                Some(max_char) => max_char.into(),
-                None => PasswordGenerator::default().max_char,
-            },
+        let min_char = match self.min_char {
+            Some(min_char) => min_char.into(),
+            None => PasswordGenerator::default().min_char,
+        };
+        let max_char = match self.max_char {
+            Some(max_char) => max_char.into(),
+            None => PasswordGenerator::default().max_char,
         };
         if self.min_char > self.max_char {
             error!("MinMax error");
             return Err(PasswordGeneratorError::MinMax {
-                min: password_generator.min_char,
-                max: password_generator.max_char,
+                min: min_char,
+                max: max_char,
+            });
+        }
+
+        // The class minimums must fit in the longest password
+        let required = self.policy.min_length();
+        if required > max_char {
+            error!("PolicyTooLong error");
+            return Err(PasswordGeneratorError::PolicyTooLong {
+                required,
+                max: max_char,
+            });
+        }
+
+        let charset = if self.charset.is_empty() {
+            Script::Ascii.chars()
+        } else {
+            self.charset
+        };
+        let mut charset: Vec<char> = charset
+            .into_iter()
+            .filter(|c| !(self.policy.exclude_ambiguous && AMBIGUOUS_CHARS.contains(*c)))
+            .collect();
+        charset.sort_unstable();
+        charset.dedup();
+        if charset.is_empty() {
+            error!("EmptyCharset error");
+            return Err(PasswordGeneratorError::EmptyCharset);
+        }
+        // A character that is a banned word on its own can never appear, so it is never drawn
+        charset.retain(|c| self.policy.accepts(&c.to_string()));
+        if charset.is_empty() {
+            error!("Unsatisfiable error");
+            return Err(PasswordGeneratorError::Unsatisfiable {
+                reason: "every character is banned",
             });
         }
-        Ok(password_generator)
+        // One character repeated min_char times breaks a shorter max repeat
+        if let (1, Some(max_repeat)) = (charset.len(), self.policy.max_repeat) {
+            if max_repeat.get() < min_char.max(required) {
+                error!("Unsatisfiable error");
+                return Err(PasswordGeneratorError::Unsatisfiable {
+                    reason: "max repeat is shorter than the password with only one character",
+                });
+            }
+        }
+        let sampler = CompositionSampler::new(&charset, &self.policy, max_char)?;
+
+        Ok(PasswordGenerator {
+            // Lengths too short for the policy can never produce a valid password
+            min_char: min_char.max(required),
+            max_char,
+            charset,
+            policy: self.policy,
+            sampler,
+        })
     }
 }
 
 /// Used to generate passwords
-#[derive(Clone, Copy)]
+#[derive(Clone)]
 pub struct PasswordGenerator {
     /// Min length of password
     min_char: usize,
     /// Max length of password
     max_char: usize,
+    /// Characters a password can be built from
+    charset: Vec<char>,
+    /// Composition rules for the password
+    policy: PasswordPolicy,
+    /// Draws passwords that meet the class minimums
+    sampler: CompositionSampler,
 }
 
 /// Default for password generator
 impl Default for PasswordGenerator {
     /// Min and max chars default to 4
     fn default() -> Self {
+        let charset = Script::Ascii.chars();
+        let policy = PasswordPolicy::default();
+        let sampler = match CompositionSampler::new(&charset, &policy, 4) {
+            Ok(sampler) => sampler,
+            Err(error) => unreachable!("Default policy is always satisfiable: {error}"),
+        };
         Self {
             min_char: 4,
             max_char: 4,
+            charset,
+            policy,
+            sampler,
         }
     }
 }
 
+/// Passwords drawn for one generate call before the policy is given up on
+const MAX_GENERATE_ATTEMPTS: usize = 1_000_000;
+
 /// Implement password generator
 impl PasswordGenerator {
-    /// Generates a password between min_char and max_char, containing valid ASCII (including punctation and spaces)
-    pub fn generate(self) -> String {
+    /// Generates a password between min_char and max_char, drawn from the charset (printable ASCII by default)
+    /// Passwords breaking the policy are thrown away and drawn again, so every valid password stays equally likely
+    pub fn generate(&self) -> std::result::Result<String, PasswordGeneratorError> {
         trace!("Generate");
         let mut rng = thread_rng();
 
-        // Randomly generates the size of the password
-        let length: usize = rng.gen_range(self.min_char..=self.max_char);
+        for _ in 0..MAX_GENERATE_ATTEMPTS {
+            // Randomly generates the size of the password
+            let length: usize = rng.gen_range(self.min_char..=self.max_char);
+
+            let password = if self.policy.min_length() == 0 {
+                // From 0..length picks a character from the charset, collects it into the password
+                (0..length)
+                    .map(|_| self.charset[rng.gen_range(0..self.charset.len())])
+                    .collect()
+            } else {
+                self.sampler.sample(&mut rng, length)
+            };
 
-        // Creates the password as a vector
-        // From 0..length generates a value between 32..=126 (all valid ASCII), converts it to a char, collects it into the vector
-        let password_vec: Vec<char> = (0..length)
-            .map(|_| rng.gen_range(32_u8..=126_u8) as char)
-            .collect();
+            if self.policy.accepts(&password) {
+                return Ok(password);
+            }
+            trace!("Password rejected by policy");
+        }
+        error!("TooManyRejections error");
+        Err(PasswordGeneratorError::TooManyRejections {
+            attempts: MAX_GENERATE_ATTEMPTS,
+        })
+    }
 
-        // Converts password as a vector into a string
-        password_vec.into_iter().collect()
+    /// Number of distinct passwords the generator can produce, None if it does not fit in a u128
+    /// Max repeat and banned words are not taken into account, so with those set this is an upper bound
+    pub fn keyspace(&self) -> Option<u128> {
+        (self.min_char..=self.max_char).try_fold(0_u128, |total, length| {
+            total.checked_add(self.sampler.count(length)?)
+        })
+    }
+
+    /// Checks that the generator can produce count distinct passwords
+    pub fn check_unique(&self, count: usize) -> std::result::Result<(), PasswordGeneratorError> {
+        match self.keyspace() {
+            Some(keyspace) if count as u128 > keyspace => {
+                error!("KeyspaceTooSmall error");
+                Err(PasswordGeneratorError::KeyspaceTooSmall {
+                    requested: count,
+                    keyspace,
+                })
+            }
+            _ => Ok(()),
+        }
     }
 }
 
@@ -113,12 +255,33 @@ impl PasswordGenerator {
 pub enum PasswordGeneratorError {
     #[error("Min length {min} must be less or equal to max length {max}")]
     MinMax { min: usize, max: usize },
+    #[error("Policy needs at least {required} characters but max length is {max}")]
+    PolicyTooLong { required: usize, max: usize },
+    #[error("Policy requires {class} characters but the charset has none")]
+    EmptyClass { class: &'static str },
+    #[error("Charset is empty")]
+    EmptyCharset,
+    #[error("Requested {requested} unique passwords but only {keyspace} exist")]
+    KeyspaceTooSmall { requested: usize, keyspace: u128 },
+    #[error("No password can satisfy the policy: {reason}")]
+    Unsatisfiable { reason: &'static str },
+    #[error("No password met the policy after {attempts} attempts")]
+    TooManyRejections { attempts: usize },
 }
 
+/// Algorithms accepted by HashGenerator::hash_password
+pub const SUPPORTED_ALGORITHMS: &[&str] = &["Md5", "Argon2"];
+
 /// Struct for my hash generator
 pub struct HashGenerator {
     pub password: String,
     pub hash_algorithm: String,
+    /// Unicode normalization applied before encoding
+    pub normalization: Normalization,
+    /// How the password is turned into bytes
+    pub encoding: Encoding,
+    /// Appended to the password before hashing, required by Argon2
+    pub salt: Option<Vec<u8>>,
 }
 /// implements hash generator
 impl HashGenerator {
@@ -127,19 +290,43 @@ impl HashGenerator {
         HashGenerator {
             password,
             hash_algorithm,
+            normalization: Normalization::default(),
+            encoding: Encoding::default(),
+            salt: None,
         }
     }
 
+    /// Set the salt, appended to the password for digest algorithms (hash(password || salt))
+    pub fn with_salt(self, salt: Option<Vec<u8>>) -> Self {
+        Self { salt, ..self }
+    }
+
+    /// Set the normalization applied before the password is encoded
+    pub fn with_normalization(self, normalization: Normalization) -> Self {
+        Self {
+            normalization,
+            ..self
+        }
+    }
+
+    /// Set the byte encoding the password is hashed in
+    pub fn with_encoding(self, encoding: Encoding) -> Self {
+        Self { encoding, ..self }
+    }
+
     /// function that hashes password with algorithms that have the digest and default trait
     fn simple_hash<T>(&self) -> Result<[u8; 16], HashErrors>
     where
         T: Digest + Default,
     {
-        let pass = self.password.clone();
+        let pass = encode_password(&self.password, self.normalization, self.encoding)?;
 
         let mut hasher = T::default();
 
-        hasher.update(pass.as_bytes());
+        hasher.update(&pass);
+        if let Some(salt) = &self.salt {
+            hasher.update(salt);
+        }
 
         let hash_bytes = hasher.finalize();
         let hash_bytes = hash_bytes.to_vec();
@@ -149,12 +336,25 @@ impl HashGenerator {
 
         Ok(ret)
     }
+    /// Argon2id with default parameters, the salt must be at least 8 bytes
+    fn argon2_hash(&self) -> Result<[u8; 16], HashErrors> {
+        let pass = encode_password(&self.password, self.normalization, self.encoding)?;
+        let salt = self.salt.as_deref().ok_or(HashErrors::Argon2Error)?;
+
+        let mut ret = [0_u8; 16];
+        argon2::Argon2::default()
+            .hash_password_into(&pass, salt, &mut ret)
+            .map_err(|_| HashErrors::Argon2Error)?;
+
+        Ok(ret)
+    }
     /// Function that pattern matches on the algorithm to call the right function
     /// Will return an error if the specified algorithm doesnt exist
     pub fn hash_password(&self) -> Result<[u8; 16], HashErrors> {
         trace!("Hash password");
         match self.hash_algorithm.as_str() {
             "Md5" => self.simple_hash::<Md5>(),
+            "Argon2" => self.argon2_hash(),
             /*
             "Sha2_256" => self.simple_hash::<sha2::Sha256>(),
             "Sha2_512" => self.simple_hash::<sha2::Sha512>(),
@@ -207,12 +407,30 @@ pub enum HashErrors {
     Argon2Error,
     #[error("Unsupported hash algorithm")]
     UnsupportedAlgorithm,
-    #[error("Hash not Present in Rainbow Table\n")]
-    NotInRainbowTable,
+    #[error(transparent)]
+    Encoding(#[from] EncodingError),
 }
 // ----------------------------------------------------------------------------------
 // Crack
 // ----------------------------------------------------------------------------------
+/// What looking a hash up in a rainbow table came to
+#[derive(Debug)]
+pub enum CrackOutcome {
+    /// A chain of the table passes through the hash
+    Found {
+        hash: [u8; 16],
+        password: String,
+        /// Link of the chain the password sits at, 0 for the chain start
+        chain_position: usize,
+        /// Chain ends that matched before this one without yielding the password
+        false_alarms: u64,
+    },
+    /// No chain of the table passes through the hash
+    NotFound,
+    /// Hashing failed part way through the lookup
+    Error(Error),
+}
+
 pub struct Crack {
     // Number of links in chain
     num_links: NonZeroUsize,
@@ -220,84 +438,143 @@ pub struct Crack {
     password_length: NonZeroUsize,
     // Hashing algorithm
     algorithm: String,
-
-    rainbow_table: HashMap<String, String>,
+    /// Which table of a set, picks the reduction functions
+    table_index: u64,
+    /// Reduction between links
+    reducer: Reducer,
+
+    rainbow_table: Chains,
+    /// Chain ends that matched without yielding the password
+    false_alarms: AtomicU64,
 }
 impl Crack {
+    /// Cracks with the charset reduction over printable ASCII
     pub fn new(
         num_links: NonZeroUsize,
         password_length: NonZeroUsize,
         algorithm: String,
-        rainbow_table: HashMap<String, String>,
-    ) -> Self {
-        Crack {
+        rainbow_table: impl Into<Chains>,
+    ) -> Result<Self, ReductionError> {
+        Ok(Crack {
             num_links,
             password_length,
             algorithm,
-            rainbow_table,
+            table_index: 0,
+            reducer: Reducer::ascii(password_length)?,
+            rainbow_table: rainbow_table.into(),
+            false_alarms: AtomicU64::new(0),
+        })
+    }
+
+    /// Cracks with the parameters a table header records
+    pub fn from_header(
+        header: &TableHeader,
+        rainbow_table: impl Into<Chains>,
+    ) -> Result<Self, TableError> {
+        Ok(Crack {
+            num_links: header.chain_length,
+            password_length: header.password_length,
+            algorithm: header.algorithm.clone(),
+            table_index: header.table_index,
+            reducer: header.reducer()?,
+            rainbow_table: rainbow_table.into(),
+            false_alarms: AtomicU64::new(0),
+        })
+    }
+
+    /// Cracks with the legacy reduction, for tables without a header
+    pub fn with_legacy_reduction(self) -> Result<Self, ReductionError> {
+        Ok(Self {
+            reducer: Reducer::legacy(self.password_length)?,
+            ..self
+        })
+    }
+
+    /// Cracks passwords of every length from `min_length` up to the password length
+    pub fn with_min_length(self, min_length: NonZeroUsize) -> Result<Self, ReductionError> {
+        Ok(Self {
+            reducer: self.reducer.clone().with_min_length(min_length)?,
+            ..self
+        })
+    }
+
+    /// Set which table of a set is being cracked with
+    pub fn with_table_index(self, table_index: u64) -> Self {
+        Self {
+            table_index,
+            ..self
         }
     }
 
     pub fn helper(&self, final_link_number: usize, first_pass: String) -> Result<String, Error> {
         // num links = 2 (2 reductions): string h-> hash r-> string h-> hash r-> string
         let mut password = first_pass;
-        let mut hash = [0_u8; 16];
-        let radix: u8 = 126_u8 - 32_u8;
-        let password_length: usize = self.password_length.into();
         //let final_link_number = final_link_number;
         for link_number in 0..final_link_number {
             trace!("{} hashing", link_number);
-            let hash_generator = HashGenerator {
-                password: password.clone(),
-                hash_algorithm: self.algorithm.clone(),
-            };
-            hash = hash_generator.hash_password()?;
+            let hash_generator = HashGenerator::new(password.clone(), self.algorithm.clone());
+            let hash = hash_generator.hash_password()?;
 
             trace!("{} reduce", link_number);
-            password = reduction(&hash, link_number as u128, radix, password_length as u32);
+            password = self.reducer.reduce(
+                &hash,
+                reduction_offset(self.table_index, self.num_links) + link_number as u128,
+            );
         }
 
         Ok(password)
     }
 
-    pub fn crack(&self, hash: &[u8; 16]) -> Result<String, Error> {
-        //let mut hash_clone = hash.clone();
-        let radix: u8 = 126 - 32;
-        //let mut link_number: usize = 0;
-        let password_length: usize = self.password_length.into();
-        //let mut link_number_clone = link_number.clone();
-
-        for link_number in 0..self.num_links.into() {
-            let mut hash_clone = *hash;
-
-            let mut link_number_clone = link_number;
+    /// Looks `hash` up in the table, walking it to a chain end from every
+    /// position, nearest the end first
+    ///
+    /// A matching end only means some chain merged with the walk, so the
+    /// password regenerated from its start is hashed and compared with
+    /// `hash`. Mismatches are false alarms: they are counted and the search
+    /// moves on to the next position.
+    pub fn crack(&self, hash: &[u8; 16]) -> CrackOutcome {
+        self.lookup(hash).unwrap_or_else(CrackOutcome::Error)
+    }
 
-            while link_number_clone < self.num_links.into() {
-                let pass = reduction(
-                    &hash_clone,
-                    link_number_clone as u128,
-                    radix,
-                    password_length as u32,
+    fn lookup(&self, hash: &[u8; 16]) -> Result<CrackOutcome, Error> {
+        let num_links: usize = self.num_links.into();
+        let offset = reduction_offset(self.table_index, self.num_links);
+        let mut false_alarms = 0;
+
+        for position in (0..num_links).rev() {
+            // Reduce as if the hash came from this position, then finish the chain
+            let mut end = self.reducer.reduce(hash, offset + position as u128);
+            for link_number in position + 1..num_links {
+                let hash_generator = HashGenerator::new(end, self.algorithm.clone());
+                end = self.reducer.reduce(
+                    &hash_generator.hash_password()?,
+                    offset + link_number as u128,
                 );
-                if self.rainbow_table.contains_key(&pass) {
-                    println!("test1");
-                    let hashed_pass =
-                        self.helper(link_number, self.rainbow_table[&pass].clone())?;
-                    return Ok(format!("{}\t{}\n", encode(hash), hashed_pass));
-                    //return Ok(format!("{}\t{}\n", encode(hash), self.rainbow_table[&pass]));
-                } else {
-                    let hash_generator = HashGenerator {
-                        password: pass.clone(),
-                        hash_algorithm: self.algorithm.clone(),
-                    };
-                    hash_clone = hash_generator.hash_password()?;
-                }
-                link_number_clone += 1;
             }
-            //error!("Hash Not Present in RainbowTable");
+
+            let Some(start) = self.rainbow_table.start_of(&end) else {
+                continue;
+            };
+            let password = self.helper(position, start)?;
+            let hash_generator = HashGenerator::new(password.clone(), self.algorithm.clone());
+            if hash_generator.hash_password()? == *hash {
+                return Ok(CrackOutcome::Found {
+                    hash: *hash,
+                    password,
+                    chain_position: position,
+                    false_alarms,
+                });
+            }
+            trace!("False alarm at position {position} for {}", encode(hash));
+            false_alarms += 1;
+            self.false_alarms.fetch_add(1, Ordering::Relaxed);
         }
-        Err(HashErrors::NotInRainbowTable.into())
-        // Err(HashErrors::NotInRainbowTable)
+        Ok(CrackOutcome::NotFound)
+    }
+
+    /// How many chain ends matched without yielding the password, over every crack so far
+    pub fn false_alarms(&self) -> u64 {
+        self.false_alarms.load(Ordering::Relaxed)
     }
 }
 
@@ -314,6 +591,10 @@ pub struct RainbowTable {
     password_length: NonZeroUsize,
     // Hashing algorithm
     algorithm: String,
+    /// Which table of a set, picks the reduction functions
+    table_index: u64,
+    /// Reduction between links
+    reducer: Reducer,
 }
 
 /// Struct for each rainbow table chain
@@ -338,15 +619,78 @@ impl Chain {
             last_pass,
         }
     }
+
+    /// Start and end of the chain
+    pub fn into_pair(self) -> (String, String) {
+        (self.first_pass, self.last_pass)
+    }
 }
 
 /// Implements rainbow table
 impl RainbowTable {
-    pub fn new(num_links: NonZeroUsize, password_length: NonZeroUsize, algorithm: String) -> Self {
-        RainbowTable {
+    pub fn new(
+        num_links: NonZeroUsize,
+        password_length: NonZeroUsize,
+        algorithm: String,
+    ) -> Result<Self, ReductionError> {
+        Ok(RainbowTable {
             num_links,
             password_length,
             algorithm,
+            table_index: 0,
+            reducer: Reducer::ascii(password_length)?,
+        })
+    }
+
+    /// Set the characters the reduction writes passwords with, printable ASCII by default
+    pub fn with_charset(self, charset: Vec<char>) -> Result<Self, ReductionError> {
+        Ok(Self {
+            reducer: Reducer::new(self.reducer.reduction(), charset, self.password_length)?
+                .with_min_length(self.reducer.min_length())?,
+            ..self
+        })
+    }
+
+    /// Reduce with the legacy reduction, as tables without a header were built
+    pub fn with_legacy_reduction(self) -> Result<Self, ReductionError> {
+        Ok(Self {
+            reducer: Reducer::legacy(self.password_length)?
+                .with_min_length(self.reducer.min_length())?,
+            ..self
+        })
+    }
+
+    /// Make chains of passwords of every length from `min_length` up to the password length
+    ///
+    /// Each length gets a share of the links as large as its share of the keyspace.
+    pub fn with_min_length(self, min_length: NonZeroUsize) -> Result<Self, ReductionError> {
+        Ok(Self {
+            reducer: self.reducer.clone().with_min_length(min_length)?,
+            ..self
+        })
+    }
+
+    /// Set which table of a set this is, so tables of one set use different reductions
+    pub fn with_table_index(self, table_index: u64) -> Self {
+        Self {
+            table_index,
+            ..self
+        }
+    }
+
+    /// Header describing a table of this many chains
+    pub fn header(&self, chains: u64) -> TableHeader {
+        TableHeader {
+            algorithm: self.algorithm.clone(),
+            chain_length: self.num_links,
+            password_length: self.password_length,
+            min_password_length: self.reducer.min_length(),
+            charset: self.reducer.charset().to_vec(),
+            reduction: self.reducer.reduction(),
+            reduction_version: self.reducer.version(),
+            table_index: self.table_index,
+            chains,
+            format: TableFormat::Text,
         }
     }
 
@@ -354,39 +698,18 @@ impl RainbowTable {
     pub fn generate_chain(self, mut password: String) -> Result<Chain, Error> {
         // num links = 2 (2 reductions): string h-> hash r-> string h-> hash r-> string
         let first_pass = password.clone();
-        let mut hash = [0_u8; 16];
-        let radix: u8 = 126_u8 - 32_u8;
-        let password_length: usize = self.password_length.into();
-        let mut chain_file = match OpenOptions::new()
-            .write(true)
-            .append(true)
-            .open("data/chain_file.txt")
-        {
-            Ok(file) => file,
-            Err(_) => todo!(),
-        };
-        write!(chain_file, "Chain: ")?;
         for link_number in 0..self.num_links.into() {
-            write!(chain_file, "{password}")?;
-            write!(chain_file, "|hash->")?;
             trace!("{} hashing", link_number);
-            let hash_generator = HashGenerator {
-                password: password.clone(),
-                hash_algorithm: self.algorithm.clone(),
-            };
-            hash = hash_generator.hash_password()?;
+            let hash_generator = HashGenerator::new(password.clone(), self.algorithm.clone());
+            let hash = hash_generator.hash_password()?;
 
-            chain_file.write_all(&hash)?;
-            write!(chain_file, "|reduce->")?;
             trace!("{} reduce", link_number);
-            password = reduction(&hash, link_number as u128, radix, password_length as u32);
+            password = self.reducer.reduce(
+                &hash,
+                reduction_offset(self.table_index, self.num_links) + link_number as u128,
+            );
         }
 
-        write!(chain_file, "{password}")?;
-        writeln!(chain_file, "\n\n")?;
-
-        //debug!("{full_chain}");
-
         Ok(Chain {
             first_pass,
             last_pass: password,
@@ -394,32 +717,40 @@ impl RainbowTable {
     }
 }
 
+/// First link number of a table, so each table of a set reduces differently
+fn reduction_offset(table_index: u64, num_links: NonZeroUsize) -> u128 {
+    u128::from(table_index) * usize::from(num_links) as u128
+}
+
 pub fn reduction(hash: &[u8; 16], link_number: u128, radix: u8, password_length: u32) -> String {
     // (h + k) mod (keyspace)
     // h = password hash as number
     // k = the number in the chain
     // keyspace is number of possible passwords
-    let password_num: u128 = u128::from_ne_bytes(*hash);
-    let password_num = password_num + link_number;
+    // Little endian, so tables agree across machines
+    let password_num: u128 = u128::from_le_bytes(*hash);
+    let password_num = password_num.wrapping_add(link_number);
 
     trace!("pre ^");
     trace!("Radix: {radix} Password length: {password_length}");
     // Ex: 8 lowercase char = 26^8
     let radix = radix as u128;
-    let keyspace = radix.pow(password_length);
     //let keyspace: u128 = radix.pow(password_length).into();
     //let keyspace: u128 = pow(radix.into(), password_length);
 
     trace!("pre %");
-    // the reduced password
-    let password_num = password_num % keyspace;
+    // the reduced password, every u128 is in a keyspace past 128 bits
+    let password_num = match radix.checked_pow(password_length) {
+        Some(keyspace) => password_num % keyspace,
+        None => password_num,
+    };
 
     // encode the numeric value to a valid password
     encoder(password_num, radix as u8)
     // = hash + link_number
 }
 
-fn encoder(mut num: u128, radix: u8) -> String {
+pub(crate) fn encoder(mut num: u128, radix: u8) -> String {
     let mut ret = String::new();
 
     while num > 0 {
@@ -0,0 +1,393 @@
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, HashMap},
+    io::{BufRead, Write},
+    sync::Arc,
+};
+
+use rand::prelude::*;
+use thiserror::Error;
+use tracing::{debug, error, trace};
+
+/// First line of every model file
+const MODEL_MAGIC: &str = "hashassin-markov v1";
+
+/// Marks the end of a word in the transition tables
+const END: char = '\n';
+
+/// Errors for Markov model training and loading
+#[derive(Error, Debug)]
+pub enum MarkovError {
+    #[error("Markov order must be between 1 and 3, got {0}")]
+    InvalidOrder(usize),
+    #[error("Wordlist contains no usable words")]
+    EmptyCorpus,
+    #[error("Invalid Markov model on line {line}: {reason}")]
+    InvalidModel { line: usize, reason: String },
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+/// Per position character transition statistics learned from a wordlist
+///
+/// The chance of a character depends on its position in the word and on the
+/// `order` characters before it.
+#[derive(Debug, Clone)]
+pub struct MarkovModel {
+    /// Number of previous characters each transition depends on
+    order: usize,
+    /// transitions[position][context] = (next char, count), most frequent first
+    transitions: Vec<HashMap<String, Vec<(char, u64)>>>,
+}
+
+/// Limits on the candidates a model emits
+#[derive(Debug, Clone, Copy)]
+pub struct MarkovOptions {
+    /// Shortest candidate
+    pub min_len: usize,
+    /// Longest candidate
+    pub max_len: usize,
+    /// Candidates less likely than this are never emitted
+    pub threshold: f64,
+}
+
+impl MarkovModel {
+    /// Counts the transitions of every word in the wordlist
+    pub fn train<I, S>(words: I, order: usize) -> Result<Self, MarkovError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        trace!("Train markov");
+        if !(1..=3).contains(&order) {
+            error!("InvalidOrder error");
+            return Err(MarkovError::InvalidOrder(order));
+        }
+
+        let mut counts: Vec<HashMap<String, HashMap<char, u64>>> = Vec::new();
+        for word in words {
+            let word = word.as_ref();
+            if word.is_empty() {
+                continue;
+            }
+
+            let chars: Vec<char> = word.chars().chain(std::iter::once(END)).collect();
+            if counts.len() < chars.len() {
+                counts.resize_with(chars.len(), HashMap::new);
+            }
+            for (position, next) in chars.iter().enumerate() {
+                let context = context_of(&chars[..position], order);
+                *counts[position]
+                    .entry(context)
+                    .or_default()
+                    .entry(*next)
+                    .or_default() += 1;
+            }
+        }
+
+        if counts.is_empty() {
+            error!("EmptyCorpus error");
+            return Err(MarkovError::EmptyCorpus);
+        }
+        debug!("Trained markov model over {} positions", counts.len());
+
+        let transitions = counts
+            .into_iter()
+            .map(|contexts| {
+                contexts
+                    .into_iter()
+                    .map(|(context, nexts)| (context, sorted_counts(nexts)))
+                    .collect()
+            })
+            .collect();
+
+        Ok(MarkovModel { order, transitions })
+    }
+
+    /// Number of previous characters each transition depends on
+    pub fn order(&self) -> usize {
+        self.order
+    }
+
+    /// Length of the longest word seen while training
+    pub fn max_len(&self) -> usize {
+        self.transitions.len().saturating_sub(1)
+    }
+
+    /// Writes the model in its text format
+    pub fn save<W: Write>(&self, mut writer: W) -> Result<(), MarkovError> {
+        writeln!(writer, "{MODEL_MAGIC}")?;
+        writeln!(writer, "order\t{}", self.order)?;
+        for (position, contexts) in self.transitions.iter().enumerate() {
+            for (context, nexts) in contexts {
+                for (next, count) in nexts {
+                    // Contexts and characters are hex encoded so tabs and spaces survive
+                    let next = if *next == END {
+                        String::new()
+                    } else {
+                        hex::encode(next.to_string())
+                    };
+                    writeln!(
+                        writer,
+                        "{position}\t{}\t{next}\t{count}",
+                        hex::encode(context)
+                    )?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads a model written by save
+    pub fn load<R: BufRead>(reader: R) -> Result<Self, MarkovError> {
+        trace!("Load markov");
+        let invalid = |line: usize, reason: &str| MarkovError::InvalidModel {
+            line,
+            reason: reason.to_string(),
+        };
+
+        let mut lines = reader.lines();
+        match lines.next() {
+            Some(Ok(magic)) if magic == MODEL_MAGIC => {}
+            Some(Err(error)) => return Err(error.into()),
+            _ => return Err(invalid(1, "missing header")),
+        }
+        let order = match lines.next() {
+            Some(Ok(line)) => match line.split_once('\t') {
+                Some(("order", order)) => order
+                    .parse::<usize>()
+                    .map_err(|_| invalid(2, "bad order"))?,
+                _ => return Err(invalid(2, "missing order")),
+            },
+            Some(Err(error)) => return Err(error.into()),
+            None => return Err(invalid(2, "missing order")),
+        };
+        if !(1..=3).contains(&order) {
+            return Err(MarkovError::InvalidOrder(order));
+        }
+
+        let mut counts: Vec<HashMap<String, HashMap<char, u64>>> = Vec::new();
+        for (index, line) in lines.enumerate() {
+            let line = line?;
+            let line_number = index + 3;
+            let fields: Vec<&str> = line.split('\t').collect();
+            let [position, context, next, count] = fields[..] else {
+                return Err(invalid(line_number, "expected 4 fields"));
+            };
+
+            let position: usize = position
+                .parse()
+                .map_err(|_| invalid(line_number, "bad position"))?;
+            let context = hex::decode(context)
+                .ok()
+                .and_then(|bytes| String::from_utf8(bytes).ok())
+                .ok_or_else(|| invalid(line_number, "bad context"))?;
+            let next = if next.is_empty() {
+                END
+            } else {
+                hex::decode(next)
+                    .ok()
+                    .and_then(|bytes| String::from_utf8(bytes).ok())
+                    .and_then(|next| next.chars().next())
+                    .ok_or_else(|| invalid(line_number, "bad character"))?
+            };
+            let count: u64 = count
+                .parse()
+                .map_err(|_| invalid(line_number, "bad count"))?;
+
+            if counts.len() <= position {
+                counts.resize_with(position + 1, HashMap::new);
+            }
+            *counts[position]
+                .entry(context)
+                .or_default()
+                .entry(next)
+                .or_default() += count;
+        }
+
+        if counts.is_empty() {
+            return Err(MarkovError::EmptyCorpus);
+        }
+
+        let transitions = counts
+            .into_iter()
+            .map(|contexts| {
+                contexts
+                    .into_iter()
+                    .map(|(context, nexts)| (context, sorted_counts(nexts)))
+                    .collect()
+            })
+            .collect();
+
+        Ok(MarkovModel { order, transitions })
+    }
+
+    /// Possible next characters with their probabilities after the given prefix
+    fn next_chars(&self, prefix: &[char]) -> Vec<(char, f64)> {
+        let Some(contexts) = self.transitions.get(prefix.len()) else {
+            return Vec::new();
+        };
+        let Some(nexts) = contexts.get(&context_of(prefix, self.order)) else {
+            return Vec::new();
+        };
+        let total: u64 = nexts.iter().map(|(_, count)| count).sum();
+        nexts
+            .iter()
+            .map(|(next, count)| (*next, *count as f64 / total as f64))
+            .collect()
+    }
+
+    /// Chance of the model producing exactly this word
+    pub fn probability(&self, word: &str) -> f64 {
+        let chars: Vec<char> = word.chars().chain(std::iter::once(END)).collect();
+        let mut probability = 1.0;
+        for position in 0..chars.len() {
+            let next = chars[position];
+            probability *= self
+                .next_chars(&chars[..position])
+                .into_iter()
+                .find(|(c, _)| *c == next)
+                .map_or(0.0, |(_, p)| p);
+        }
+        probability
+    }
+
+    /// Candidates from most to least likely
+    pub fn ordered(self: &Arc<Self>, options: MarkovOptions) -> MarkovOrdered {
+        let mut queue = BinaryHeap::new();
+        queue.push(Prefix {
+            log_probability: 0.0,
+            chars: Vec::new(),
+            done: false,
+        });
+        MarkovOrdered {
+            model: Arc::clone(self),
+            options,
+            queue,
+        }
+    }
+
+    /// Draws one random candidate, None if it broke the options
+    pub fn sample<R: Rng>(&self, rng: &mut R, options: &MarkovOptions) -> Option<String> {
+        let mut chars = Vec::new();
+        let mut probability = 1.0;
+        loop {
+            let nexts = self.next_chars(&chars);
+            let mut target = rng.gen::<f64>();
+            let (next, p) = *nexts
+                .iter()
+                .find(|(_, p)| {
+                    target -= p;
+                    target < 0.0
+                })
+                .or(nexts.last())?;
+
+            probability *= p;
+            if next == END {
+                break;
+            }
+            chars.push(next);
+            if chars.len() > options.max_len || probability < options.threshold {
+                return None;
+            }
+        }
+
+        (chars.len() >= options.min_len && probability >= options.threshold)
+            .then(|| chars.into_iter().collect())
+    }
+}
+
+/// The last `order` characters of a prefix
+fn context_of(prefix: &[char], order: usize) -> String {
+    prefix[prefix.len().saturating_sub(order)..]
+        .iter()
+        .collect()
+}
+
+/// Counts sorted from most to least frequent, ties broken by character
+fn sorted_counts(nexts: HashMap<char, u64>) -> Vec<(char, u64)> {
+    let mut nexts: Vec<(char, u64)> = nexts.into_iter().collect();
+    nexts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    nexts
+}
+
+/// Partial candidate waiting in the search queue
+#[derive(Debug)]
+struct Prefix {
+    /// ln of the chance of reaching this prefix
+    log_probability: f64,
+    /// Characters so far
+    chars: Vec<char>,
+    /// Whether the word has ended
+    done: bool,
+}
+
+impl PartialEq for Prefix {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Prefix {}
+
+impl PartialOrd for Prefix {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Prefix {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.log_probability
+            .total_cmp(&other.log_probability)
+            .then_with(|| other.chars.cmp(&self.chars))
+    }
+}
+
+/// Iterator over candidates from most to least likely
+///
+/// Probabilities only shrink as a prefix grows, so always expanding the most
+/// likely prefix first yields finished words in exact descending order.
+pub struct MarkovOrdered {
+    model: Arc<MarkovModel>,
+    options: MarkovOptions,
+    queue: BinaryHeap<Prefix>,
+}
+
+impl Iterator for MarkovOrdered {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        let log_threshold = self.options.threshold.ln();
+        while let Some(prefix) = self.queue.pop() {
+            if prefix.done {
+                return Some(prefix.chars.into_iter().collect());
+            }
+
+            for (next, p) in self.model.next_chars(&prefix.chars) {
+                let log_probability = prefix.log_probability + p.ln();
+                if log_probability < log_threshold {
+                    continue;
+                }
+                if next == END {
+                    if prefix.chars.len() >= self.options.min_len {
+                        self.queue.push(Prefix {
+                            log_probability,
+                            chars: prefix.chars.clone(),
+                            done: true,
+                        });
+                    }
+                } else if prefix.chars.len() < self.options.max_len {
+                    let mut chars = prefix.chars.clone();
+                    chars.push(next);
+                    self.queue.push(Prefix {
+                        log_probability,
+                        chars,
+                        done: false,
+                    });
+                }
+            }
+        }
+        None
+    }
+}
@@ -0,0 +1,210 @@
+use std::{fmt, str::FromStr};
+
+use num::{BigUint, ToPrimitive};
+use thiserror::Error;
+use tracing::trace;
+
+use crate::keyspace::Keyspace;
+
+/// Characters in hashcat's ?s
+const SYMBOLS: &str = " !\"#$%&'()*+,-./:;<=>?@[\\]^_`{|}~";
+
+/// Errors for mask parsing
+#[derive(Error, Debug)]
+pub enum MaskError {
+    #[error("Invalid mask {mask:?}: {reason}")]
+    InvalidMask { mask: String, reason: String },
+    #[error("Unknown mask position {0}, expected append or prepend")]
+    UnknownPosition(String),
+}
+
+/// Characters a built in placeholder stands for
+fn builtin(placeholder: char) -> Option<Vec<char>> {
+    let chars = match placeholder {
+        'l' => ('a'..='z').collect(),
+        'u' => ('A'..='Z').collect(),
+        'd' => ('0'..='9').collect(),
+        'h' => ('0'..='9').chain('a'..='f').collect(),
+        'H' => ('0'..='9').chain('A'..='F').collect(),
+        's' => SYMBOLS.chars().collect(),
+        'a' => ('a'..='z')
+            .chain('A'..='Z')
+            .chain('0'..='9')
+            .chain(SYMBOLS.chars())
+            .collect(),
+        _ => return None,
+    };
+    Some(chars)
+}
+
+/// Expands placeholders in a mask or custom charset into one charset per position
+fn expand(mask: &str, custom: &[Vec<char>]) -> Result<Vec<Vec<char>>, MaskError> {
+    let invalid = |reason: String| MaskError::InvalidMask {
+        mask: mask.to_string(),
+        reason,
+    };
+
+    let mut positions = Vec::new();
+    let mut chars = mask.chars();
+    while let Some(c) = chars.next() {
+        if c != '?' {
+            positions.push(vec![c]);
+            continue;
+        }
+        let placeholder = chars
+            .next()
+            .ok_or_else(|| invalid("trailing ?".to_string()))?;
+        let charset = match placeholder {
+            '?' => vec!['?'],
+            '1'..='9' => {
+                let index = placeholder as usize - '1' as usize;
+                custom
+                    .get(index)
+                    .cloned()
+                    .ok_or_else(|| invalid(format!("custom charset ?{placeholder} is not set")))?
+            }
+            _ => builtin(placeholder)
+                .ok_or_else(|| invalid(format!("unknown placeholder ?{placeholder}")))?,
+        };
+        positions.push(charset);
+    }
+    Ok(positions)
+}
+
+/// Parses a custom charset like `?l?d_` into a sorted, deduplicated charset
+pub fn custom_charset(charset: &str) -> Result<Vec<char>, MaskError> {
+    let mut chars: Vec<char> = expand(charset, &[])?.into_iter().flatten().collect();
+    chars.sort_unstable();
+    chars.dedup();
+    if chars.is_empty() {
+        return Err(MaskError::InvalidMask {
+            mask: charset.to_string(),
+            reason: "empty charset".to_string(),
+        });
+    }
+    Ok(chars)
+}
+
+/// A hashcat style mask like `?u?l?l?l?d?d`, one charset per position
+///
+/// Placeholders: ?l lower, ?u upper, ?d digit, ?h/?H hex, ?s symbol, ?a all of
+/// those, ?1 to ?9 custom charsets and ?? for a literal ?. Anything else is a
+/// literal character.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mask {
+    /// Charset of each position
+    positions: Vec<Vec<char>>,
+    /// Number of candidates, which can be past 128 bits
+    size: BigUint,
+    /// The size capped at u128::MAX, for indexing
+    keyspace: u128,
+    /// The mask as written
+    source: String,
+}
+
+impl Mask {
+    /// Parses a mask that may use custom charsets ?1, ?2, ...
+    pub fn parse(mask: &str, custom: &[Vec<char>]) -> Result<Self, MaskError> {
+        trace!("Parse mask");
+        let positions = expand(mask, custom)?;
+        let size: BigUint = positions
+            .iter()
+            .map(|charset| BigUint::from(charset.len()))
+            .product();
+        Ok(Mask {
+            positions,
+            keyspace: size.to_u128().unwrap_or(u128::MAX),
+            size,
+            source: mask.to_string(),
+        })
+    }
+
+    /// Number of characters in every candidate
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    /// Whether the mask has no positions
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    /// Exact number of candidates, which [`Keyspace::keyspace`] caps at 128 bits
+    pub fn size(&self) -> &BigUint {
+        &self.size
+    }
+}
+
+impl FromStr for Mask {
+    type Err = MaskError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Mask::parse(s, &[])
+    }
+}
+
+impl fmt::Display for Mask {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+impl Keyspace for Mask {
+    /// Saturates instead of overflowing, since the indices past 128 bits could never be reached anyway
+    fn keyspace(&self) -> u128 {
+        self.keyspace
+    }
+
+    /// The last position changes fastest, so index 0 is the first character of every charset
+    fn candidate(&self, index: u128) -> Option<String> {
+        if index >= self.keyspace {
+            return None;
+        }
+        let mut rest = index;
+        let mut chars: Vec<char> = self
+            .positions
+            .iter()
+            .rev()
+            .map(|charset| {
+                let radix = charset.len() as u128;
+                let c = charset[(rest % radix) as usize];
+                rest /= radix;
+                c
+            })
+            .collect();
+        chars.reverse();
+        Some(chars.into_iter().collect())
+    }
+}
+
+/// Which side of the word a hybrid attack puts the mask on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MaskPosition {
+    /// word then mask, like password?d?d
+    #[default]
+    Append,
+    /// mask then word, like ?d?d?dpassword
+    Prepend,
+}
+
+impl FromStr for MaskPosition {
+    type Err = MaskError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "append" => Ok(MaskPosition::Append),
+            "prepend" => Ok(MaskPosition::Prepend),
+            _ => Err(MaskError::UnknownPosition(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for MaskPosition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            MaskPosition::Append => "append",
+            MaskPosition::Prepend => "prepend",
+        };
+        write!(f, "{name}")
+    }
+}
@@ -0,0 +1,449 @@
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, HashMap},
+    fmt,
+    io::{BufRead, Write},
+    sync::Arc,
+};
+
+use thiserror::Error;
+use tracing::{debug, error, trace};
+
+/// First line of every grammar file
+const GRAMMAR_MAGIC: &str = "hashassin-pcfg v1";
+
+/// Errors for PCFG training and loading
+#[derive(Error, Debug)]
+pub enum PcfgError {
+    #[error("Password corpus contains no usable passwords")]
+    EmptyCorpus,
+    #[error("Invalid PCFG grammar on line {line}: {reason}")]
+    InvalidGrammar { line: usize, reason: String },
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+/// Character class of a segment in a base structure
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+enum Class {
+    /// Letters
+    Letter,
+    /// Digits
+    Digit,
+    /// Everything else
+    Symbol,
+}
+
+impl Class {
+    /// Class of a single character
+    fn of(c: char) -> Self {
+        if c.is_ascii_digit() {
+            Class::Digit
+        } else if c.is_alphabetic() {
+            Class::Letter
+        } else {
+            Class::Symbol
+        }
+    }
+
+    /// Letter used for the class in structures
+    fn symbol(self) -> char {
+        match self {
+            Class::Letter => 'L',
+            Class::Digit => 'D',
+            Class::Symbol => 'S',
+        }
+    }
+}
+
+/// One run of same class characters in a base structure, like D2
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+struct Segment {
+    /// Class of every character in the run
+    class: Class,
+    /// Number of characters in the run
+    len: usize,
+}
+
+impl fmt::Display for Segment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.class.symbol(), self.len)
+    }
+}
+
+/// Parses a segment like L6
+fn parse_segment(segment: &str) -> Option<Segment> {
+    let mut chars = segment.chars();
+    let class = match chars.next()? {
+        'L' => Class::Letter,
+        'D' => Class::Digit,
+        'S' => Class::Symbol,
+        _ => return None,
+    };
+    let len = chars.as_str().parse().ok().filter(|len| *len > 0)?;
+    Some(Segment { class, len })
+}
+
+/// Parses a base structure like L6D2S1
+fn parse_structure(structure: &str) -> Option<Vec<Segment>> {
+    let mut segments = Vec::new();
+    let mut start = 0;
+    for (index, c) in structure.char_indices().skip(1) {
+        if c.is_ascii_alphabetic() {
+            segments.push(parse_segment(&structure[start..index])?);
+            start = index;
+        }
+    }
+    segments.push(parse_segment(&structure[start..])?);
+    Some(segments)
+}
+
+/// Splits a password into its base structure and the terminal for each segment
+fn segment(password: &str) -> Vec<(Segment, String)> {
+    let mut segments: Vec<(Segment, String)> = Vec::new();
+    for c in password.chars() {
+        let class = Class::of(c);
+        match segments.last_mut() {
+            Some((segment, terminal)) if segment.class == class => {
+                segment.len += 1;
+                terminal.push(c);
+            }
+            _ => segments.push((Segment { class, len: 1 }, c.to_string())),
+        }
+    }
+    segments
+}
+
+/// Writes a structure as its usual name, like L6D2S1
+fn structure_name(structure: &[Segment]) -> String {
+    structure.iter().map(Segment::to_string).collect()
+}
+
+/// Probabilistic context free grammar learned from cracked passwords
+///
+/// A password is a base structure such as L6D2S1 whose segments are each
+/// filled with a terminal, like "monkey", "12" and "!". Base structures and
+/// terminals are picked independently with the frequencies seen in training.
+#[derive(Debug, Clone)]
+pub struct Pcfg {
+    /// Base structures with their probabilities, most likely first
+    structures: Vec<(Vec<Segment>, f64)>,
+    /// Terminals for each segment with their probabilities, most likely first
+    terminals: HashMap<Segment, Vec<(String, f64)>>,
+}
+
+/// Limits on the guesses a grammar emits
+#[derive(Debug, Clone, Copy)]
+pub struct PcfgOptions {
+    /// Shortest guess
+    pub min_len: usize,
+    /// Longest guess
+    pub max_len: usize,
+    /// Guesses less likely than this are never emitted
+    pub threshold: f64,
+}
+
+impl Pcfg {
+    /// Learns structures and terminals from a corpus of cracked passwords
+    pub fn train<I, S>(passwords: I) -> Result<Self, PcfgError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        trace!("Train pcfg");
+        let mut structures: HashMap<Vec<Segment>, u64> = HashMap::new();
+        let mut terminals: HashMap<Segment, HashMap<String, u64>> = HashMap::new();
+        for password in passwords {
+            let segments = segment(password.as_ref());
+            if segments.is_empty() {
+                continue;
+            }
+            *structures
+                .entry(segments.iter().map(|(segment, _)| *segment).collect())
+                .or_default() += 1;
+            for (segment, terminal) in segments {
+                *terminals
+                    .entry(segment)
+                    .or_default()
+                    .entry(terminal)
+                    .or_default() += 1;
+            }
+        }
+
+        if structures.is_empty() {
+            error!("EmptyCorpus error");
+            return Err(PcfgError::EmptyCorpus);
+        }
+        debug!("Learned {} base structures", structures.len());
+
+        Ok(Pcfg::from_counts(structures, terminals))
+    }
+
+    /// Turns counts into probabilities sorted from most to least likely
+    fn from_counts(
+        structures: HashMap<Vec<Segment>, u64>,
+        terminals: HashMap<Segment, HashMap<String, u64>>,
+    ) -> Self {
+        Pcfg {
+            structures: normalize(structures),
+            terminals: terminals
+                .into_iter()
+                .map(|(segment, counts)| (segment, normalize(counts)))
+                .collect(),
+        }
+    }
+
+    /// Number of base structures learned
+    pub fn num_structures(&self) -> usize {
+        self.structures.len()
+    }
+
+    /// Writes the grammar in its text format
+    pub fn save<W: Write>(&self, mut writer: W) -> Result<(), PcfgError> {
+        writeln!(writer, "{GRAMMAR_MAGIC}")?;
+        for (structure, probability) in &self.structures {
+            writeln!(writer, "S\t{}\t{probability:e}", structure_name(structure))?;
+        }
+        for (segment, terminals) in &self.terminals {
+            for (terminal, probability) in terminals {
+                // Terminals are hex encoded so tabs and spaces survive
+                writeln!(
+                    writer,
+                    "T\t{segment}\t{}\t{probability:e}",
+                    hex::encode(terminal)
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads a grammar written by save
+    pub fn load<R: BufRead>(reader: R) -> Result<Self, PcfgError> {
+        trace!("Load pcfg");
+        let invalid = |line: usize, reason: &str| PcfgError::InvalidGrammar {
+            line,
+            reason: reason.to_string(),
+        };
+
+        let mut lines = reader.lines();
+        match lines.next() {
+            Some(Ok(magic)) if magic == GRAMMAR_MAGIC => {}
+            Some(Err(error)) => return Err(error.into()),
+            _ => return Err(invalid(1, "missing header")),
+        }
+
+        let mut structures = Vec::new();
+        let mut terminals: HashMap<Segment, Vec<(String, f64)>> = HashMap::new();
+        for (index, line) in lines.enumerate() {
+            let line = line?;
+            let line_number = index + 2;
+            let fields: Vec<&str> = line.split('\t').collect();
+            let parse_probability = |field: &str| {
+                field
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|p| (0.0..=1.0).contains(p))
+                    .ok_or_else(|| invalid(line_number, "bad probability"))
+            };
+            match fields[..] {
+                ["S", structure, probability] => {
+                    let structure = parse_structure(structure)
+                        .ok_or_else(|| invalid(line_number, "bad structure"))?;
+                    structures.push((structure, parse_probability(probability)?));
+                }
+                ["T", segment, terminal, probability] => {
+                    let segment = parse_segment(segment)
+                        .ok_or_else(|| invalid(line_number, "bad segment"))?;
+                    let terminal = hex::decode(terminal)
+                        .ok()
+                        .and_then(|bytes| String::from_utf8(bytes).ok())
+                        .ok_or_else(|| invalid(line_number, "bad terminal"))?;
+                    terminals
+                        .entry(segment)
+                        .or_default()
+                        .push((terminal, parse_probability(probability)?));
+                }
+                _ => return Err(invalid(line_number, "unknown line")),
+            }
+        }
+
+        // Every segment used by a structure needs terminals to fill it
+        if let Some((structure, _)) = structures
+            .iter()
+            .find(|(structure, _)| structure.iter().any(|s| !terminals.contains_key(s)))
+        {
+            return Err(invalid(
+                0,
+                &format!("no terminals for structure {}", structure_name(structure)),
+            ));
+        }
+        if structures.is_empty() {
+            return Err(PcfgError::EmptyCorpus);
+        }
+
+        sort_by_probability(&mut structures);
+        for list in terminals.values_mut() {
+            sort_by_probability(list);
+        }
+        Ok(Pcfg {
+            structures,
+            terminals,
+        })
+    }
+
+    /// Chance of the grammar producing exactly this password
+    pub fn probability(&self, password: &str) -> f64 {
+        let segments = segment(password);
+        let structure: Vec<Segment> = segments.iter().map(|(segment, _)| *segment).collect();
+        let Some((_, mut probability)) = self.structures.iter().find(|(s, _)| *s == structure)
+        else {
+            return 0.0;
+        };
+        for (segment, terminal) in &segments {
+            probability *= self.terminals[segment]
+                .iter()
+                .find(|(t, _)| t == terminal)
+                .map_or(0.0, |(_, p)| *p);
+        }
+        probability
+    }
+
+    /// Guesses from most to least likely
+    pub fn guesses(self: &Arc<Self>, options: PcfgOptions) -> PcfgGuesses {
+        let mut queue = BinaryHeap::new();
+        for (index, (structure, probability)) in self.structures.iter().enumerate() {
+            let len: usize = structure.iter().map(|s| s.len).sum();
+            if len < options.min_len || len > options.max_len {
+                continue;
+            }
+            let state = State {
+                probability: 0.0,
+                structure: index,
+                terminals: vec![0; structure.len()],
+                pivot: 0,
+            };
+            let probability = probability * self.terminal_probability(&state);
+            queue.push(State {
+                probability,
+                ..state
+            });
+        }
+
+        PcfgGuesses {
+            grammar: Arc::clone(self),
+            threshold: options.threshold,
+            queue,
+        }
+    }
+
+    /// Product of the chances of the terminals a state picked
+    fn terminal_probability(&self, state: &State) -> f64 {
+        let (structure, _) = &self.structures[state.structure];
+        structure
+            .iter()
+            .zip(&state.terminals)
+            .map(|(segment, &index)| self.terminals[segment][index].1)
+            .product()
+    }
+}
+
+/// Probabilities for counts, sorted from most to least likely
+fn normalize<K: Ord>(counts: HashMap<K, u64>) -> Vec<(K, f64)> {
+    let total: u64 = counts.values().sum();
+    let mut list: Vec<(K, f64)> = counts
+        .into_iter()
+        .map(|(key, count)| (key, count as f64 / total as f64))
+        .collect();
+    sort_by_probability(&mut list);
+    list
+}
+
+/// Sorts from most to least likely, ties broken by key so output is stable
+fn sort_by_probability<K: Ord>(list: &mut [(K, f64)]) {
+    list.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+}
+
+/// A base structure with one terminal picked for each segment
+#[derive(Debug)]
+struct State {
+    /// Chance of this guess
+    probability: f64,
+    /// Index of the base structure
+    structure: usize,
+    /// Index of the terminal picked for each segment
+    terminals: Vec<usize>,
+    /// Children only move terminals at or after this segment, so no state is queued twice
+    pivot: usize,
+}
+
+impl PartialEq for State {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for State {}
+
+impl PartialOrd for State {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for State {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.probability
+            .total_cmp(&other.probability)
+            .then_with(|| other.structure.cmp(&self.structure))
+            .then_with(|| other.terminals.cmp(&self.terminals))
+    }
+}
+
+/// Iterator over guesses from most to least likely
+///
+/// This is the "next" algorithm from Weir et al.: each popped state queues the
+/// states that swap one terminal at or after its pivot for the next less likely
+/// one. Children are never more likely than their parent, so guesses come out
+/// in descending order.
+pub struct PcfgGuesses {
+    grammar: Arc<Pcfg>,
+    threshold: f64,
+    queue: BinaryHeap<State>,
+}
+
+impl Iterator for PcfgGuesses {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        let state = self.queue.pop()?;
+        if state.probability < self.threshold {
+            self.queue.clear();
+            return None;
+        }
+
+        let (structure, structure_probability) = &self.grammar.structures[state.structure];
+        for position in state.pivot..state.terminals.len() {
+            if state.terminals[position] + 1 < self.grammar.terminals[&structure[position]].len() {
+                let mut terminals = state.terminals.clone();
+                terminals[position] += 1;
+                let mut child = State {
+                    probability: 0.0,
+                    structure: state.structure,
+                    terminals,
+                    pivot: position,
+                };
+                child.probability =
+                    structure_probability * self.grammar.terminal_probability(&child);
+                self.queue.push(child);
+            }
+        }
+
+        Some(
+            structure
+                .iter()
+                .zip(&state.terminals)
+                .map(|(segment, &index)| self.grammar.terminals[segment][index].0.as_str())
+                .collect(),
+        )
+    }
+}
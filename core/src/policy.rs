@@ -0,0 +1,266 @@
+use rand::prelude::*;
+use std::num::NonZeroUsize;
+
+use tracing::{error, trace};
+
+use crate::PasswordGeneratorError;
+
+/// Characters that are easy to confuse with one another when read or typed
+pub const AMBIGUOUS_CHARS: &str = "0Oo1lI|`'\"";
+
+/// Number of character classes a policy can put a lower bound on
+const NUM_CLASSES: usize = 4;
+
+/// Names of the character classes, used in error messages
+const CLASS_NAMES: [&str; NUM_CLASSES] = ["uppercase", "lowercase", "digit", "symbol"];
+
+/// ln of the weight of taking count characters from a class of the given size: size^count / count!
+fn ln_term(count: usize, ln_size: f64, ln_fact: &[f64]) -> f64 {
+    if count == 0 {
+        0.0
+    } else {
+        count as f64 * ln_size - ln_fact[count]
+    }
+}
+
+/// Composition rules a generated password has to satisfy
+#[derive(Default, Debug, Clone)]
+pub struct PasswordPolicy {
+    /// Minimum number of uppercase letters
+    pub min_upper: usize,
+    /// Minimum number of lowercase letters
+    pub min_lower: usize,
+    /// Minimum number of digits
+    pub min_digit: usize,
+    /// Minimum number of symbols (punctuation and space)
+    pub min_symbol: usize,
+    /// Longest allowed run of the same character, unlimited if None
+    pub max_repeat: Option<NonZeroUsize>,
+    /// Leave look-alike characters such as 0/O and l/1 out of the charset
+    pub exclude_ambiguous: bool,
+    /// Words that may not appear anywhere in the password (case insensitive)
+    pub banned_words: Vec<String>,
+}
+
+impl PasswordPolicy {
+    /// Minimum count for each character class
+    fn minimums(&self) -> [usize; NUM_CLASSES] {
+        [
+            self.min_upper,
+            self.min_lower,
+            self.min_digit,
+            self.min_symbol,
+        ]
+    }
+
+    /// Smallest password length that can satisfy the class minimums
+    pub fn min_length(&self) -> usize {
+        self.minimums().iter().sum()
+    }
+
+    /// Checks the rules that are enforced by rejection instead of by construction
+    pub fn accepts(&self, password: &str) -> bool {
+        if let Some(max_repeat) = self.max_repeat {
+            let mut run = 0;
+            let mut previous = None;
+            for c in password.chars() {
+                run = if Some(c) == previous { run + 1 } else { 1 };
+                if run > max_repeat.get() {
+                    return false;
+                }
+                previous = Some(c);
+            }
+        }
+
+        if !self.banned_words.is_empty() {
+            let lowered = password.to_lowercase();
+            if self
+                .banned_words
+                .iter()
+                .any(|word| !word.is_empty() && lowered.contains(&word.to_lowercase()))
+            {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+/// Picks the class a character belongs to
+fn class_of(c: char) -> usize {
+    if c.is_uppercase() {
+        0
+    } else if c.is_lowercase() {
+        1
+    } else if c.is_numeric() {
+        2
+    } else {
+        3
+    }
+}
+
+/// Samples passwords uniformly from all strings of a given length that meet the class minimums
+///
+/// The number of characters drawn from each class is picked with probability proportional to
+/// the number of strings having that composition, then classes are shuffled over the positions
+/// and each position is filled uniformly from its class. This is exactly uniform over the valid
+/// strings, unlike forcing e.g. the first character to be uppercase.
+#[derive(Debug, Clone)]
+pub(crate) struct CompositionSampler {
+    /// Available characters split by class
+    classes: [Vec<char>; NUM_CLASSES],
+    /// Minimum number of characters from each class
+    minimums: [usize; NUM_CLASSES],
+    /// ln(r!) for every length up to the maximum
+    ln_fact: Vec<f64>,
+    /// ln of the weighted number of ways classes k.. can fill r positions, indexed [k][r]
+    ways: Vec<Vec<f64>>,
+}
+
+impl CompositionSampler {
+    /// Splits the charset into classes and precomputes the counting table up to max_len
+    pub(crate) fn new(
+        charset: &[char],
+        policy: &PasswordPolicy,
+        max_len: usize,
+    ) -> Result<Self, PasswordGeneratorError> {
+        let mut classes: [Vec<char>; NUM_CLASSES] = Default::default();
+        for &c in charset {
+            classes[class_of(c)].push(c);
+        }
+        let minimums = policy.minimums();
+
+        // Report the first class the policy needs but the charset cannot supply
+        if let Some(class) = (0..NUM_CLASSES).find(|&k| minimums[k] > 0 && classes[k].is_empty()) {
+            error!("EmptyClass error");
+            return Err(PasswordGeneratorError::EmptyClass {
+                class: CLASS_NAMES[class],
+            });
+        }
+
+        // ln(r!) for every length we may need
+        let mut ln_fact = vec![0.0_f64; max_len + 1];
+        for r in 1..=max_len {
+            ln_fact[r] = ln_fact[r - 1] + (r as f64).ln();
+        }
+
+        // ways[k][r] = ln sum over counts c_k.. summing to r of prod(size^c / c!)
+        let mut ways = vec![vec![f64::NEG_INFINITY; max_len + 1]; NUM_CLASSES + 1];
+        ways[NUM_CLASSES][0] = 0.0;
+        for k in (0..NUM_CLASSES).rev() {
+            let ln_size = (classes[k].len() as f64).ln();
+            for r in 0..=max_len {
+                let terms: Vec<f64> = (minimums[k]..=r)
+                    .filter(|&c| c == 0 || !classes[k].is_empty())
+                    .map(|c| ln_term(c, ln_size, &ln_fact) + ways[k + 1][r - c])
+                    .collect();
+                ways[k][r] = log_sum_exp(&terms);
+            }
+        }
+
+        trace!("Composition table built up to length {max_len}");
+        Ok(CompositionSampler {
+            classes,
+            minimums,
+            ln_fact,
+            ways,
+        })
+    }
+
+    /// Exact number of strings of this length meeting the class minimums, None if it overflows a u128
+    pub(crate) fn count(&self, length: usize) -> Option<u128> {
+        // ways[r] = strings of r characters drawn from classes k.. that meet their minimums
+        let mut ways: Vec<Option<u128>> = (0..=length).map(|r| Some(u128::from(r == 0))).collect();
+        for k in (0..NUM_CLASSES).rev() {
+            let size = self.classes[k].len() as u128;
+            ways = (0..=length)
+                .map(|r| {
+                    (self.minimums[k]..=r)
+                        .filter(|&c| c == 0 || size > 0)
+                        .filter(|&c| ways[r - c] != Some(0))
+                        .try_fold(0_u128, |total, c| {
+                            // Pick which c of the r positions use this class, then fill them
+                            let term = binomial(r, c)?
+                                .checked_mul(size.checked_pow(c as u32)?)?
+                                .checked_mul(ways[r - c]?)?;
+                            total.checked_add(term)
+                        })
+                })
+                .collect();
+        }
+        ways[length]
+    }
+
+    /// Generates one password of exactly length characters
+    pub(crate) fn sample<R: Rng>(&self, rng: &mut R, length: usize) -> String {
+        // Draw how many characters come from each class
+        let mut remaining = length;
+        let mut slots: Vec<usize> = Vec::with_capacity(length);
+        for k in 0..NUM_CLASSES {
+            let count = if k == NUM_CLASSES - 1 {
+                remaining
+            } else {
+                let ln_size = (self.classes[k].len() as f64).ln();
+                let candidates: Vec<(usize, f64)> = (self.minimums[k]..=remaining)
+                    .filter(|&c| c == 0 || !self.classes[k].is_empty())
+                    .map(|c| {
+                        let weight = ln_term(c, ln_size, &self.ln_fact);
+                        (c, weight + self.ways[k + 1][remaining - c])
+                    })
+                    .collect();
+                pick_weighted(rng, &candidates)
+            };
+            slots.extend(std::iter::repeat_n(k, count));
+            remaining -= count;
+        }
+
+        // Spread the classes over the positions and fill each one
+        slots.shuffle(rng);
+        slots
+            .into_iter()
+            .map(|k| self.classes[k][rng.gen_range(0..self.classes[k].len())])
+            .collect()
+    }
+}
+
+/// n choose k, None if it overflows a u128
+fn binomial(n: usize, k: usize) -> Option<u128> {
+    (0..k).try_fold(1_u128, |acc, i| {
+        Some(acc.checked_mul((n - i) as u128)? / (i as u128 + 1))
+    })
+}
+
+/// Numerically stable ln(sum(exp(x)))
+fn log_sum_exp(terms: &[f64]) -> f64 {
+    let max = terms.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    if max == f64::NEG_INFINITY {
+        return max;
+    }
+    max + terms.iter().map(|t| (t - max).exp()).sum::<f64>().ln()
+}
+
+/// Picks a value with probability proportional to exp(weight)
+fn pick_weighted<R: Rng>(rng: &mut R, candidates: &[(usize, f64)]) -> usize {
+    let max = candidates
+        .iter()
+        .map(|(_, w)| *w)
+        .fold(f64::NEG_INFINITY, f64::max);
+    let weights: Vec<f64> = candidates.iter().map(|(_, w)| (w - max).exp()).collect();
+    let total: f64 = weights.iter().sum();
+
+    let mut target = rng.gen::<f64>() * total;
+    for ((value, _), weight) in candidates.iter().zip(&weights) {
+        if target < *weight {
+            return *value;
+        }
+        target -= weight;
+    }
+
+    // Floating point leftovers land on the last possible choice
+    candidates
+        .iter()
+        .rev()
+        .find(|(_, w)| *w > f64::NEG_INFINITY)
+        .map_or(0, |(value, _)| *value)
+}
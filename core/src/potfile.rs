@@ -0,0 +1,237 @@
+use std::{
+    collections::HashMap,
+    fs::{File, OpenOptions},
+    io::{BufRead, BufReader, ErrorKind, Write},
+    path::{Path, PathBuf},
+    sync::{Mutex, PoisonError},
+};
+
+use thiserror::Error;
+use tracing::{debug, trace, warn};
+
+use crate::targets::{decode_field, encode_field, Target, HASH_LEN};
+
+/// Errors for potfile reading and writing
+#[derive(Error, Debug)]
+pub enum PotfileError {
+    #[error("Invalid potfile line {line}: {reason}")]
+    InvalidLine { line: usize, reason: String },
+    #[error("Potfile {0} does not exist")]
+    Missing(PathBuf),
+    #[error("Potfile was opened read only")]
+    ReadOnly,
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+/// Writes a plaintext the way hashcat and John do, as $HEX[...] when it would not survive as text
+///
+/// Colons are escaped too, so a plaintext always starts after the last colon
+/// of a line this crate wrote.
+pub fn encode_plain(plain: &str) -> String {
+    encode_field(plain.as_bytes())
+}
+
+/// Reads a plaintext written by encode_plain, hashcat or John
+///
+/// Escaped bytes that are not UTF-8 are replaced, since candidates are strings.
+pub fn decode_plain(plain: &str) -> Result<String, String> {
+    decode_field(plain).map(|bytes| String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// Prefix of John's raw MD5 lines, `$dynamic_0$hash:plain`
+const JOHN_UNSALTED: &str = "$dynamic_0$";
+/// Prefix of John's md5($p.$s) lines, `$dynamic_1$hash$salt:plain`
+const JOHN_SALTED: &str = "$dynamic_1$";
+
+/// Splits a hashcat `hash:rest` or John `$dynamic_N$...` line into the digest and what follows it
+///
+/// For hashcat lines the rest is `plain` or `salt:plain`, which only the target
+/// being looked up can tell apart. John lines keep the salt inside the
+/// ciphertext, so theirs come back as `salt:plain` in the hashcat form.
+fn parse_line(line: &str) -> Result<([u8; HASH_LEN], String), String> {
+    let (hash, rest) = if let Some(john) = line.strip_prefix(JOHN_UNSALTED) {
+        john.split_once(':')
+            .ok_or_else(|| "expected $dynamic_0$hash:plain".to_string())?
+    } else if let Some(john) = line.strip_prefix(JOHN_SALTED) {
+        let (ciphertext, plain) = john
+            .split_once(':')
+            .ok_or_else(|| "expected $dynamic_1$hash$salt:plain".to_string())?;
+        let (hash, salt) = ciphertext
+            .split_once('$')
+            .ok_or_else(|| "expected $dynamic_1$hash$salt:plain".to_string())?;
+        // John escapes awkward salts as HEX$...
+        let salt = match salt.strip_prefix("HEX$") {
+            Some(salt) => hex::decode(salt).map_err(|_| "bad HEX$ salt".to_string())?,
+            None => salt.as_bytes().to_vec(),
+        };
+        return Ok((
+            parse_hash(hash)?,
+            format!("{}:{plain}", encode_field(&salt)),
+        ));
+    } else {
+        line.split_once(':')
+            .ok_or_else(|| "expected hash:plain".to_string())?
+    };
+    Ok((parse_hash(hash)?, rest.to_string()))
+}
+
+/// Reads a hex digest
+fn parse_hash(hash: &str) -> Result<[u8; HASH_LEN], String> {
+    hex::decode(hash)
+        .map_err(|_| "hash is not hex".to_string())?
+        .try_into()
+        .map_err(|_| format!("hash is not {HASH_LEN} bytes"))
+}
+
+/// Picks the plaintext for a target out of what followed its hash on a line
+///
+/// A salted target needs the line to start with its salt, raw or escaped, and
+/// takes the rest as the plaintext. For an unsalted target a colon could end
+/// a salt or belong to the plaintext, so such lines are reported rather than
+/// guessed at; this crate escapes colons in what it writes.
+fn plain_for(rest: &str, salt: Option<&[u8]>) -> Option<Result<String, String>> {
+    let Some(salt) = salt else {
+        if rest.contains(':') {
+            return Some(Err(format!(
+                "ambiguous {rest:?}, could be salt:plain or a plaintext with a colon"
+            )));
+        }
+        return Some(decode_plain(rest));
+    };
+    let escaped = encode_field(salt);
+    let raw = std::str::from_utf8(salt).ok();
+    let plain = [Some(escaped.as_str()), raw]
+        .into_iter()
+        .flatten()
+        .find_map(|salt| rest.strip_prefix(salt)?.strip_prefix(':'));
+    plain.map(decode_plain)
+}
+
+/// What followed each digest on its potfile lines
+type Entries = HashMap<[u8; HASH_LEN], Vec<String>>;
+
+/// Cracked hashes kept across runs, one `hash[:salt]:plain` line each like hashcat
+///
+/// Salts and plaintexts are written raw, or as $HEX[...] when they would not
+/// survive as text. John's `$dynamic_0$` and `$dynamic_1$` lines are read too.
+#[derive(Debug)]
+pub struct Potfile {
+    /// What followed each digest on its lines, `plain` or `salt:plain`
+    entries: Entries,
+    /// Number of lines read
+    len: usize,
+    /// Where new cracks are appended, None when opened read only
+    writer: Option<Mutex<File>>,
+}
+
+impl Potfile {
+    /// Reads every entry and opens the file for appending, creating it if needed
+    ///
+    /// Lines that cannot be parsed, for example hashes from other algorithms
+    /// that hashassin does not support, are skipped with a warning.
+    pub fn open(path: &Path) -> Result<Self, PotfileError> {
+        trace!("Open potfile");
+        let (entries, len) = match File::open(path) {
+            Ok(file) => read_entries(file)?,
+            Err(error) if error.kind() == ErrorKind::NotFound => (Entries::new(), 0),
+            Err(error) => return Err(error.into()),
+        };
+
+        let writer = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Potfile {
+            entries,
+            len,
+            writer: Some(Mutex::new(writer)),
+        })
+    }
+
+    /// Reads every entry of an existing potfile without opening it for appending
+    pub fn read(path: &Path) -> Result<Self, PotfileError> {
+        trace!("Read potfile");
+        let (entries, len) = match File::open(path) {
+            Ok(file) => read_entries(file)?,
+            Err(error) if error.kind() == ErrorKind::NotFound => {
+                return Err(PotfileError::Missing(path.to_path_buf()))
+            }
+            Err(error) => return Err(error.into()),
+        };
+        Ok(Potfile {
+            entries,
+            len,
+            writer: None,
+        })
+    }
+
+    /// Plaintext of a target cracked in an earlier run
+    ///
+    /// A line for the hash whose plaintext is a bad $HEX[] is reported and skipped.
+    pub fn get(&self, target: &Target) -> Option<String> {
+        self.entries
+            .get(&target.hash)?
+            .iter()
+            .filter_map(|rest| plain_for(rest, target.salt.as_deref()))
+            .find_map(|plain| {
+                plain
+                    .inspect_err(|reason| warn!("Potfile entry for {target}: {reason}"))
+                    .ok()
+            })
+    }
+
+    /// Whether a target was cracked in an earlier run
+    pub fn contains(&self, target: &Target) -> bool {
+        self.get(target).is_some()
+    }
+
+    /// Number of entries read when the potfile was opened
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the potfile had no entries when opened
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Appends a crack and flushes it straight away, so it survives a crash
+    ///
+    /// Fails with ReadOnly for a potfile opened with read.
+    pub fn append(&self, target: &Target, plain: &str) -> Result<(), PotfileError> {
+        let mut writer = self
+            .writer
+            .as_ref()
+            .ok_or(PotfileError::ReadOnly)?
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        writeln!(writer, "{target}:{}", encode_plain(plain))?;
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Entries of a potfile by digest, and the number of lines read
+fn read_entries(file: File) -> Result<(Entries, usize), PotfileError> {
+    let mut entries = Entries::new();
+    let mut len = 0;
+    for (index, line) in BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        if line.is_empty() {
+            continue;
+        }
+        match parse_line(&line) {
+            Ok((hash, rest)) => {
+                entries.entry(hash).or_default().push(rest);
+                len += 1;
+            }
+            Err(reason) => warn!(
+                "{}",
+                PotfileError::InvalidLine {
+                    line: index + 1,
+                    reason
+                }
+            ),
+        }
+    }
+    debug!("Potfile has {len} entries");
+    Ok((entries, len))
+}
@@ -0,0 +1,173 @@
+use std::{collections::HashSet, sync::Arc};
+
+use tracing::{debug, trace};
+
+/// Shape of the chains PRINCE builds
+#[derive(Debug, Clone, Copy)]
+pub struct PrinceOptions {
+    /// Fewest words in a chain
+    pub min_elements: usize,
+    /// Most words in a chain
+    pub max_elements: usize,
+    /// Shortest candidate in characters
+    pub min_len: usize,
+    /// Longest candidate in characters
+    pub max_len: usize,
+}
+
+impl Default for PrinceOptions {
+    /// One to eight words, up to 16 characters like princeprocessor
+    fn default() -> Self {
+        PrinceOptions {
+            min_elements: 1,
+            max_elements: 8,
+            min_len: 1,
+            max_len: 16,
+        }
+    }
+}
+
+/// PRINCE (PRobability INfinite Chained Elements) candidate generator
+///
+/// Builds passphrases by chaining words of one wordlist, like
+/// "correct" + "horse" + "battery".
+#[derive(Debug, Clone)]
+pub struct Prince {
+    /// Words by length in characters, in wordlist order
+    by_len: Vec<Vec<String>>,
+    options: PrinceOptions,
+}
+
+impl Prince {
+    /// Groups the words by length, dropping empty and repeated words
+    pub fn new<I, S>(words: I, options: PrinceOptions) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        trace!("New prince");
+        let mut seen = HashSet::new();
+        let mut by_len: Vec<Vec<String>> = Vec::new();
+        for word in words {
+            let word = word.as_ref();
+            let len = word.chars().count();
+            if len == 0 || len > options.max_len || !seen.insert(word.to_string()) {
+                continue;
+            }
+            if by_len.len() <= len {
+                by_len.resize_with(len + 1, Vec::new);
+            }
+            by_len[len].push(word.to_string());
+        }
+        debug!("Prince has {} words", seen.len());
+        Prince { by_len, options }
+    }
+
+    /// Number of words of a length
+    fn count(&self, len: usize) -> usize {
+        self.by_len.get(len).map_or(0, Vec::len)
+    }
+
+    /// Every way to split a length into word lengths the wordlist has, with an allowed number of words
+    fn chains(&self, total: usize) -> Vec<Vec<usize>> {
+        let mut chains = Vec::new();
+        let mut current = Vec::new();
+        self.split(total, &mut current, &mut chains);
+        // Smallest keyspace first, like princeprocessor
+        chains.sort_by_cached_key(|chain| {
+            chain
+                .iter()
+                .map(|&len| self.count(len) as u128)
+                .fold(1_u128, u128::saturating_mul)
+        });
+        chains
+    }
+
+    /// Extends a partial split of the remaining length
+    fn split(&self, remaining: usize, current: &mut Vec<usize>, chains: &mut Vec<Vec<usize>>) {
+        if remaining == 0 {
+            if current.len() >= self.options.min_elements {
+                chains.push(current.clone());
+            }
+            return;
+        }
+        if current.len() == self.options.max_elements {
+            return;
+        }
+        for len in 1..=remaining {
+            if self.count(len) > 0 {
+                current.push(len);
+                self.split(remaining - len, current, chains);
+                current.pop();
+            }
+        }
+    }
+
+    /// Candidates from shortest to longest
+    pub fn candidates(self: &Arc<Self>) -> PrinceCandidates {
+        PrinceCandidates {
+            prince: Arc::clone(self),
+            total: self.options.min_len.max(1),
+            chains: self.chains(self.options.min_len.max(1)),
+            chain: 0,
+            odometer: Vec::new(),
+        }
+    }
+}
+
+/// Iterator over PRINCE candidates ordered by total length
+pub struct PrinceCandidates {
+    prince: Arc<Prince>,
+    /// Length of the candidates being made
+    total: usize,
+    /// Word lengths of every chain of this length
+    chains: Vec<Vec<usize>>,
+    /// Chain being walked
+    chain: usize,
+    /// Word index for each element of the chain, last one changes fastest
+    odometer: Vec<usize>,
+}
+
+impl Iterator for PrinceCandidates {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        loop {
+            let Some(chain) = self.chains.get(self.chain) else {
+                self.total += 1;
+                if self.total > self.prince.options.max_len {
+                    return None;
+                }
+                self.chains = self.prince.chains(self.total);
+                self.chain = 0;
+                continue;
+            };
+            if self.odometer.len() != chain.len() {
+                self.odometer = vec![0; chain.len()];
+            }
+
+            let candidate = chain
+                .iter()
+                .zip(&self.odometer)
+                .map(|(&len, &index)| self.prince.by_len[len][index].as_str())
+                .collect();
+
+            // Advance to the next word combination, or the next chain once they are all used
+            let mut element = chain.len();
+            loop {
+                if element == 0 {
+                    self.chain += 1;
+                    self.odometer.clear();
+                    break;
+                }
+                element -= 1;
+                self.odometer[element] += 1;
+                if self.odometer[element] < self.prince.count(chain[element]) {
+                    break;
+                }
+                self.odometer[element] = 0;
+            }
+            return Some(candidate);
+        }
+    }
+}
@@ -0,0 +1,151 @@
+use rand::prelude::*;
+use std::num::NonZeroUsize;
+use tracing::{error, trace};
+
+use crate::PasswordGeneratorError;
+
+/// Consonants and clusters that can start a syllable
+const ONSETS: &[&str] = &[
+    "b", "c", "d", "f", "g", "h", "j", "k", "l", "m", "n", "p", "r", "s", "t", "v", "w", "z", "bl",
+    "br", "ch", "cl", "cr", "dr", "fl", "fr", "gl", "gr", "pl", "pr", "sh", "sl", "sp", "st", "th",
+    "tr",
+];
+
+/// Vowels and vowel pairs at the heart of a syllable
+const NUCLEI: &[&str] = &[
+    "a", "e", "i", "o", "u", "a", "e", "i", "o", "ai", "ea", "ee", "io", "oa", "oo", "ou",
+];
+
+/// Consonants that can end a syllable, empty for open syllables
+const CODAS: &[&str] = &[
+    "", "", "", "", "n", "r", "s", "t", "l", "m", "ck", "ng", "nd", "st",
+];
+
+/// Builder for pronounceable password generator
+#[derive(Default, Debug)]
+pub struct PronounceableGeneratorBuilder {
+    /// Min chars for password
+    min_char: Option<NonZeroUsize>,
+    /// Max chars for password
+    max_char: Option<NonZeroUsize>,
+    /// Capitalize the first letter
+    capitalize: bool,
+    /// Number of digits to append
+    digits: usize,
+}
+
+/// Implements builder for pronounceable password generator
+impl PronounceableGeneratorBuilder {
+    /// Set the minimum number of characters in the password
+    pub fn with_min_char(self, min_char: Option<NonZeroUsize>) -> Self {
+        trace!("With min char");
+        Self { min_char, ..self }
+    }
+
+    /// Set the maximum number of characters in the password
+    pub fn with_max_char(self, max_char: Option<NonZeroUsize>) -> Self {
+        trace!("With max char");
+        Self { max_char, ..self }
+    }
+
+    /// Capitalize the first letter, like most people do
+    pub fn with_capitalize(self, capitalize: bool) -> Self {
+        trace!("With capitalize");
+        Self { capitalize, ..self }
+    }
+
+    /// Append this many random digits after the syllables
+    pub fn with_digits(self, digits: usize) -> Self {
+        trace!("With digits");
+        Self { digits, ..self }
+    }
+
+    /// Build a PronounceableGenerator
+    pub fn build(self) -> Result<PronounceableGenerator, PasswordGeneratorError> {
+        trace!("Build");
+        let default = PronounceableGenerator::default();
+        let min_char = self.min_char.map_or(default.min_char, usize::from);
+        let max_char = self.max_char.map_or(default.max_char, usize::from);
+        if min_char > max_char {
+            error!("MinMax error");
+            return Err(PasswordGeneratorError::MinMax {
+                min: min_char,
+                max: max_char,
+            });
+        }
+        // The digits have to leave room for at least one letter
+        if self.digits >= max_char {
+            error!("PolicyTooLong error");
+            return Err(PasswordGeneratorError::PolicyTooLong {
+                required: self.digits + 1,
+                max: max_char,
+            });
+        }
+
+        Ok(PronounceableGenerator {
+            min_char,
+            max_char,
+            capitalize: self.capitalize,
+            digits: self.digits,
+        })
+    }
+}
+
+/// Generates passwords made of syllables, like "brantoma"
+#[derive(Debug, Clone, Copy)]
+pub struct PronounceableGenerator {
+    /// Min length of password
+    min_char: usize,
+    /// Max length of password
+    max_char: usize,
+    /// Capitalize the first letter
+    capitalize: bool,
+    /// Number of digits appended
+    digits: usize,
+}
+
+/// Default for pronounceable password generator
+impl Default for PronounceableGenerator {
+    /// Min and max chars default to 8
+    fn default() -> Self {
+        Self {
+            min_char: 8,
+            max_char: 8,
+            capitalize: false,
+            digits: 0,
+        }
+    }
+}
+
+impl PronounceableGenerator {
+    /// Generates a password between min_char and max_char from random syllables
+    pub fn generate(&self) -> String {
+        trace!("Generate");
+        let mut rng = thread_rng();
+        let length = rng.gen_range(self.min_char.max(self.digits + 1)..=self.max_char);
+        let letters = length - self.digits;
+
+        // Keep adding syllables until the word is long enough, then cut it to size
+        let mut word = String::new();
+        while word.len() < letters {
+            for part in [ONSETS, NUCLEI, CODAS] {
+                if let Some(piece) = part.choose(&mut rng) {
+                    word.push_str(piece);
+                }
+            }
+        }
+        word.truncate(letters);
+
+        if self.capitalize {
+            word = word
+                .chars()
+                .enumerate()
+                .map(|(i, c)| if i == 0 { c.to_ascii_uppercase() } else { c })
+                .collect();
+        }
+        for _ in 0..self.digits {
+            word.push(char::from(b'0' + rng.gen_range(0..10)));
+        }
+        word
+    }
+}
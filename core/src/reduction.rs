@@ -0,0 +1,397 @@
+//! Reduction functions, which turn a hash back into a password
+//!
+//! A rainbow table only works with the reduction it was built with, so the
+//! table header names the reduction, its version, its charset and the
+//! password lengths. Every version reduces the same on every machine:
+//!
+//! 1. The 16 hash bytes are read as a little endian number and the link
+//!    number is added, wrapping at 2^128. The link number is the table index
+//!    times the chain length, plus the link's position in the chain.
+//! 2. The keyspace is `charset.len() ^ length` summed over every length from
+//!    the shortest to the longest. Up to 2^128 the index is that number
+//!    modulo the keyspace.
+//! 3. Past 2^128, version 2 appends 16 byte words to the hash, word `i` being
+//!    the MD5 of the hash followed by `i` as a little endian u32, from 1 up,
+//!    until there are 64 more bits than the keyspace has. The link number is
+//!    added to all of those bytes read as a little endian number, and the
+//!    index is that modulo the keyspace, so passwords spread over all of it.
+//!    Version 1 used the 128 bit number as is, which left every password
+//!    past the first 2^128 unreachable.
+//! 4. `charset` gives the lowest indices to the shortest length: indices
+//!    below `charset.len() ^ shortest` are passwords of the shortest length,
+//!    the next `charset.len() ^ (shortest + 1)` are one longer, and so on, so
+//!    each length gets a share of the chains as large as its share of the
+//!    keyspace. What is left of the index once the shorter lengths are taken
+//!    off is written as exactly length digits, most significant first. Digit
+//!    `d` is the `d`th character of the charset sorted by code point.
+//! 5. `legacy` only has one length. It writes digits least significant
+//!    first, stopping at the highest non zero digit. Its charset is the 94
+//!    code points from 97, so digit `d` is the character with code point
+//!    `97 + d`.
+//!
+//! Known answers are kept in `tests/reduction_vectors.tsv`.
+
+use std::{fmt, num::NonZeroUsize, str::FromStr};
+
+use md5::{Digest, Md5};
+use num::{BigUint, Integer, ToPrimitive, Zero};
+use thiserror::Error;
+
+use crate::charset::Script;
+
+/// Version of the reductions this build makes
+///
+/// Any change to what a reduction returns needs a new version, so tables
+/// built with the old one are refused rather than silently never cracking.
+pub const REDUCTION_VERSION: u32 = 2;
+
+/// Longest password a reduction makes
+///
+/// The keyspace grows with every character and each reduction does arithmetic
+/// on it, so longer passwords are refused when the reducer is built.
+pub const MAX_PASSWORD_LENGTH: usize = 1024;
+
+/// Errors for reductions that cannot be built
+#[derive(Error, Debug)]
+pub enum ReductionError {
+    #[error("The reduction charset is empty")]
+    EmptyCharset,
+    #[error("Charset does not match the {0} reduction")]
+    CharsetMismatch(Reduction),
+    #[error("Reduction version {0} is not supported, this build reduces with version {REDUCTION_VERSION}")]
+    UnsupportedVersion(u32),
+    #[error(
+        "Password length {0} is too long, reductions make at most {MAX_PASSWORD_LENGTH} characters"
+    )]
+    TooLong(usize),
+    #[error("Min password length {min} must be less or equal to the password length {max}")]
+    MinMax { min: usize, max: usize },
+    #[error("The {0} reduction only makes passwords of one length")]
+    FixedLength(Reduction),
+}
+
+/// Which reduction function a table uses
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Reduction {
+    /// Hash plus the link number, written in base 94 from 'a'
+    ///
+    /// Most of its passwords fall outside printable ASCII and high zero digits
+    /// are dropped, so it is only kept to read old tables.
+    Legacy,
+    /// Hash plus the link number, written as exactly password length digits of the charset
+    #[default]
+    Charset,
+}
+
+impl FromStr for Reduction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "legacy" => Ok(Reduction::Legacy),
+            "charset" => Ok(Reduction::Charset),
+            _ => Err(format!("unknown reduction function {s}")),
+        }
+    }
+}
+
+impl fmt::Display for Reduction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Reduction::Legacy => write!(f, "legacy"),
+            Reduction::Charset => write!(f, "charset"),
+        }
+    }
+}
+
+/// Characters of legacy passwords, 'a' and the 93 code points after it
+fn legacy_charset() -> Vec<char> {
+    (97_u8..97 + 94).map(char::from).collect()
+}
+
+/// Refuses lengths past MAX_PASSWORD_LENGTH
+fn check_length(password_length: NonZeroUsize) -> Result<(), ReductionError> {
+    match password_length.get() {
+        length if length > MAX_PASSWORD_LENGTH => Err(ReductionError::TooLong(length)),
+        _ => Ok(()),
+    }
+}
+
+/// A reduction function with the passwords it reduces to
+///
+/// Every password is a keyspace index written in the charset, so tables can
+/// store indices instead of passwords.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reducer {
+    reduction: Reduction,
+    version: u32,
+    /// Sorted and distinct for the charset reduction
+    charset: Vec<char>,
+    /// Shortest passwords
+    min_length: NonZeroUsize,
+    /// Longest passwords
+    password_length: NonZeroUsize,
+    /// Number of passwords of each length, shortest first
+    sizes: Vec<BigUint>,
+    /// Number of passwords of every length, kept since every reduction needs it
+    keyspace: BigUint,
+    /// The sizes as u128s, empty when the keyspace does not fit in one
+    small_sizes: Vec<u128>,
+}
+
+impl Reducer {
+    /// Reduction with a charset, sorted and deduplicated for the charset reduction
+    pub fn new(
+        reduction: Reduction,
+        mut charset: Vec<char>,
+        password_length: NonZeroUsize,
+    ) -> Result<Self, ReductionError> {
+        check_length(password_length)?;
+        match reduction {
+            Reduction::Legacy if charset != legacy_charset() => {
+                return Err(ReductionError::CharsetMismatch(reduction))
+            }
+            Reduction::Legacy => {}
+            Reduction::Charset => {
+                charset.sort_unstable();
+                charset.dedup();
+                if charset.is_empty() {
+                    return Err(ReductionError::EmptyCharset);
+                }
+            }
+        }
+        Ok(Reducer::build(
+            reduction,
+            charset,
+            password_length,
+            password_length,
+        ))
+    }
+
+    /// The legacy reduction, which tables without a header were built with
+    pub fn legacy(password_length: NonZeroUsize) -> Result<Self, ReductionError> {
+        check_length(password_length)?;
+        Ok(Reducer::build(
+            Reduction::Legacy,
+            legacy_charset(),
+            password_length,
+            password_length,
+        ))
+    }
+
+    /// The charset reduction over printable ASCII, the password generator's default charset
+    pub fn ascii(password_length: NonZeroUsize) -> Result<Self, ReductionError> {
+        check_length(password_length)?;
+        Ok(Reducer::build(
+            Reduction::Charset,
+            Script::Ascii.chars(),
+            password_length,
+            password_length,
+        ))
+    }
+
+    /// A reducer with a charset and lengths already checked
+    fn build(
+        reduction: Reduction,
+        charset: Vec<char>,
+        min_length: NonZeroUsize,
+        password_length: NonZeroUsize,
+    ) -> Self {
+        let sizes: Vec<BigUint> = (min_length.get()..=password_length.get())
+            .map(|length| num::pow(BigUint::from(charset.len()), length))
+            .collect();
+        let keyspace: BigUint = sizes.iter().sum();
+        let small_sizes = match keyspace.to_u128() {
+            Some(_) => sizes.iter().filter_map(ToPrimitive::to_u128).collect(),
+            None => Vec::new(),
+        };
+        Reducer {
+            reduction,
+            version: REDUCTION_VERSION,
+            charset,
+            min_length,
+            password_length,
+            sizes,
+            keyspace,
+            small_sizes,
+        }
+    }
+
+    /// Make passwords of every length from `min_length` up to the password length
+    pub fn with_min_length(self, min_length: NonZeroUsize) -> Result<Self, ReductionError> {
+        if min_length > self.password_length {
+            return Err(ReductionError::MinMax {
+                min: min_length.get(),
+                max: self.password_length.get(),
+            });
+        }
+        if self.reduction == Reduction::Legacy && min_length != self.password_length {
+            return Err(ReductionError::FixedLength(self.reduction));
+        }
+        Ok(Self {
+            version: self.version,
+            ..Reducer::build(
+                self.reduction,
+                self.charset,
+                min_length,
+                self.password_length,
+            )
+        })
+    }
+
+    /// Reduce as a version of the reduction did, refusing versions this build does not know
+    pub fn with_version(self, version: u32) -> Result<Self, ReductionError> {
+        if !(1..=REDUCTION_VERSION).contains(&version) {
+            return Err(ReductionError::UnsupportedVersion(version));
+        }
+        Ok(Self { version, ..self })
+    }
+
+    pub fn reduction(&self) -> Reduction {
+        self.reduction
+    }
+
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    pub fn charset(&self) -> &[char] {
+        &self.charset
+    }
+
+    /// Length of the shortest passwords
+    pub fn min_length(&self) -> NonZeroUsize {
+        self.min_length
+    }
+
+    /// Length of the longest passwords
+    pub fn password_length(&self) -> NonZeroUsize {
+        self.password_length
+    }
+
+    /// Number of passwords the reduction makes
+    pub fn keyspace(&self) -> &BigUint {
+        &self.keyspace
+    }
+
+    /// Password for a hash at a link of a chain
+    pub fn reduce(&self, hash: &[u8; 16], link_number: u128) -> String {
+        let number = u128::from_le_bytes(*hash).wrapping_add(link_number);
+        match self.keyspace.to_u128() {
+            Some(keyspace) => self.password(number % keyspace),
+            None if self.version == 1 => self.password(number),
+            None => {
+                let mut bytes = hash.to_vec();
+                for word in 1..(self.keyspace.bits() + 64).div_ceil(128) {
+                    let mut hasher = Md5::new();
+                    hasher.update(hash);
+                    hasher.update((word as u32).to_le_bytes());
+                    bytes.extend(hasher.finalize());
+                }
+                let number = BigUint::from_bytes_le(&bytes) + link_number;
+                self.password_at(number % &self.keyspace)
+            }
+        }
+    }
+
+    /// Password at a keyspace index
+    pub fn password(&self, index: u128) -> String {
+        if self.small_sizes.is_empty() {
+            return self.password_at(BigUint::from(index));
+        }
+        match self.reduction {
+            Reduction::Legacy => crate::encoder(index, 94),
+            Reduction::Charset => {
+                let (length, mut index) = self.split(index);
+                // Most significant digit first, padded with the first character
+                let radix = self.charset.len() as u128;
+                let mut password = vec![self.charset[0]; length];
+                for c in password.iter_mut().rev() {
+                    *c = self.charset[(index % radix) as usize];
+                    index /= radix;
+                }
+                password.into_iter().collect()
+            }
+        }
+    }
+
+    /// Length of the password at an index, and its index among passwords of that length
+    fn split(&self, mut index: u128) -> (usize, u128) {
+        for (length, size) in (self.min_length.get()..).zip(&self.small_sizes) {
+            if index < *size {
+                return (length, index);
+            }
+            index -= size;
+        }
+        (self.password_length.get(), index)
+    }
+
+    /// Password at a keyspace index of any size
+    ///
+    /// Slower than [`Reducer::password`], so only used for indices past a u128.
+    pub fn password_at(&self, mut index: BigUint) -> String {
+        let radix = BigUint::from(self.charset.len());
+        match self.reduction {
+            Reduction::Legacy => {
+                let mut password = String::new();
+                while !index.is_zero() {
+                    password.push(self.next_digit(&mut index, &radix));
+                }
+                password
+            }
+            Reduction::Charset => {
+                let mut length = self.password_length.get();
+                for (shorter, size) in (self.min_length.get()..).zip(&self.sizes) {
+                    if index < *size {
+                        length = shorter;
+                        break;
+                    }
+                    index -= size;
+                }
+                let mut password: Vec<char> = (0..length)
+                    .map(|_| self.next_digit(&mut index, &radix))
+                    .collect();
+                password.reverse();
+                password.into_iter().collect()
+            }
+        }
+    }
+
+    /// Takes the least significant digit off an index, as a character
+    fn next_digit(&self, index: &mut BigUint, radix: &BigUint) -> char {
+        let (rest, digit) = index.div_rem(radix);
+        *index = rest;
+        // Always below the radix, which is the charset length
+        self.charset[digit.to_usize().unwrap_or(0)]
+    }
+
+    /// Keyspace index of a password, None if the reduction never makes it or the index is past a u128
+    pub fn index(&self, password: &str) -> Option<u128> {
+        match self.reduction {
+            Reduction::Legacy => {
+                // Least significant digit first
+                let mut index: u128 = 0;
+                for c in password.chars().rev() {
+                    let digit = u32::from(c).checked_sub(97).filter(|digit| *digit < 94)?;
+                    index = index.checked_mul(94)?.checked_add(u128::from(digit))?;
+                }
+                // High zero digits are never written, so a password ending in 'a' has no index
+                (self.password(index) == password).then_some(index)
+            }
+            Reduction::Charset => {
+                let radix = self.charset.len() as u128;
+                let mut index: u128 = 0;
+                let mut length: usize = 0;
+                for c in password.chars() {
+                    let digit = self.charset.binary_search(&c).ok()?;
+                    index = index.checked_mul(radix)?.checked_add(digit as u128)?;
+                    length += 1;
+                }
+                // Shorter lengths come first
+                let shorter = length.checked_sub(self.min_length.get())?;
+                let offset: u128 = self.small_sizes.get(..shorter)?.iter().sum();
+                (length <= self.password_length.get()).then_some(offset + index)
+            }
+        }
+    }
+}
@@ -0,0 +1,565 @@
+use std::{fmt, io::BufRead, str::FromStr};
+
+use thiserror::Error;
+use tracing::{debug, trace, warn};
+
+/// Rules shipped with the crate, modeled on hashcat's best64
+const BEST64: &str = include_str!("../data/best64.rule");
+
+/// Longest candidate a rule can make, in bytes, like hashcat
+///
+/// Longer words are dropped, which also keeps rules like `dddd...` from
+/// growing a word without bound.
+pub const MAX_WORD_LEN: usize = 256;
+
+/// Errors for rule parsing
+#[derive(Error, Debug)]
+pub enum RuleError {
+    #[error("Invalid rule {rule:?}: {reason}")]
+    InvalidRule { rule: String, reason: String },
+    #[error("Rule file contains no usable rules")]
+    EmptyRuleSet,
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+/// One rule function, using hashcat's names
+///
+/// Positions are 0-9 then A-Z for 10-35. Functions that point past the end of
+/// the word leave it unchanged, like hashcat does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    /// `:` do nothing
+    Noop,
+    /// `l` lowercase everything
+    Lower,
+    /// `u` uppercase everything
+    Upper,
+    /// `c` uppercase the first letter, lowercase the rest
+    Capitalize,
+    /// `C` lowercase the first letter, uppercase the rest
+    InvertCapitalize,
+    /// `t` toggle the case of everything
+    ToggleAll,
+    /// `TN` toggle the case at N
+    Toggle(usize),
+    /// `r` reverse
+    Reverse,
+    /// `d` append the word to itself
+    Duplicate,
+    /// `pN` append the word to itself N times
+    DuplicateN(usize),
+    /// `f` append the word reversed
+    Reflect,
+    /// `{` move the first character to the end
+    RotateLeft,
+    /// `}` move the last character to the front
+    RotateRight,
+    /// `$X` append X
+    Append(char),
+    /// `^X` prepend X
+    Prepend(char),
+    /// `[` delete the first character
+    DeleteFirst,
+    /// `]` delete the last character
+    DeleteLast,
+    /// `DN` delete the character at N
+    DeleteAt(usize),
+    /// `xNM` keep M characters from N
+    Extract(usize, usize),
+    /// `ONM` delete M characters from N
+    Omit(usize, usize),
+    /// `iNX` insert X at N
+    Insert(usize, char),
+    /// `oNX` overwrite the character at N with X
+    Overwrite(usize, char),
+    /// `'N` keep the first N characters
+    Truncate(usize),
+    /// `sXY` replace every X with Y
+    Replace(char, char),
+    /// `@X` delete every X
+    Purge(char),
+    /// `zN` repeat the first character N more times
+    DuplicateFirst(usize),
+    /// `ZN` repeat the last character N more times
+    DuplicateLast(usize),
+    /// `q` double every character
+    DuplicateAll,
+    /// `k` swap the first two characters
+    SwapFront,
+    /// `K` swap the last two characters
+    SwapBack,
+    /// `*NM` swap the characters at N and M
+    Swap(usize, usize),
+    /// `LN` shift the character at N one bit left
+    BitwiseLeft(usize),
+    /// `RN` shift the character at N one bit right
+    BitwiseRight(usize),
+    /// `+N` increment the character at N
+    Increment(usize),
+    /// `-N` decrement the character at N
+    Decrement(usize),
+    /// `.N` replace the character at N with the one after it
+    ReplaceNext(usize),
+    /// `,N` replace the character at N with the one before it
+    ReplacePrevious(usize),
+    /// `yN` prepend the first N characters
+    DuplicateBlockFront(usize),
+    /// `YN` append the last N characters
+    DuplicateBlockBack(usize),
+    /// `E` lowercase everything, then uppercase the first letter and every letter after a space
+    Title,
+    /// `eX` like E but after X instead of space
+    TitleSeparator(char),
+    /// `<N` reject if longer than N
+    RejectLonger(usize),
+    /// `>N` reject if shorter than N
+    RejectShorter(usize),
+    /// `_N` reject unless exactly N long
+    RejectLength(usize),
+    /// `!X` reject if it contains X
+    RejectContains(char),
+    /// `/X` reject unless it contains X
+    RejectMissing(char),
+    /// `(X` reject unless it starts with X
+    RejectFirst(char),
+    /// `)X` reject unless it ends with X
+    RejectLast(char),
+    /// `=NX` reject unless the character at N is X
+    RejectAt(usize, char),
+    /// `%NX` reject unless it contains X at least N times
+    RejectCount(usize, char),
+}
+
+/// A sequence of rule functions applied left to right, like `c $1 $2`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rule {
+    /// Functions in the order they run
+    ops: Vec<Op>,
+    /// The rule as written
+    source: String,
+}
+
+/// Reads a position: 0-9 then A-Z
+fn position(c: char) -> Option<usize> {
+    match c {
+        '0'..='9' => Some(c as usize - '0' as usize),
+        'A'..='Z' => Some(c as usize - 'A' as usize + 10),
+        _ => None,
+    }
+}
+
+/// Character with its case flipped
+fn toggle(c: char) -> char {
+    if c.is_lowercase() {
+        c.to_uppercase().next().unwrap_or(c)
+    } else {
+        c.to_lowercase().next().unwrap_or(c)
+    }
+}
+
+/// Character with its code point moved by the function, unchanged if that is not a character
+fn shift(c: char, f: impl Fn(u32) -> Option<u32>) -> char {
+    f(c as u32).and_then(char::from_u32).unwrap_or(c)
+}
+
+impl FromStr for Rule {
+    type Err = RuleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = |reason: &str| RuleError::InvalidRule {
+            rule: s.to_string(),
+            reason: reason.to_string(),
+        };
+
+        let mut chars = s.chars();
+        let mut ops = Vec::new();
+        while let Some(name) = chars.next() {
+            // Spaces only separate functions, arguments can still be spaces
+            if name == ' ' || name == '\t' {
+                continue;
+            }
+            let mut char_arg = || chars.next().ok_or_else(|| invalid("missing argument"));
+            let op = match name {
+                ':' => Op::Noop,
+                'l' => Op::Lower,
+                'u' => Op::Upper,
+                'c' => Op::Capitalize,
+                'C' => Op::InvertCapitalize,
+                't' => Op::ToggleAll,
+                'r' => Op::Reverse,
+                'd' => Op::Duplicate,
+                'f' => Op::Reflect,
+                '{' => Op::RotateLeft,
+                '}' => Op::RotateRight,
+                '[' => Op::DeleteFirst,
+                ']' => Op::DeleteLast,
+                'q' => Op::DuplicateAll,
+                'k' => Op::SwapFront,
+                'K' => Op::SwapBack,
+                'E' => Op::Title,
+                '$' => Op::Append(char_arg()?),
+                '^' => Op::Prepend(char_arg()?),
+                '@' => Op::Purge(char_arg()?),
+                '!' => Op::RejectContains(char_arg()?),
+                '/' => Op::RejectMissing(char_arg()?),
+                '(' => Op::RejectFirst(char_arg()?),
+                ')' => Op::RejectLast(char_arg()?),
+                'e' => Op::TitleSeparator(char_arg()?),
+                's' => Op::Replace(char_arg()?, char_arg()?),
+                _ => {
+                    let mut position_arg = || {
+                        chars
+                            .next()
+                            .and_then(position)
+                            .ok_or_else(|| invalid("missing or bad position"))
+                    };
+                    match name {
+                        'T' => Op::Toggle(position_arg()?),
+                        'p' => Op::DuplicateN(position_arg()?),
+                        'D' => Op::DeleteAt(position_arg()?),
+                        '\'' => Op::Truncate(position_arg()?),
+                        'z' => Op::DuplicateFirst(position_arg()?),
+                        'Z' => Op::DuplicateLast(position_arg()?),
+                        'L' => Op::BitwiseLeft(position_arg()?),
+                        'R' => Op::BitwiseRight(position_arg()?),
+                        '+' => Op::Increment(position_arg()?),
+                        '-' => Op::Decrement(position_arg()?),
+                        '.' => Op::ReplaceNext(position_arg()?),
+                        ',' => Op::ReplacePrevious(position_arg()?),
+                        'y' => Op::DuplicateBlockFront(position_arg()?),
+                        'Y' => Op::DuplicateBlockBack(position_arg()?),
+                        '<' => Op::RejectLonger(position_arg()?),
+                        '>' => Op::RejectShorter(position_arg()?),
+                        '_' => Op::RejectLength(position_arg()?),
+                        'x' => Op::Extract(position_arg()?, position_arg()?),
+                        'O' => Op::Omit(position_arg()?, position_arg()?),
+                        '*' => Op::Swap(position_arg()?, position_arg()?),
+                        'i' | 'o' | '=' | '%' => {
+                            let n = position_arg()?;
+                            let c = chars.next().ok_or_else(|| invalid("missing argument"))?;
+                            match name {
+                                'i' => Op::Insert(n, c),
+                                'o' => Op::Overwrite(n, c),
+                                '=' => Op::RejectAt(n, c),
+                                _ => Op::RejectCount(n, c),
+                            }
+                        }
+                        _ => return Err(invalid(&format!("unknown function {name:?}"))),
+                    }
+                }
+            };
+            ops.push(op);
+        }
+
+        if ops.is_empty() {
+            return Err(invalid("no functions"));
+        }
+        Ok(Rule {
+            ops,
+            source: s.to_string(),
+        })
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+impl Rule {
+    /// Mangles the word, None if a rejection function threw it out or it got longer than MAX_WORD_LEN
+    pub fn apply(&self, word: &str) -> Option<String> {
+        if word.len() > MAX_WORD_LEN {
+            return None;
+        }
+        let mut w: Vec<char> = word.chars().collect();
+        for op in &self.ops {
+            let len = w.len();
+            match *op {
+                Op::Noop => {}
+                Op::Lower => w = w.iter().flat_map(|c| c.to_lowercase()).collect(),
+                Op::Upper => w = w.iter().flat_map(|c| c.to_uppercase()).collect(),
+                Op::Capitalize | Op::InvertCapitalize => {
+                    let first_upper = *op == Op::Capitalize;
+                    w = w
+                        .iter()
+                        .enumerate()
+                        .map(|(i, &c)| {
+                            if (i == 0) == first_upper {
+                                c.to_uppercase().next().unwrap_or(c)
+                            } else {
+                                c.to_lowercase().next().unwrap_or(c)
+                            }
+                        })
+                        .collect();
+                }
+                Op::ToggleAll => w.iter_mut().for_each(|c| *c = toggle(*c)),
+                Op::Toggle(n) => {
+                    if let Some(c) = w.get_mut(n) {
+                        *c = toggle(*c);
+                    }
+                }
+                Op::Reverse => w.reverse(),
+                Op::Duplicate => w.extend_from_within(..),
+                Op::DuplicateN(n) => {
+                    for _ in 0..n {
+                        w.extend_from_within(..len);
+                    }
+                }
+                Op::Reflect => {
+                    let reversed: Vec<char> = w.iter().rev().copied().collect();
+                    w.extend(reversed);
+                }
+                Op::RotateLeft => {
+                    if len > 0 {
+                        w.rotate_left(1);
+                    }
+                }
+                Op::RotateRight => {
+                    if len > 0 {
+                        w.rotate_right(1);
+                    }
+                }
+                Op::Append(c) => w.push(c),
+                Op::Prepend(c) => w.insert(0, c),
+                Op::DeleteFirst => {
+                    if len > 0 {
+                        w.remove(0);
+                    }
+                }
+                Op::DeleteLast => {
+                    w.pop();
+                }
+                Op::DeleteAt(n) => {
+                    if n < len {
+                        w.remove(n);
+                    }
+                }
+                Op::Extract(n, m) => {
+                    if n + m <= len {
+                        w = w[n..n + m].to_vec();
+                    }
+                }
+                Op::Omit(n, m) => {
+                    if n + m <= len {
+                        w.drain(n..n + m);
+                    }
+                }
+                Op::Insert(n, c) => {
+                    if n <= len {
+                        w.insert(n, c);
+                    }
+                }
+                Op::Overwrite(n, c) => {
+                    if let Some(old) = w.get_mut(n) {
+                        *old = c;
+                    }
+                }
+                Op::Truncate(n) => w.truncate(n),
+                Op::Replace(from, to) => w.iter_mut().filter(|c| **c == from).for_each(|c| *c = to),
+                Op::Purge(c) => w.retain(|&x| x != c),
+                Op::DuplicateFirst(n) => {
+                    if let Some(&first) = w.first() {
+                        w.splice(0..0, std::iter::repeat_n(first, n));
+                    }
+                }
+                Op::DuplicateLast(n) => {
+                    if let Some(&last) = w.last() {
+                        w.extend(std::iter::repeat_n(last, n));
+                    }
+                }
+                Op::DuplicateAll => w = w.iter().flat_map(|&c| [c, c]).collect(),
+                Op::SwapFront => {
+                    if len >= 2 {
+                        w.swap(0, 1);
+                    }
+                }
+                Op::SwapBack => {
+                    if len >= 2 {
+                        w.swap(len - 1, len - 2);
+                    }
+                }
+                Op::Swap(n, m) => {
+                    if n < len && m < len {
+                        w.swap(n, m);
+                    }
+                }
+                Op::BitwiseLeft(n) => {
+                    if let Some(c) = w.get_mut(n) {
+                        *c = shift(*c, |x| x.checked_shl(1));
+                    }
+                }
+                Op::BitwiseRight(n) => {
+                    if let Some(c) = w.get_mut(n) {
+                        *c = shift(*c, |x| Some(x >> 1));
+                    }
+                }
+                Op::Increment(n) => {
+                    if let Some(c) = w.get_mut(n) {
+                        *c = shift(*c, |x| x.checked_add(1));
+                    }
+                }
+                Op::Decrement(n) => {
+                    if let Some(c) = w.get_mut(n) {
+                        *c = shift(*c, |x| x.checked_sub(1));
+                    }
+                }
+                Op::ReplaceNext(n) => {
+                    if n + 1 < len {
+                        w[n] = w[n + 1];
+                    }
+                }
+                Op::ReplacePrevious(n) => {
+                    if n >= 1 && n < len {
+                        w[n] = w[n - 1];
+                    }
+                }
+                Op::DuplicateBlockFront(n) => {
+                    if n <= len {
+                        w.splice(0..0, w[..n].to_vec());
+                    }
+                }
+                Op::DuplicateBlockBack(n) => {
+                    if n <= len {
+                        w.extend_from_within(len - n..);
+                    }
+                }
+                Op::Title | Op::TitleSeparator(_) => {
+                    let separator = match *op {
+                        Op::TitleSeparator(separator) => separator,
+                        _ => ' ',
+                    };
+                    let mut after_separator = true;
+                    w = w
+                        .iter()
+                        .flat_map(|&c| {
+                            let out: Vec<char> = if after_separator {
+                                c.to_uppercase().collect()
+                            } else {
+                                c.to_lowercase().collect()
+                            };
+                            after_separator = c == separator;
+                            out
+                        })
+                        .collect();
+                }
+                Op::RejectLonger(n) => {
+                    if len > n {
+                        return None;
+                    }
+                }
+                Op::RejectShorter(n) => {
+                    if len < n {
+                        return None;
+                    }
+                }
+                Op::RejectLength(n) => {
+                    if len != n {
+                        return None;
+                    }
+                }
+                Op::RejectContains(c) => {
+                    if w.contains(&c) {
+                        return None;
+                    }
+                }
+                Op::RejectMissing(c) => {
+                    if !w.contains(&c) {
+                        return None;
+                    }
+                }
+                Op::RejectFirst(c) => {
+                    if w.first() != Some(&c) {
+                        return None;
+                    }
+                }
+                Op::RejectLast(c) => {
+                    if w.last() != Some(&c) {
+                        return None;
+                    }
+                }
+                Op::RejectAt(n, c) => {
+                    if w.get(n) != Some(&c) {
+                        return None;
+                    }
+                }
+                Op::RejectCount(n, c) => {
+                    if w.iter().filter(|&&x| x == c).count() < n {
+                        return None;
+                    }
+                }
+            }
+            // Characters are at least a byte, so this bounds every step
+            if w.len() > MAX_WORD_LEN {
+                return None;
+            }
+        }
+        let word: String = w.into_iter().collect();
+        (word.len() <= MAX_WORD_LEN).then_some(word)
+    }
+}
+
+/// An ordered list of rules, every one applied to each word
+#[derive(Debug, Clone)]
+pub struct RuleSet {
+    rules: Vec<Rule>,
+}
+
+impl RuleSet {
+    /// Parses a rule file, one rule per line
+    ///
+    /// Blank lines and lines starting with # are ignored. Rules this engine
+    /// does not understand are skipped with a warning, like hashcat does.
+    pub fn parse<R: BufRead>(reader: R) -> Result<Self, RuleError> {
+        trace!("Parse rules");
+        let mut rules = Vec::new();
+        for (index, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            match line.parse() {
+                Ok(rule) => rules.push(rule),
+                Err(error) => warn!("Skipping rule on line {}: {error}", index + 1),
+            }
+        }
+
+        if rules.is_empty() {
+            return Err(RuleError::EmptyRuleSet);
+        }
+        debug!("Parsed {} rules", rules.len());
+        Ok(RuleSet { rules })
+    }
+
+    /// The built in best64 rule set
+    pub fn best64() -> Result<Self, RuleError> {
+        Self::parse(BEST64.as_bytes())
+    }
+
+    /// A single rule that leaves every word unchanged
+    pub fn noop() -> Self {
+        RuleSet {
+            rules: vec![Rule {
+                ops: vec![Op::Noop],
+                source: ":".to_string(),
+            }],
+        }
+    }
+
+    /// Number of rules
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    /// Whether there are no rules
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Every candidate the rules make from the word, in rule order
+    pub fn apply<'a>(&'a self, word: &'a str) -> impl Iterator<Item = String> + 'a {
+        self.rules.iter().filter_map(move |rule| rule.apply(word))
+    }
+}
@@ -0,0 +1,149 @@
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io::{BufRead, BufReader, BufWriter, Write},
+    ops::Range,
+    path::Path,
+};
+
+use thiserror::Error;
+use tracing::trace;
+
+use crate::targets::Target;
+
+/// First line of every session file
+const SESSION_MAGIC: &str = "hashassin-session v1";
+
+/// Errors for session files
+#[derive(Error, Debug)]
+pub enum SessionError {
+    #[error("Invalid session file on line {line}: {reason}")]
+    InvalidSession { line: usize, reason: String },
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+/// Everything needed to pick an attack up where it stopped
+///
+/// The attack and its parameters are kept as the command line arguments that
+/// started it, so a restored run parses them exactly like the first one.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Session {
+    /// Arguments of the crack command, without the command name
+    pub args: Vec<String>,
+    /// Candidates, words or users fully tried, in the order the attack hands them out
+    pub position: u128,
+    /// Targets not cracked yet, None if the attack does not track them
+    pub remaining: Option<Vec<Target>>,
+}
+
+impl Session {
+    /// Writes the session next to its final path first, so a crash mid write keeps the old checkpoint
+    pub fn save(&self, path: &Path) -> Result<(), SessionError> {
+        trace!("Save session");
+        let temporary = path.with_extension("tmp");
+        {
+            let mut writer = BufWriter::new(File::create(&temporary)?);
+            writeln!(writer, "{SESSION_MAGIC}")?;
+            // Arguments are hex encoded so tabs and newlines survive
+            for arg in &self.args {
+                writeln!(writer, "arg\t{}", hex::encode(arg))?;
+            }
+            writeln!(writer, "position\t{}", self.position)?;
+            if let Some(remaining) = &self.remaining {
+                writeln!(writer, "remaining\t{}", remaining.len())?;
+                for target in remaining {
+                    writeln!(writer, "target\t{target}")?;
+                }
+            }
+            writer.flush()?;
+        }
+        fs::rename(temporary, path)?;
+        Ok(())
+    }
+
+    /// Reads a session written by save
+    pub fn load(path: &Path) -> Result<Self, SessionError> {
+        trace!("Load session");
+        let invalid = |line: usize, reason: &str| SessionError::InvalidSession {
+            line,
+            reason: reason.to_string(),
+        };
+
+        let mut lines = BufReader::new(File::open(path)?).lines();
+        match lines.next() {
+            Some(Ok(magic)) if magic == SESSION_MAGIC => {}
+            Some(Err(error)) => return Err(error.into()),
+            _ => return Err(invalid(1, "missing header")),
+        }
+
+        let mut session = Session::default();
+        for (index, line) in lines.enumerate() {
+            let line = line?;
+            let line_number = index + 2;
+            let (key, value) = line
+                .split_once('\t')
+                .ok_or_else(|| invalid(line_number, "expected key<TAB>value"))?;
+            match key {
+                "arg" => session.args.push(
+                    hex::decode(value)
+                        .ok()
+                        .and_then(|bytes| String::from_utf8(bytes).ok())
+                        .ok_or_else(|| invalid(line_number, "bad argument"))?,
+                ),
+                "position" => {
+                    session.position = value
+                        .parse()
+                        .map_err(|_| invalid(line_number, "bad position"))?
+                }
+                "remaining" => session.remaining = Some(Vec::new()),
+                "target" => session.remaining.get_or_insert_with(Vec::new).push(
+                    value
+                        .parse()
+                        .map_err(|reason: String| invalid(line_number, &reason))?,
+                ),
+                _ => return Err(invalid(line_number, "unknown key")),
+            }
+        }
+        Ok(session)
+    }
+}
+
+/// Tracks how far an attack has got when work finishes out of order
+///
+/// Threads finish ranges in any order, so the checkpoint is the end of the
+/// longest run of finished ranges from the start. Restoring from it may redo
+/// a little work but never skips any.
+#[derive(Debug, Clone, Default)]
+pub struct Watermark {
+    /// Everything before this is done
+    position: u128,
+    /// Finished ranges after the position, start to end
+    finished: BTreeMap<u128, u128>,
+}
+
+impl Watermark {
+    /// Starts tracking from a position restored from a session
+    pub fn new(position: u128) -> Self {
+        Watermark {
+            position,
+            finished: BTreeMap::new(),
+        }
+    }
+
+    /// Marks a range as done and moves the position past every range now joined to it
+    pub fn complete(&mut self, range: Range<u128>) {
+        if range.end <= self.position {
+            return;
+        }
+        self.finished.insert(range.start, range.end);
+        while let Some(end) = self.finished.remove(&self.position) {
+            self.position = end;
+        }
+    }
+
+    /// Everything before this is done
+    pub fn position(&self) -> u128 {
+        self.position
+    }
+}
@@ -0,0 +1,650 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::OnceLock,
+    time::{Duration, Instant},
+};
+
+use tracing::{debug, trace};
+
+use crate::{keyboard::Layout, HashGenerator, PasswordGenerator, SUPPORTED_ALGORITHMS};
+
+/// Common passwords and words, most popular first
+const COMMON_PASSWORDS: &str = include_str!("../data/common_passwords.txt");
+
+/// Guesses per character for anything no pattern explains
+const BRUTEFORCE_CARDINALITY: f64 = 10.0;
+
+/// Year used as the reference point for date guesses
+const REFERENCE_YEAR: i64 = 2026;
+
+/// Years closer than this to the reference year still count this many guesses
+const MIN_YEAR_SPACE: i64 = 20;
+
+/// Only this many characters are searched for patterns, the rest counts as brute force
+const MAX_ANALYZED_CHARS: usize = 100;
+
+/// How long each algorithm is benchmarked for
+const BENCHMARK_TIME: Duration = Duration::from_millis(100);
+
+/// Salt used while benchmarking, long enough for Argon2
+const BENCHMARK_SALT: &[u8] = b"hashassin-bench";
+
+/// Kind of structure found in a password
+#[derive(Debug, Clone, PartialEq)]
+pub enum Pattern {
+    /// Common password or word, rank 1 is the most common
+    Dictionary {
+        rank: usize,
+        reversed: bool,
+        l33t: bool,
+    },
+    /// Run of evenly spaced characters like abc or 9753
+    Sequence { ascending: bool },
+    /// Adjacent keys on a QWERTY keyboard like qwerty or zxcvbn
+    KeyboardWalk { turns: usize },
+    /// Year or full date like 1999 or 12/05/1990
+    Date { year: i64 },
+    /// Block repeated back to back like aaa or abcabc
+    Repeat { block: String, count: usize },
+    /// Characters explained by no other pattern
+    BruteForce,
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Pattern::Dictionary { .. } => "dictionary",
+            Pattern::Sequence { .. } => "sequence",
+            Pattern::KeyboardWalk { .. } => "keyboard-walk",
+            Pattern::Date { .. } => "date",
+            Pattern::Repeat { .. } => "repeat",
+            Pattern::BruteForce => "bruteforce",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// A pattern covering part of the password
+#[derive(Debug, Clone)]
+pub struct PatternMatch {
+    /// What was found
+    pub pattern: Pattern,
+    /// The matched part of the password
+    pub token: String,
+    /// Index of the first char covered
+    pub start: usize,
+    /// Index one past the last char covered
+    pub end: usize,
+    /// log10 of the guesses needed to find this part on its own
+    pub guesses_log10: f64,
+}
+
+/// Hashing speed of one algorithm
+#[derive(Debug, Clone)]
+pub struct Throughput {
+    /// Name as accepted by HashGenerator
+    pub algorithm: String,
+    /// Hashes computed per second on one thread
+    pub hashes_per_second: f64,
+}
+
+/// Time to find the password with one algorithm
+#[derive(Debug, Clone)]
+pub struct CrackTime {
+    /// Name as accepted by HashGenerator
+    pub algorithm: String,
+    /// Seconds needed in the worst case, may be infinite for very strong passwords
+    pub seconds: f64,
+}
+
+/// Result of estimating a password's strength
+#[derive(Debug, Clone)]
+pub struct StrengthEstimate {
+    /// Length times log2 of the size of the character pool used
+    pub entropy_bits: f64,
+    /// Guesses an attacker that knows common patterns needs, may be infinite
+    pub guesses: f64,
+    /// log10 of guesses, always finite
+    pub guesses_log10: f64,
+    /// Patterns making up the cheapest way to guess the password, in order
+    pub patterns: Vec<PatternMatch>,
+    /// Crack time for every supported algorithm
+    pub crack_times: Vec<CrackTime>,
+}
+
+/// Estimates how hard a password is to guess, zxcvbn style
+pub fn estimate(password: &str) -> StrengthEstimate {
+    estimate_with(password, measured_throughput())
+}
+
+/// Same as estimate but with caller supplied hashing speeds
+pub fn estimate_with(password: &str, throughput: &[Throughput]) -> StrengthEstimate {
+    trace!("Estimate strength");
+    let chars: Vec<char> = password.chars().collect();
+    let (analyzed, rest) = chars.split_at(chars.len().min(MAX_ANALYZED_CHARS));
+
+    let (mut guesses_log10, mut patterns) = most_guessable(analyzed);
+    if !rest.is_empty() {
+        guesses_log10 += rest.len() as f64 * BRUTEFORCE_CARDINALITY.log10();
+        patterns.push(PatternMatch {
+            pattern: Pattern::BruteForce,
+            token: rest.iter().collect(),
+            start: analyzed.len(),
+            end: chars.len(),
+            guesses_log10: rest.len() as f64 * BRUTEFORCE_CARDINALITY.log10(),
+        });
+    }
+
+    let crack_times = throughput
+        .iter()
+        .map(|t| CrackTime {
+            algorithm: t.algorithm.clone(),
+            seconds: 10_f64.powf(guesses_log10 - t.hashes_per_second.log10()),
+        })
+        .collect();
+
+    StrengthEstimate {
+        entropy_bits: chars.len() as f64 * pool_size(&chars).log2(),
+        guesses: 10_f64.powf(guesses_log10),
+        guesses_log10,
+        patterns,
+        crack_times,
+    }
+}
+
+/// Hashing speed of every supported algorithm, benchmarked once per process
+pub fn measured_throughput() -> &'static [Throughput] {
+    static THROUGHPUT: OnceLock<Vec<Throughput>> = OnceLock::new();
+    THROUGHPUT.get_or_init(|| {
+        SUPPORTED_ALGORITHMS
+            .iter()
+            .filter_map(|algorithm| benchmark(algorithm))
+            .collect()
+    })
+}
+
+/// Hashes random passwords for BENCHMARK_TIME and reports the rate
+fn benchmark(algorithm: &str) -> Option<Throughput> {
+    let password_generator = PasswordGenerator::default();
+    let start = Instant::now();
+    let mut hashed: u64 = 0;
+    while start.elapsed() < BENCHMARK_TIME {
+        HashGenerator::new(password_generator.generate().ok()?, algorithm.to_string())
+            .with_salt(Some(BENCHMARK_SALT.to_vec()))
+            .hash_password()
+            .ok()?;
+        hashed += 1;
+    }
+
+    let hashes_per_second = hashed as f64 / start.elapsed().as_secs_f64();
+    debug!("{algorithm}: {hashes_per_second:.0} hashes per second");
+    Some(Throughput {
+        algorithm: algorithm.to_string(),
+        hashes_per_second,
+    })
+}
+
+/// Size of the character pool the password draws from
+fn pool_size(chars: &[char]) -> f64 {
+    let mut pool = 0.0;
+    if chars.iter().any(|c| c.is_ascii_lowercase()) {
+        pool += 26.0;
+    }
+    if chars.iter().any(|c| c.is_ascii_uppercase()) {
+        pool += 26.0;
+    }
+    if chars.iter().any(|c| c.is_ascii_digit()) {
+        pool += 10.0;
+    }
+    if chars
+        .iter()
+        .any(|c| c.is_ascii() && !c.is_ascii_alphanumeric())
+    {
+        pool += 33.0;
+    }
+    if chars.iter().any(|c| !c.is_ascii()) {
+        pool += 100.0;
+    }
+    f64::max(pool, 1.0)
+}
+
+/// Finds the sequence of non overlapping patterns that is cheapest to guess
+/// Returns log10 of the guesses and the patterns, with brute force filling the gaps
+fn most_guessable(chars: &[char]) -> (f64, Vec<PatternMatch>) {
+    most_guessable_cached(chars, &mut HashMap::new())
+}
+
+/// most_guessable sharing repeated block estimates through cache
+fn most_guessable_cached(
+    chars: &[char],
+    cache: &mut HashMap<Vec<char>, f64>,
+) -> (f64, Vec<PatternMatch>) {
+    let n = chars.len();
+    if n == 0 {
+        return (0.0, Vec::new());
+    }
+
+    let mut matches = find_matches(chars, cache);
+    for start in 0..n {
+        for end in start + 1..=n {
+            matches.push(bruteforce_match(chars, start, end));
+        }
+    }
+
+    // best[end][count] = (log10 guesses, index of last match) over prefixes made of count matches
+    let mut best: Vec<Vec<Option<(f64, usize)>>> = vec![vec![None; n + 1]; n + 1];
+    let mut by_end: Vec<Vec<usize>> = vec![Vec::new(); n + 1];
+    for (index, m) in matches.iter().enumerate() {
+        by_end[m.end].push(index);
+    }
+    for end in 1..=n {
+        for &index in &by_end[end] {
+            let m = &matches[index];
+            for count in 1..=end {
+                let previous = if m.start == 0 {
+                    (count == 1).then_some(0.0)
+                } else {
+                    best[m.start][count - 1].map(|(g, _)| g)
+                };
+                if let Some(previous) = previous {
+                    let total = previous + m.guesses_log10;
+                    if best[end][count].is_none_or(|(g, _)| total < g) {
+                        best[end][count] = Some((total, index));
+                    }
+                }
+            }
+        }
+    }
+
+    // Every extra pattern multiplies the attacker's work by the number of orderings
+    let mut chosen = (f64::INFINITY, 0);
+    let mut ln_fact = 0.0;
+    for (count, entry) in best[n].iter().enumerate().skip(1) {
+        ln_fact += (count as f64).ln();
+        if let Some((g, _)) = entry {
+            let total = g + ln_fact / std::f64::consts::LN_10;
+            if total < chosen.0 {
+                chosen = (total, count);
+            }
+        }
+    }
+
+    let mut patterns = Vec::with_capacity(chosen.1);
+    let (mut end, mut count) = (n, chosen.1);
+    while count > 0 {
+        let Some((_, index)) = best[end][count] else {
+            break;
+        };
+        let m = matches[index].clone();
+        end = m.start;
+        count -= 1;
+        patterns.push(m);
+    }
+    patterns.reverse();
+
+    (chosen.0, patterns)
+}
+
+/// Brute force match of chars[start..end]
+fn bruteforce_match(chars: &[char], start: usize, end: usize) -> PatternMatch {
+    let len = end - start;
+    let minimum: f64 = if len == 1 { 11.0 } else { 51.0 };
+    PatternMatch {
+        pattern: Pattern::BruteForce,
+        token: chars[start..end].iter().collect(),
+        start,
+        end,
+        guesses_log10: f64::max(len as f64 * BRUTEFORCE_CARDINALITY.log10(), minimum.log10()),
+    }
+}
+
+/// Runs every matcher over the password
+fn find_matches(chars: &[char], cache: &mut HashMap<Vec<char>, f64>) -> Vec<PatternMatch> {
+    let mut matches = dictionary_matches(chars);
+    matches.extend(sequence_matches(chars));
+    matches.extend(keyboard_matches(chars));
+    matches.extend(date_matches(chars));
+    matches.extend(repeat_matches(chars, cache));
+    matches
+}
+
+/// Rank of every common password, 1 being the most common
+fn ranked_dictionary() -> &'static HashMap<String, usize> {
+    static DICTIONARY: OnceLock<HashMap<String, usize>> = OnceLock::new();
+    DICTIONARY.get_or_init(|| {
+        COMMON_PASSWORDS
+            .lines()
+            .filter(|line| !line.is_empty())
+            .enumerate()
+            .map(|(rank, word)| (word.to_string(), rank + 1))
+            .collect()
+    })
+}
+
+/// Undoes common l33t substitutions, None if there were none
+fn unl33t(token: &str) -> Option<String> {
+    let mut changed = false;
+    let plain = token
+        .chars()
+        .map(|c| {
+            let plain = match c {
+                '4' | '@' => 'a',
+                '3' => 'e',
+                '1' | '!' => 'i',
+                '0' => 'o',
+                '$' | '5' => 's',
+                '7' => 't',
+                '|' => 'l',
+                _ => return c,
+            };
+            changed = true;
+            plain
+        })
+        .collect();
+    changed.then_some(plain)
+}
+
+/// Ways to capitalize a word that has the given number of upper and lower case letters
+fn uppercase_variations(token: &str) -> f64 {
+    let upper = token.chars().filter(|c| c.is_uppercase()).count();
+    let lower = token.chars().filter(|c| c.is_lowercase()).count();
+    if upper == 0 {
+        return 1.0;
+    }
+    // First letter only and all caps are the usual choices
+    let first_upper = token.chars().next().is_some_and(char::is_uppercase);
+    if lower == 0 || (upper == 1 && first_upper) {
+        return 2.0;
+    }
+    (1..=upper.min(lower))
+        .map(|i| binomial(upper + lower, i))
+        .sum()
+}
+
+/// n choose k as a float
+fn binomial(n: usize, k: usize) -> f64 {
+    (1..=k).fold(1.0, |acc, i| acc * (n + 1 - i) as f64 / i as f64)
+}
+
+/// Common passwords, plain, reversed or with l33t substitutions
+fn dictionary_matches(chars: &[char]) -> Vec<PatternMatch> {
+    let dictionary = ranked_dictionary();
+    let mut matches = Vec::new();
+    for start in 0..chars.len() {
+        for end in start + 1..=chars.len() {
+            let token: String = chars[start..end].iter().collect();
+            let lowered = token.to_lowercase();
+            let reversed: String = lowered.chars().rev().collect();
+            let l33t = unl33t(&lowered);
+
+            let found = [
+                (Some(lowered), false, false),
+                (Some(reversed), true, false),
+                (l33t, false, true),
+            ]
+            .into_iter()
+            .find_map(|(word, reversed, l33t)| {
+                dictionary.get(&word?).map(|&rank| (rank, reversed, l33t))
+            });
+
+            if let Some((rank, reversed, l33t)) = found {
+                let guesses = rank as f64
+                    * uppercase_variations(&token)
+                    * if reversed { 2.0 } else { 1.0 }
+                    * if l33t { 2.0 } else { 1.0 };
+                matches.push(PatternMatch {
+                    pattern: Pattern::Dictionary {
+                        rank,
+                        reversed,
+                        l33t,
+                    },
+                    token,
+                    start,
+                    end,
+                    guesses_log10: guesses.log10(),
+                });
+            }
+        }
+    }
+    matches
+}
+
+/// Runs of at least three characters with the same small step between them
+fn sequence_matches(chars: &[char]) -> Vec<PatternMatch> {
+    let mut matches = Vec::new();
+    let mut start = 0;
+    while start + 2 < chars.len() {
+        let delta = chars[start + 1] as i64 - chars[start] as i64;
+        let mut end = start + 2;
+        while end < chars.len() && chars[end] as i64 - chars[end - 1] as i64 == delta {
+            end += 1;
+        }
+
+        if end - start >= 3 && delta != 0 && delta.abs() <= 5 {
+            let first = chars[start];
+            let base: f64 = if "aAzZ019".contains(first) {
+                4.0
+            } else if first.is_ascii_digit() {
+                10.0
+            } else {
+                26.0
+            };
+            let ascending = delta > 0;
+            let guesses = base * (end - start) as f64 * if ascending { 1.0 } else { 2.0 };
+            matches.push(PatternMatch {
+                pattern: Pattern::Sequence { ascending },
+                token: chars[start..end].iter().collect(),
+                start,
+                end,
+                guesses_log10: guesses.log10(),
+            });
+            start = end - 1;
+        } else {
+            start += 1;
+        }
+    }
+    matches
+}
+
+/// Runs of at least three adjacent keys
+fn keyboard_matches(chars: &[char]) -> Vec<PatternMatch> {
+    // Average number of neighbours a key has and number of keys a walk can start on
+    const AVERAGE_DEGREE: f64 = 4.6;
+    const STARTING_KEYS: f64 = 94.0;
+
+    let mut matches = Vec::new();
+    let mut start = 0;
+    while start + 2 < chars.len() {
+        let mut end = start + 1;
+        let mut turns = 0;
+        let mut last_step = None;
+        while end < chars.len() {
+            let Some(step) = Layout::Qwerty.step(chars[end - 1], chars[end]) else {
+                break;
+            };
+            if last_step != Some(step) {
+                turns += 1;
+            }
+            last_step = Some(step);
+            end += 1;
+        }
+
+        if end - start >= 3 {
+            // Sum over walk lengths and turn counts of the ways to make such a walk
+            let len = end - start;
+            let guesses: f64 = (2..=len)
+                .map(|i| {
+                    (1..=turns.min(i - 1))
+                        .map(|j| {
+                            binomial(i - 1, j - 1) * STARTING_KEYS * AVERAGE_DEGREE.powi(j as i32)
+                        })
+                        .sum::<f64>()
+                })
+                .sum();
+            let token: String = chars[start..end].iter().collect();
+            let shifted = token.chars().filter(|c| !c.is_ascii_lowercase()).count();
+            let guesses = guesses * if shifted > 0 { 2.0 } else { 1.0 };
+            matches.push(PatternMatch {
+                pattern: Pattern::KeyboardWalk { turns },
+                token,
+                start,
+                end,
+                guesses_log10: guesses.log10(),
+            });
+            start = end - 1;
+        } else {
+            start += 1;
+        }
+    }
+    matches
+}
+
+/// Turns a two or four digit year into a full year
+fn full_year(year: i64, digits: usize) -> Option<i64> {
+    match digits {
+        2 if year > 50 => Some(1900 + year),
+        2 => Some(2000 + year),
+        4 if (1900..=2099).contains(&year) => Some(year),
+        _ => None,
+    }
+}
+
+/// Reads a token as a year or a full day/month/year date in any common order
+fn parse_date(token: &str) -> Option<(i64, bool)> {
+    let separated: Vec<&str> = token.split(['/', '-', '.', '_', ' ']).collect();
+    let parts: Vec<(i64, usize)> = if separated.len() == 3 {
+        separated
+            .iter()
+            .map(|p| p.parse().ok().map(|v| (v, p.len())))
+            .collect::<Option<_>>()?
+    } else if separated.len() == 1 && token.chars().all(|c| c.is_ascii_digit()) {
+        let digits = token.len();
+        if digits == 4 {
+            // Plain year
+            let year = token.parse().ok()?;
+            return full_year(year, 4).map(|year| (year, false));
+        }
+        // Try every split of the digits into three date parts
+        let splits: &[(usize, usize)] = match digits {
+            6 => &[(2, 2)],
+            8 => &[(2, 2), (4, 2)],
+            _ => return None,
+        };
+        let parse = |range: std::ops::Range<usize>| token[range].parse::<i64>().ok();
+        for &(first, second) in splits {
+            let candidate = [
+                (parse(0..first)?, first),
+                (parse(first..first + second)?, second),
+                (parse(first + second..digits)?, digits - first - second),
+            ];
+            if let Some(date) = date_from_parts(&candidate) {
+                return Some((date, true));
+            }
+        }
+        return None;
+    } else {
+        return None;
+    };
+
+    date_from_parts(&parts).map(|year| (year, true))
+}
+
+/// Checks if three numbers form a day, month and year in some order, returning the year
+fn date_from_parts(parts: &[(i64, usize)]) -> Option<i64> {
+    if parts.len() != 3 {
+        return None;
+    }
+    let is_day_month = |a: i64, b: i64| (1..=31).contains(&a) && (1..=12).contains(&b);
+    let (first, second, third) = (parts[0], parts[1], parts[2]);
+    // Year last (dmy or mdy) or year first (ymd)
+    if is_day_month(first.0, second.0) || is_day_month(second.0, first.0) {
+        if let Some(year) = full_year(third.0, third.1) {
+            return Some(year);
+        }
+    }
+    if is_day_month(third.0, second.0) {
+        return full_year(first.0, first.1);
+    }
+    None
+}
+
+/// Years and full dates
+fn date_matches(chars: &[char]) -> Vec<PatternMatch> {
+    let mut matches = Vec::new();
+    for start in 0..chars.len() {
+        for end in start + 4..=chars.len().min(start + 10) {
+            let token: String = chars[start..end].iter().collect();
+            if let Some((year, full_date)) = parse_date(&token) {
+                let year_space = (year - REFERENCE_YEAR).abs().max(MIN_YEAR_SPACE) as f64;
+                let guesses = if full_date {
+                    let separated = !token.chars().all(|c| c.is_ascii_digit());
+                    year_space * 365.0 * if separated { 4.0 } else { 1.0 }
+                } else {
+                    year_space
+                };
+                matches.push(PatternMatch {
+                    pattern: Pattern::Date { year },
+                    token,
+                    start,
+                    end,
+                    guesses_log10: guesses.log10(),
+                });
+            }
+        }
+    }
+    matches
+}
+
+/// Blocks repeated back to back, like aaa or abcabc
+/// Only the longest repeat at each start is kept, block estimates are cached by content
+fn repeat_matches(chars: &[char], cache: &mut HashMap<Vec<char>, f64>) -> Vec<PatternMatch> {
+    let mut matches = Vec::new();
+    for start in 0..chars.len() {
+        // (end, block_len) of the repeat covering the most chars, shortest block on ties
+        let mut longest: Option<(usize, usize)> = None;
+        for block_len in 1..=(chars.len() - start) / 2 {
+            let block = &chars[start..start + block_len];
+            let mut count = 1;
+            while start + (count + 1) * block_len <= chars.len()
+                && &chars[start + count * block_len..start + (count + 1) * block_len] == block
+            {
+                count += 1;
+            }
+
+            // Single characters need three copies before they look like a pattern
+            let end = start + count * block_len;
+            if count >= 2
+                && (block_len > 1 || count >= 3)
+                && longest.is_none_or(|(longest_end, _)| end > longest_end)
+            {
+                longest = Some((end, block_len));
+            }
+        }
+
+        let Some((end, block_len)) = longest else {
+            continue;
+        };
+        let block = &chars[start..start + block_len];
+        let block_guesses_log10 = match cache.get(block) {
+            Some(&guesses_log10) => guesses_log10,
+            None => {
+                let (guesses_log10, _) = most_guessable_cached(block, cache);
+                cache.insert(block.to_vec(), guesses_log10);
+                guesses_log10
+            }
+        };
+        let count = (end - start) / block_len;
+        matches.push(PatternMatch {
+            pattern: Pattern::Repeat {
+                block: block.iter().collect(),
+                count,
+            },
+            token: chars[start..end].iter().collect(),
+            start,
+            end,
+            guesses_log10: block_guesses_log10 + (count as f64).log10(),
+        });
+    }
+    matches
+}
@@ -0,0 +1,654 @@
+use std::{
+    cmp::Ordering,
+    collections::HashMap,
+    fmt,
+    fs::File,
+    io::{self, BufRead, BufReader, Seek, Write},
+    num::NonZeroUsize,
+    ops::Deref,
+    path::Path,
+    str::FromStr,
+    sync::Arc,
+};
+
+use memmap2::Mmap;
+use thiserror::Error;
+use tracing::{trace, warn};
+
+use crate::{
+    reduction::{Reducer, Reduction},
+    SUPPORTED_ALGORITHMS,
+};
+
+/// First word of every rainbow table with a header
+pub const TABLE_MAGIC: &str = "hashassin-rainbow";
+
+/// Format version written by this crate
+///
+/// Version 1 tables are always text, version 2 added binary tables and the
+/// format field, version 3 added the reduction version field and version 4
+/// the min password length field.
+pub const TABLE_VERSION: u32 = 4;
+
+/// Errors for rainbow table files
+#[derive(Error, Debug)]
+pub enum TableError {
+    #[error("Invalid rainbow table header on line {line}: {reason}")]
+    InvalidHeader { line: usize, reason: String },
+    #[error("Rainbow table format version {0} is not supported, this build reads versions 1 to {TABLE_VERSION}")]
+    UnsupportedVersion(u32),
+    #[error("Incompatible rainbow table: {0}")]
+    Incompatible(String),
+    #[error("Invalid rainbow table chain on line {0}, expected start<TAB>end")]
+    InvalidChain(usize),
+    #[error("Invalid binary rainbow table: {0}")]
+    InvalidBinary(String),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+/// How the chains after the header are stored
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TableFormat {
+    /// `start<TAB>end` password lines
+    #[default]
+    Text,
+    /// Fixed width little endian end and start keyspace indices, sorted by end
+    Binary,
+}
+
+impl FromStr for TableFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "text" => Ok(TableFormat::Text),
+            "binary" => Ok(TableFormat::Binary),
+            _ => Err(format!("unknown table format {s}")),
+        }
+    }
+}
+
+impl fmt::Display for TableFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TableFormat::Text => write!(f, "text"),
+            TableFormat::Binary => write!(f, "binary"),
+        }
+    }
+}
+
+/// Parameters a rainbow table was built with, stored at the top of the file
+///
+/// The header is a `hashassin-rainbow <version>` line, one `key<TAB>value`
+/// line per field and an empty line, followed by the `start<TAB>end` chains.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableHeader {
+    /// Hashing algorithm of every link
+    pub algorithm: String,
+    /// Hash and reduce steps in each chain
+    pub chain_length: NonZeroUsize,
+    /// Length of the passwords the reduction makes
+    pub password_length: NonZeroUsize,
+    /// Length of the shortest passwords, the same as the password length for tables from before it was recorded
+    pub min_password_length: NonZeroUsize,
+    /// Characters of the passwords the reduction makes
+    pub charset: Vec<char>,
+    /// Reduction function between links
+    pub reduction: Reduction,
+    /// Version of the reduction function, 1 for tables from before it was recorded
+    pub reduction_version: u32,
+    /// Which table of a set this is, each uses its own reduction functions
+    pub table_index: u64,
+    /// Number of chains in the table
+    pub chains: u64,
+    /// How the chains are stored
+    pub format: TableFormat,
+}
+
+impl TableHeader {
+    /// Writes the header and the empty line that ends it
+    pub fn write<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<()> {
+        writeln!(writer, "{TABLE_MAGIC} {TABLE_VERSION}")?;
+        writeln!(writer, "algorithm\t{}", self.algorithm)?;
+        writeln!(writer, "chain_length\t{}", self.chain_length)?;
+        writeln!(writer, "password_length\t{}", self.password_length)?;
+        writeln!(writer, "min_password_length\t{}", self.min_password_length)?;
+        // The charset is hex encoded UTF-8, since it can hold tabs and control characters
+        writeln!(
+            writer,
+            "charset\t{}",
+            hex::encode(self.charset.iter().collect::<String>())
+        )?;
+        writeln!(writer, "reduction\t{}", self.reduction)?;
+        writeln!(writer, "reduction_version\t{}", self.reduction_version)?;
+        writeln!(writer, "table_index\t{}", self.table_index)?;
+        writeln!(writer, "chains\t{}", self.chains)?;
+        writeln!(writer, "format\t{}", self.format)?;
+        writeln!(writer)
+    }
+
+    /// Parses the header after its first line, counting the lines read
+    fn parse<R: BufRead>(
+        version: &str,
+        reader: &mut R,
+        line_number: &mut usize,
+    ) -> Result<Self, TableError> {
+        let invalid = |line: usize, reason: &str| TableError::InvalidHeader {
+            line,
+            reason: reason.to_string(),
+        };
+        let version: u32 = version.parse().map_err(|_| invalid(1, "bad version"))?;
+        if !(1..=TABLE_VERSION).contains(&version) {
+            return Err(TableError::UnsupportedVersion(version));
+        }
+
+        let mut fields = HashMap::new();
+        loop {
+            let mut line = String::new();
+            *line_number += 1;
+            let line_number = *line_number;
+            if reader.read_line(&mut line)? == 0 {
+                return Err(invalid(line_number, "header does not end"));
+            }
+            let line = line.trim_end_matches(['\n', '\r']);
+            if line.is_empty() {
+                break;
+            }
+            let (key, value) = line
+                .split_once('\t')
+                .ok_or_else(|| invalid(line_number, "expected key<TAB>value"))?;
+            fields.insert(key.to_string(), (line_number, value.to_string()));
+        }
+
+        let field = |key: &str| {
+            fields
+                .get(key)
+                .ok_or_else(|| invalid(*line_number, &format!("missing {key}")))
+        };
+        fn parsed<T: FromStr>((line, value): &(usize, String), key: &str) -> Result<T, TableError> {
+            value.parse().map_err(|_| TableError::InvalidHeader {
+                line: *line,
+                reason: format!("bad {key}"),
+            })
+        }
+
+        let (charset_line, charset) = field("charset")?;
+        let charset = hex::decode(charset)
+            .ok()
+            .and_then(|bytes| String::from_utf8(bytes).ok())
+            .ok_or_else(|| invalid(*charset_line, "bad charset"))?
+            .chars()
+            .collect();
+        let (reduction_line, reduction) = field("reduction")?;
+        let reduction = reduction
+            .parse()
+            .map_err(|reason: String| invalid(*reduction_line, &reason))?;
+        let reduction_version = match version {
+            1 | 2 => 1,
+            _ => parsed(field("reduction_version")?, "reduction_version")?,
+        };
+        let password_length = parsed(field("password_length")?, "password_length")?;
+        let min_password_length = match version {
+            1..=3 => password_length,
+            _ => parsed(field("min_password_length")?, "min_password_length")?,
+        };
+        let format = match version {
+            1 => TableFormat::Text,
+            _ => {
+                let (format_line, format) = field("format")?;
+                format
+                    .parse()
+                    .map_err(|reason: String| invalid(*format_line, &reason))?
+            }
+        };
+
+        let header = TableHeader {
+            algorithm: field("algorithm")?.1.clone(),
+            chain_length: parsed(field("chain_length")?, "chain_length")?,
+            password_length,
+            min_password_length,
+            charset,
+            reduction,
+            reduction_version,
+            table_index: parsed(field("table_index")?, "table_index")?,
+            chains: parsed(field("chains")?, "chains")?,
+            format,
+        };
+        // No file can hold more chains than this, so a larger count is corrupt
+        let record_width = header.index_width() * 2;
+        if usize::try_from(header.chains)
+            .ok()
+            .and_then(|chains| chains.checked_mul(record_width))
+            .is_none()
+        {
+            return Err(invalid(field("chains")?.0, "too many chains"));
+        }
+        Ok(header)
+    }
+
+    /// Number of passwords the reduction can make, None if it does not fit in a u128
+    pub fn keyspace(&self) -> Option<u128> {
+        (self.min_password_length.get()..=self.password_length.get()).try_fold(
+            0_u128,
+            |total, length| {
+                total.checked_add(
+                    (self.charset.len() as u128).checked_pow(u32::try_from(length).ok()?)?,
+                )
+            },
+        )
+    }
+
+    /// Bytes each keyspace index takes in a binary table
+    pub fn index_width(&self) -> usize {
+        let largest = self.keyspace().unwrap_or(0).saturating_sub(1);
+        ((u128::BITS - largest.leading_zeros()) as usize)
+            .div_ceil(8)
+            .max(1)
+    }
+
+    /// Reduction the table was built with
+    pub fn reducer(&self) -> Result<Reducer, TableError> {
+        Reducer::new(self.reduction, self.charset.clone(), self.password_length)
+            .and_then(|reducer| reducer.with_min_length(self.min_password_length))
+            .and_then(|reducer| reducer.with_version(self.reduction_version))
+            .map_err(|error| TableError::Incompatible(error.to_string()))
+    }
+
+    /// Refuses tables this build cannot crack with
+    pub fn check(&self) -> Result<(), TableError> {
+        if !SUPPORTED_ALGORITHMS.contains(&self.algorithm.as_str()) {
+            return Err(TableError::Incompatible(format!(
+                "algorithm {} is not supported",
+                self.algorithm
+            )));
+        }
+        let reducer = self.reducer()?;
+        if reducer.charset() != self.charset {
+            return Err(TableError::Incompatible(
+                "charset is not sorted and distinct".to_string(),
+            ));
+        }
+        if self.format == TableFormat::Binary && self.keyspace().is_none() {
+            return Err(TableError::Incompatible(
+                "keyspace is too large for a binary table".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Chains of a rainbow table, looked up by their end
+#[derive(Debug, Clone)]
+pub enum Chains {
+    /// Start of each chain by its end, from text tables
+    Text(HashMap<String, String>),
+    /// End and start keyspace indices sorted by end, from binary tables
+    Sorted { reducer: Reducer, records: Records },
+}
+
+impl Chains {
+    /// Number of distinct chain ends
+    pub fn len(&self) -> usize {
+        match self {
+            Chains::Text(chains) => chains.len(),
+            Chains::Sorted { records, .. } => records.len(),
+        }
+    }
+
+    /// Whether there are no chains
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Start of the chain that ends in a password
+    pub fn start_of(&self, end: &str) -> Option<String> {
+        match self {
+            Chains::Text(chains) => chains.get(end).cloned(),
+            Chains::Sorted { reducer, records } => {
+                let start = records.find(reducer.index(end)?)?;
+                Some(reducer.password(start))
+            }
+        }
+    }
+
+    /// Every chain as its start and end passwords
+    pub fn pairs(&self) -> Vec<(String, String)> {
+        match self {
+            Chains::Text(chains) => chains
+                .iter()
+                .map(|(end, start)| (start.clone(), end.clone()))
+                .collect(),
+            Chains::Sorted { reducer, records } => (0..records.len())
+                .map(|record| {
+                    (
+                        reducer.password(records.start(record)),
+                        reducer.password(records.end(record)),
+                    )
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Bytes holding the records of a binary table
+#[derive(Debug)]
+enum RecordBytes {
+    /// Read into memory from a stream
+    Owned(Vec<u8>),
+    /// The whole table file, paged in by the kernel as lookups touch it
+    Mapped(Mmap),
+}
+
+impl Deref for RecordBytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            RecordBytes::Owned(bytes) => bytes,
+            RecordBytes::Mapped(map) => map,
+        }
+    }
+}
+
+/// Fixed width end and start indices of a binary table, sorted by end
+///
+/// Records are decoded where they lie rather than copied out, and clones
+/// share the same bytes, so every cracking thread searches one table.
+#[derive(Debug, Clone)]
+pub struct Records {
+    bytes: Arc<RecordBytes>,
+    /// Where the first record starts in the bytes
+    offset: usize,
+    /// Bytes in each index, so a record is twice this
+    width: usize,
+    len: usize,
+}
+
+/// Interpolation probes before a lookup falls back to bisecting
+///
+/// Ends are reductions of hashes, so they spread evenly over the keyspace and
+/// interpolation usually lands within a few records. The fallback keeps the
+/// worst case logarithmic when they do not.
+const INTERPOLATION_PROBES: u32 = 8;
+
+impl Records {
+    /// Records after `offset`, which must be exactly as many as the header says
+    fn new(bytes: RecordBytes, offset: usize, header: &TableHeader) -> Result<Self, TableError> {
+        let width = header.index_width();
+        let data = bytes.len().saturating_sub(offset);
+        let (len, trailing) = (data / (width * 2), data % (width * 2));
+        if trailing > 0 {
+            return Err(TableError::InvalidBinary(format!(
+                "{trailing} trailing bytes after the last chain"
+            )));
+        }
+        if len as u64 != header.chains {
+            return Err(TableError::InvalidBinary(format!(
+                "header says {} chains but {len} were read",
+                header.chains
+            )));
+        }
+        Ok(Records {
+            bytes: Arc::new(bytes),
+            offset,
+            width,
+            len,
+        })
+    }
+
+    /// Number of records
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether there are no records
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// End index of a record
+    pub fn end(&self, record: usize) -> u128 {
+        let at = self.offset + record * self.width * 2;
+        read_index(&self.bytes[at..at + self.width])
+    }
+
+    /// Start index of a record
+    pub fn start(&self, record: usize) -> u128 {
+        let at = self.offset + record * self.width * 2 + self.width;
+        read_index(&self.bytes[at..at + self.width])
+    }
+
+    /// Start index of the chain ending at `end`
+    pub fn find(&self, end: u128) -> Option<u128> {
+        // Searches records low..high
+        let (mut low, mut high) = (0, self.len);
+        let mut probes = 0;
+        while low < high {
+            let (low_end, high_end) = (self.end(low), self.end(high - 1));
+            if end < low_end || end > high_end {
+                return None;
+            }
+            let middle = if probes < INTERPOLATION_PROBES && high_end > low_end {
+                let fraction = (end - low_end) as f64 / (high_end - low_end) as f64;
+                (low + ((high - 1 - low) as f64 * fraction) as usize).min(high - 1)
+            } else {
+                low + (high - low) / 2
+            };
+            probes += 1;
+            match self.end(middle).cmp(&end) {
+                Ordering::Equal => return Some(self.start(middle)),
+                Ordering::Less => low = middle + 1,
+                Ordering::Greater => high = middle,
+            }
+        }
+        None
+    }
+
+    /// Whether the ends strictly increase, which lookups rely on
+    fn sorted(&self) -> bool {
+        (1..self.len).all(|record| self.end(record - 1) < self.end(record))
+    }
+}
+
+impl From<HashMap<String, String>> for Chains {
+    fn from(chains: HashMap<String, String>) -> Self {
+        Chains::Text(chains)
+    }
+}
+
+/// A rainbow table, read into memory or mapped
+#[derive(Debug, Clone)]
+pub struct TableFile {
+    /// None for tables written before headers existed
+    pub header: Option<TableHeader>,
+    pub chains: Chains,
+}
+
+impl TableFile {
+    /// Reads the header, if there is one, and every chain
+    ///
+    /// The header is checked, so an incompatible table is refused before any
+    /// chain is read.
+    pub fn read<R: BufRead>(mut reader: R) -> Result<Self, TableError> {
+        trace!("Read rainbow table");
+        let mut first = String::new();
+        reader.read_line(&mut first)?;
+        let first = first.trim_end_matches(['\n', '\r']).to_string();
+
+        let mut line_number = 1;
+        let mut chains = HashMap::new();
+        // Chains that end alike are kept once, so lines are counted separately
+        let mut read: u64 = 0;
+        let header = match first.strip_prefix(TABLE_MAGIC) {
+            Some(version) => {
+                let header = TableHeader::parse(version.trim(), &mut reader, &mut line_number)?;
+                header.check()?;
+                if header.format == TableFormat::Binary {
+                    let chains = read_binary(&header, reader)?;
+                    return Ok(TableFile {
+                        header: Some(header),
+                        chains,
+                    });
+                }
+                Some(header)
+            }
+            None => {
+                if !first.is_empty() {
+                    insert_chain(&mut chains, &first, line_number)?;
+                    read += 1;
+                }
+                None
+            }
+        };
+
+        for line in reader.lines() {
+            line_number += 1;
+            let line = line?;
+            if !line.is_empty() {
+                insert_chain(&mut chains, &line, line_number)?;
+                read += 1;
+            }
+        }
+        if let Some(header) = &header {
+            if read != header.chains {
+                warn!(
+                    "Rainbow table header says {} chains but {read} were read",
+                    header.chains
+                );
+            }
+        }
+        Ok(TableFile {
+            header,
+            chains: Chains::Text(chains),
+        })
+    }
+
+    /// Opens a table file, mapping binary tables instead of reading them
+    ///
+    /// A mapped table is only paged in as lookups touch it, so it may be
+    /// larger than memory. Its order is trusted rather than checked, since
+    /// checking would read every page. Text tables are read as by
+    /// [`TableFile::read`].
+    pub fn open(path: &Path) -> Result<Self, TableError> {
+        trace!("Open rainbow table");
+        let mut reader = BufReader::new(File::open(path)?);
+        let mut first = String::new();
+        reader.read_line(&mut first)?;
+        if let Some(version) = first
+            .trim_end_matches(['\n', '\r'])
+            .strip_prefix(TABLE_MAGIC)
+        {
+            let mut line_number = 1;
+            let header = TableHeader::parse(version.trim(), &mut reader, &mut line_number)?;
+            header.check()?;
+            if header.format == TableFormat::Binary {
+                let offset = usize::try_from(reader.stream_position()?)
+                    .map_err(|_| TableError::InvalidBinary("header is too large".to_string()))?;
+                // SAFETY: the table must not be truncated or rewritten while
+                // it is open, which would change the bytes under every reader
+                let map = unsafe { Mmap::map(reader.get_ref())? };
+                // Lookups jump around the file, so reading ahead only wastes
+                // memory. This is only advice, so failing is fine.
+                #[cfg(unix)]
+                let _ = map.advise(memmap2::Advice::Random);
+                let records = Records::new(RecordBytes::Mapped(map), offset, &header)?;
+                return Ok(TableFile {
+                    chains: Chains::Sorted {
+                        reducer: header.reducer()?,
+                        records,
+                    },
+                    header: Some(header),
+                });
+            }
+        }
+        reader.rewind()?;
+        Self::read(reader)
+    }
+}
+
+/// Adds a `start<TAB>end` line, keyed by the end
+fn insert_chain(
+    chains: &mut HashMap<String, String>,
+    line: &str,
+    line_number: usize,
+) -> Result<(), TableError> {
+    let (start, end) = line
+        .split_once('\t')
+        .ok_or(TableError::InvalidChain(line_number))?;
+    chains.insert(end.to_string(), start.to_string());
+    Ok(())
+}
+
+/// Reads the records after a binary table header
+fn read_binary<R: BufRead>(header: &TableHeader, mut reader: R) -> Result<Chains, TableError> {
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes)?;
+    let records = Records::new(RecordBytes::Owned(bytes), 0, header)?;
+    // Lookups search the ends in order
+    if !records.sorted() {
+        return Err(TableError::InvalidBinary(
+            "chains are not sorted by end".to_string(),
+        ));
+    }
+    Ok(Chains::Sorted {
+        reducer: header.reducer()?,
+        records,
+    })
+}
+
+/// Little endian index of any width up to 16 bytes
+fn read_index(bytes: &[u8]) -> u128 {
+    let mut index = [0_u8; 16];
+    index[..bytes.len()].copy_from_slice(bytes);
+    u128::from_le_bytes(index)
+}
+
+/// Writes a binary table: the header, then every chain as fixed width indices sorted by end
+///
+/// Chains whose start or end has no index in the keyspace are skipped, since
+/// their passwords did not come from the reduction, and so are chains that end
+/// like one already kept. Returns the number of chains written.
+pub fn write_binary<W: Write + ?Sized>(
+    header: &TableHeader,
+    chains: impl IntoIterator<Item = (String, String)>,
+    writer: &mut W,
+) -> Result<u64, TableError> {
+    trace!("Write binary rainbow table");
+    let keyspace = header.keyspace().ok_or_else(|| {
+        TableError::Incompatible("keyspace is too large for a binary table".to_string())
+    })?;
+    let reducer = header.reducer()?;
+    let index = |password: &str| reducer.index(password).filter(|index| *index < keyspace);
+
+    let mut skipped = 0;
+    let mut records: Vec<(u128, u128)> = chains
+        .into_iter()
+        .filter_map(|(start, end)| match (index(&end), index(&start)) {
+            (Some(end), Some(start)) => Some((end, start)),
+            _ => {
+                skipped += 1;
+                None
+            }
+        })
+        .collect();
+    if skipped > 0 {
+        warn!("Skipped {skipped} chains whose start or end is outside the keyspace");
+    }
+    records.sort_unstable();
+    records.dedup_by_key(|&mut (end, _)| end);
+
+    let header = TableHeader {
+        chains: records.len() as u64,
+        format: TableFormat::Binary,
+        ..header.clone()
+    };
+    header.write(writer)?;
+    let width = header.index_width();
+    for (end, start) in &records {
+        writer.write_all(&end.to_le_bytes()[..width])?;
+        writer.write_all(&start.to_le_bytes()[..width])?;
+    }
+    writer.flush()?;
+    Ok(header.chains)
+}
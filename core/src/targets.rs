@@ -0,0 +1,283 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    io::BufRead,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex, PoisonError,
+    },
+};
+
+use thiserror::Error;
+use tracing::{debug, trace};
+
+use crate::{
+    encoding::{Encoding, Normalization},
+    HashErrors, HashGenerator,
+};
+
+/// Size of every supported digest in bytes
+pub const HASH_LEN: usize = 16;
+
+/// Errors for loading target hashes
+#[derive(Error, Debug)]
+pub enum TargetError {
+    #[error("Invalid target on line {line}: {reason}")]
+    InvalidTarget { line: usize, reason: String },
+    #[error("Binary target file is {0} bytes, not a multiple of 16")]
+    Truncated(usize),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+/// Writes a field the way hashcat does: raw, or as $HEX[...] when it would not survive as text
+///
+/// Colons are escaped too, so a field this crate wrote never splits a line.
+pub fn encode_field(field: &[u8]) -> String {
+    let needs_hex = field.starts_with(b"$HEX[")
+        || field
+            .iter()
+            .any(|byte| !(0x20..0x7f).contains(byte) || *byte == b':');
+    match std::str::from_utf8(field) {
+        Ok(text) if !needs_hex => text.to_string(),
+        _ => format!("$HEX[{}]", hex::encode(field)),
+    }
+}
+
+/// Reads a field written by encode_field, hashcat or John
+pub fn decode_field(field: &str) -> Result<Vec<u8>, String> {
+    match field
+        .strip_prefix("$HEX[")
+        .and_then(|hex| hex.strip_suffix(']'))
+    {
+        Some(hex) => hex::decode(hex).map_err(|_| "bad $HEX[] field".to_string()),
+        None => Ok(field.as_bytes().to_vec()),
+    }
+}
+
+/// A hash to crack, with the salt it was made with if any
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Target {
+    /// Digest of the password
+    pub hash: [u8; HASH_LEN],
+    /// Salt appended to the password before hashing
+    pub salt: Option<Vec<u8>>,
+}
+
+impl Target {
+    /// Reads concatenated raw digests, as written by gen-hashes
+    pub fn from_binary(bytes: &[u8]) -> Result<Vec<Target>, TargetError> {
+        if !bytes.len().is_multiple_of(HASH_LEN) {
+            return Err(TargetError::Truncated(bytes.len()));
+        }
+        Ok(bytes
+            .chunks_exact(HASH_LEN)
+            .filter_map(|chunk| chunk.try_into().ok())
+            .map(|hash| Target { hash, salt: None })
+            .collect())
+    }
+
+    /// Reads one `hash` or `hash:salt` per line, the hash in hex and the salt as hashcat writes it; blank lines are skipped
+    pub fn from_hex_lines<R: BufRead>(reader: R) -> Result<Vec<Target>, TargetError> {
+        let mut targets = Vec::new();
+        for (index, line) in reader.lines().enumerate() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            targets.push(line.parse().map_err(|reason| TargetError::InvalidTarget {
+                line: index + 1,
+                reason,
+            })?);
+        }
+        Ok(targets)
+    }
+
+    /// Reads one `user:hash` or `user:hash:salt` per line, keeping each hash with its user
+    pub fn from_user_lines<R: BufRead>(reader: R) -> Result<Vec<(String, Target)>, TargetError> {
+        let mut targets = Vec::new();
+        for (index, line) in reader.lines().enumerate() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let invalid = |reason: String| TargetError::InvalidTarget {
+                line: index + 1,
+                reason,
+            };
+            let (user, target) = line
+                .split_once(':')
+                .ok_or_else(|| invalid("expected user:hash".to_string()))?;
+            targets.push((user.to_string(), target.parse().map_err(invalid)?));
+        }
+        Ok(targets)
+    }
+}
+
+impl FromStr for Target {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (hash, salt) = match s.split_once(':') {
+            Some((hash, salt)) => (hash, Some(salt)),
+            None => (s, None),
+        };
+        let hash = hex::decode(hash)
+            .map_err(|_| "hash is not hex".to_string())?
+            .try_into()
+            .map_err(|_| format!("hash is not {HASH_LEN} bytes"))?;
+        let salt = salt.map(decode_field).transpose()?;
+        Ok(Target { hash, salt })
+    }
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", hex::encode(self.hash))?;
+        if let Some(salt) = &self.salt {
+            write!(f, ":{}", encode_field(salt))?;
+        }
+        Ok(())
+    }
+}
+
+/// Digests sharing one salt, unsalted targets under None
+type SaltGroup = (Option<Vec<u8>>, HashSet<[u8; HASH_LEN]>);
+
+/// Every hash being attacked, grouped by salt so each candidate is hashed once per salt
+#[derive(Debug)]
+pub struct TargetSet {
+    /// Hashing algorithm the targets were made with
+    algorithm: String,
+    /// Unicode normalization applied to candidates
+    normalization: Normalization,
+    /// Byte encoding of candidates
+    encoding: Encoding,
+    /// Digests grouped by salt
+    groups: Vec<SaltGroup>,
+    /// Number of distinct targets
+    total: usize,
+    /// Targets already cracked, so each is only reported once
+    cracked: Mutex<HashSet<Target>>,
+    /// Candidates skipped because the encoding cannot represent them
+    unencodable: AtomicU64,
+}
+
+impl TargetSet {
+    /// Groups the targets by salt, dropping duplicates
+    pub fn new(targets: impl IntoIterator<Item = Target>, algorithm: String) -> Self {
+        trace!("New target set");
+        let mut by_salt: HashMap<Option<Vec<u8>>, HashSet<[u8; HASH_LEN]>> = HashMap::new();
+        for target in targets {
+            by_salt.entry(target.salt).or_default().insert(target.hash);
+        }
+        let mut groups: Vec<SaltGroup> = by_salt.into_iter().collect();
+        groups.sort_by(|a, b| a.0.cmp(&b.0));
+        let total = groups.iter().map(|(_, hashes)| hashes.len()).sum();
+        debug!("{total} targets over {} salts", groups.len());
+
+        TargetSet {
+            algorithm,
+            normalization: Normalization::default(),
+            encoding: Encoding::default(),
+            groups,
+            total,
+            cracked: Mutex::new(HashSet::new()),
+            unencodable: AtomicU64::new(0),
+        }
+    }
+
+    /// Set the Unicode normalization applied to candidates
+    pub fn with_normalization(self, normalization: Normalization) -> Self {
+        Self {
+            normalization,
+            ..self
+        }
+    }
+
+    /// Set the byte encoding candidates are hashed in
+    pub fn with_encoding(self, encoding: Encoding) -> Self {
+        Self { encoding, ..self }
+    }
+
+    /// Number of distinct targets
+    pub fn len(&self) -> usize {
+        self.total
+    }
+
+    /// Whether there is nothing to crack
+    pub fn is_empty(&self) -> bool {
+        self.total == 0
+    }
+
+    /// Number of targets not cracked yet
+    pub fn remaining(&self) -> usize {
+        let cracked = self.cracked.lock().unwrap_or_else(PoisonError::into_inner);
+        self.total - cracked.len()
+    }
+
+    /// Targets not cracked yet, grouped by salt
+    pub fn remaining_targets(&self) -> Vec<Target> {
+        let cracked = self.cracked.lock().unwrap_or_else(PoisonError::into_inner);
+        self.groups
+            .iter()
+            .flat_map(|(salt, hashes)| {
+                hashes.iter().map(|&hash| Target {
+                    hash,
+                    salt: salt.clone(),
+                })
+            })
+            .filter(|target| !cracked.contains(target))
+            .collect()
+    }
+
+    /// Number of candidates skipped because the encoding cannot represent them
+    pub fn unencodable(&self) -> u64 {
+        self.unencodable.load(Ordering::Relaxed)
+    }
+
+    /// Whether every target has been cracked
+    pub fn is_done(&self) -> bool {
+        self.remaining() == 0
+    }
+
+    /// Hashes the candidate with every salt and returns the targets it newly cracks
+    ///
+    /// A candidate the encoding cannot represent cannot be any target's
+    /// password, so it is counted and skipped rather than failing the attack.
+    pub fn check(&self, candidate: &str) -> Result<Vec<Target>, HashErrors> {
+        let mut found = Vec::new();
+        for (salt, hashes) in &self.groups {
+            let hash = match HashGenerator::new(candidate.to_string(), self.algorithm.clone())
+                .with_normalization(self.normalization)
+                .with_encoding(self.encoding)
+                .with_salt(salt.clone())
+                .hash_password()
+            {
+                Ok(hash) => hash,
+                Err(HashErrors::Encoding(error)) => {
+                    debug!("Skipping {candidate:?}: {error}");
+                    self.unencodable.fetch_add(1, Ordering::Relaxed);
+                    return Ok(found);
+                }
+                Err(error) => return Err(error),
+            };
+            if !hashes.contains(&hash) {
+                continue;
+            }
+
+            let target = Target {
+                hash,
+                salt: salt.clone(),
+            };
+            let mut cracked = self.cracked.lock().unwrap_or_else(PoisonError::into_inner);
+            if cracked.insert(target.clone()) {
+                found.push(target);
+            }
+        }
+        Ok(found)
+    }
+}
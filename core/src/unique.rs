@@ -0,0 +1,54 @@
+use std::{
+    collections::HashSet,
+    hash::{BuildHasher, RandomState},
+    sync::{Mutex, PoisonError},
+};
+
+/// Thread safe set of passwords that were already handed out
+///
+/// The set is split into shards behind their own locks, picked by hash, so
+/// worker threads rarely wait on each other.
+#[derive(Debug)]
+pub struct UniqueFilter {
+    /// Seen passwords, split by hash
+    shards: Vec<Mutex<HashSet<String>>>,
+    /// Picks the shard for a password
+    hasher: RandomState,
+}
+
+impl UniqueFilter {
+    /// Creates an empty filter with the given number of shards (at least one)
+    pub fn new(num_shards: usize) -> Self {
+        UniqueFilter {
+            shards: (0..num_shards.max(1))
+                .map(|_| Mutex::new(HashSet::new()))
+                .collect(),
+            hasher: RandomState::new(),
+        }
+    }
+
+    /// Remembers the password, returns false if it was seen before
+    pub fn insert(&self, password: &str) -> bool {
+        let shard = self.hasher.hash_one(password) as usize % self.shards.len();
+        let mut seen = self.shards[shard]
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if seen.contains(password) {
+            return false;
+        }
+        seen.insert(password.to_string())
+    }
+
+    /// Number of distinct passwords seen
+    pub fn len(&self) -> usize {
+        self.shards
+            .iter()
+            .map(|shard| shard.lock().unwrap_or_else(PoisonError::into_inner).len())
+            .sum()
+    }
+
+    /// Whether no password was seen yet
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
@@ -0,0 +1,66 @@
+//! Candidates built from usernames
+
+use std::collections::HashSet;
+
+use hashassin_core::association::{base_words, candidates};
+
+#[test]
+fn emails_split_into_names_and_initials() {
+    assert_eq!(
+        base_words("john.smith@example.com"),
+        [
+            "john.smith@example.com",
+            "john.smith",
+            "johnsmith",
+            "john",
+            "smith",
+            "jsmith",
+            "smithj",
+            "moc.elpmaxe@htims.nhoj",
+            "htims.nhoj",
+            "htimsnhoj",
+            "nhoj",
+            "htims",
+            "htimsj",
+            "jhtims",
+        ]
+    );
+}
+
+#[test]
+fn camel_case_and_digits_are_boundaries() {
+    let words = base_words("JohnSmith1987");
+    for word in [
+        "JohnSmith1987",
+        "John",
+        "Smith",
+        "JSmith",
+        "SmithJ",
+        "htimS",
+    ] {
+        assert!(
+            words.iter().any(|w| w == word),
+            "{word} missing from {words:?}"
+        );
+    }
+    // Digit runs are not names, so they are never a word on their own
+    assert!(!words.iter().any(|w| w == "1987"));
+    assert_eq!(base_words("alice"), ["alice", "ecila"]);
+}
+
+#[test]
+fn candidates_add_cases_and_suffixes_without_repeats() {
+    let all = candidates("john.smith@example.com");
+    assert_eq!(all[0], "john.smith@example.com");
+    for candidate in [
+        "john1987",
+        "Smith!",
+        "JSMITH123",
+        "jsmith07",
+        "Johnsmith2024",
+    ] {
+        assert!(all.iter().any(|c| c == candidate), "{candidate} missing");
+    }
+    let unique: HashSet<&String> = all.iter().collect();
+    assert_eq!(unique.len(), all.len());
+}
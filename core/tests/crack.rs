@@ -0,0 +1,89 @@
+//! Rainbow table lookups
+
+use std::{collections::HashMap, num::NonZeroUsize};
+
+use hashassin_core::{
+    reduction::{Reduction, REDUCTION_VERSION},
+    table::{TableFormat, TableHeader},
+    Crack, CrackOutcome, HashGenerator,
+};
+
+/// Header of a tiny table over abc, small enough that chains merge
+fn header() -> TableHeader {
+    TableHeader {
+        algorithm: "Md5".to_string(),
+        chain_length: NonZeroUsize::new(6).unwrap(),
+        password_length: NonZeroUsize::new(4).unwrap(),
+        min_password_length: NonZeroUsize::new(4).unwrap(),
+        charset: vec!['a', 'b', 'c'],
+        reduction: Reduction::Charset,
+        reduction_version: REDUCTION_VERSION,
+        table_index: 0,
+        chains: 2,
+        format: TableFormat::Text,
+    }
+}
+
+/// Cracker over chains from each of `starts`
+fn crack(starts: &[&str]) -> Crack {
+    let walker = Crack::from_header(&header(), HashMap::new()).unwrap();
+    let chains: HashMap<String, String> = starts
+        .iter()
+        .map(|start| {
+            (
+                walker.helper(6, start.to_string()).unwrap(),
+                start.to_string(),
+            )
+        })
+        .collect();
+    assert_eq!(chains.len(), starts.len(), "chains must end differently");
+    Crack::from_header(&header(), chains).unwrap()
+}
+
+#[test]
+fn merged_chains_are_false_alarms() {
+    let hash = HashGenerator::new("aaaa".to_string(), "Md5".to_string())
+        .hash_password()
+        .unwrap();
+
+    // aaaa starts its own chain, so alone it is found straight away
+    let alone = crack(&["aaaa"]);
+    let CrackOutcome::Found { false_alarms, .. } = alone.crack(&hash) else {
+        panic!("aaaa was not found");
+    };
+    assert_eq!(false_alarms, 0);
+
+    // The walk from a later position merges into the acba chain, whose start
+    // does not regenerate the hash, so it is skipped and the search goes on
+    let merged = crack(&["aaaa", "acba"]);
+    match merged.crack(&hash) {
+        CrackOutcome::Found {
+            password,
+            chain_position,
+            false_alarms,
+            ..
+        } => {
+            assert_eq!(password, "aaaa");
+            assert_eq!(chain_position, 0);
+            assert_eq!(false_alarms, 1);
+        }
+        other => panic!("aaaa was not found: {other:?}"),
+    }
+    assert_eq!(merged.false_alarms(), 1);
+
+    // The total counts every lookup
+    merged.crack(&hash);
+    assert_eq!(merged.false_alarms(), 2);
+}
+
+#[test]
+fn missing_passwords_are_not_found() {
+    let hash = HashGenerator::new("zzzz".to_string(), "Md5".to_string())
+        .hash_password()
+        .unwrap();
+
+    assert!(matches!(
+        crack(&["aaaa", "acba"]).crack(&hash),
+        CrackOutcome::NotFound
+    ));
+}
@@ -0,0 +1,81 @@
+//! Keyboard walks on each layout
+
+use std::{collections::HashSet, num::NonZeroUsize};
+
+use hashassin_core::keyboard::{KeyboardWalks, Layout, WalkOptions};
+
+fn walks(
+    layout: Layout,
+    length: usize,
+    max_turns: usize,
+    strokes: usize,
+    shifted: bool,
+) -> Vec<String> {
+    KeyboardWalks::new(WalkOptions {
+        layout,
+        length: NonZeroUsize::new(length).unwrap(),
+        max_turns,
+        strokes: NonZeroUsize::new(strokes).unwrap(),
+        shifted,
+    })
+    .collect()
+}
+
+#[test]
+fn walks_follow_adjacent_keys_within_the_turn_limit() {
+    for layout in [Layout::Qwerty, Layout::Azerty, Layout::Qwertz] {
+        for max_turns in 0..=2 {
+            let all = walks(layout, 4, max_turns, 1, false);
+            let unique: HashSet<&String> = all.iter().collect();
+            assert_eq!(unique.len(), all.len(), "{layout} {max_turns}");
+            for walk in &all {
+                let keys: Vec<char> = walk.chars().collect();
+                assert_eq!(keys.len(), 4, "{walk}");
+                let directions: Vec<_> = keys
+                    .windows(2)
+                    .map(|pair| layout.step(pair[0], pair[1]).unwrap())
+                    .collect();
+                let turns = directions
+                    .windows(2)
+                    .filter(|pair| pair[0] != pair[1])
+                    .count();
+                assert!(turns <= max_turns, "{layout} {walk} has {turns} turns");
+            }
+        }
+    }
+}
+
+#[test]
+fn common_walks_are_found() {
+    let straight = walks(Layout::Qwerty, 4, 0, 1, false);
+    for walk in ["qwer", "asdf", "zxcv", "1qaz", "rewq"] {
+        assert!(straight.iter().any(|w| w == walk), "{walk} missing");
+    }
+    assert!(walks(Layout::Azerty, 4, 0, 1, false)
+        .iter()
+        .any(|w| w == "azer"));
+    assert!(walks(Layout::Qwertz, 6, 0, 1, false)
+        .iter()
+        .any(|w| w == "qwertz"));
+
+    let double = walks(Layout::Qwerty, 4, 0, 2, false);
+    assert!(double.iter().any(|w| w == "1qaz2wsx"));
+    assert!(double.iter().all(|w| w.chars().count() == 8));
+}
+
+#[test]
+fn shifted_walks_follow_their_plain_walk() {
+    let plain = walks(Layout::Qwerty, 3, 1, 1, false);
+    let shifted = walks(Layout::Qwerty, 3, 1, 1, true);
+    assert_eq!(shifted.len(), plain.len() * 2);
+    let position = shifted.iter().position(|w| w == "1qa").unwrap();
+    assert_eq!(shifted[position + 1], "!QA");
+    // The same walks come out in the same order on every run
+    assert_eq!(walks(Layout::Qwerty, 3, 1, 1, false), plain);
+}
+
+#[test]
+fn unknown_layouts_are_rejected() {
+    assert_eq!("QWERTZ".parse::<Layout>().unwrap(), Layout::Qwertz);
+    assert!("dvorak".parse::<Layout>().is_err());
+}
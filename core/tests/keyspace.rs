@@ -0,0 +1,86 @@
+//! Indexed keyspaces and how they are split between threads
+
+use hashassin_core::{
+    combinator::{Combinator, Hybrid},
+    keyspace::{chunks, partition, Keyspace},
+    mask::{Mask, MaskPosition},
+};
+
+fn words(words: &[&str]) -> Vec<String> {
+    words.iter().map(|word| word.to_string()).collect()
+}
+
+#[test]
+fn partitions_cover_the_keyspace_once() {
+    for (total, parts) in [(0, 3), (2, 5), (10, 1), (10, 3), (11, 4), (1000, 7)] {
+        let ranges = partition(total, parts);
+        assert_eq!(ranges.len(), parts);
+        // Consecutive ranges from 0 to total leave no gaps and no overlaps
+        let mut next = 0;
+        for range in &ranges {
+            assert_eq!(range.start, next, "{total} in {parts}");
+            next = range.end;
+        }
+        assert_eq!(next, total);
+        let lens: Vec<u128> = ranges.iter().map(|range| range.end - range.start).collect();
+        let (min, max) = (lens.iter().min().unwrap(), lens.iter().max().unwrap());
+        assert!(max - min <= 1, "{total} in {parts}: {lens:?}");
+    }
+    // No threads still gets one range
+    assert_eq!(partition(5, 0), vec![0..5]);
+    // Keyspaces past 64 bits split without overflowing
+    let ranges = partition(u128::MAX, 2);
+    assert_eq!(ranges[0].end, ranges[1].start);
+    assert_eq!(ranges[1].end, u128::MAX);
+}
+
+#[test]
+fn chunks_cover_the_range_in_order() {
+    let pieces: Vec<_> = chunks(5..16, 4).collect();
+    assert_eq!(pieces, vec![5..9, 9..13, 13..16]);
+    assert_eq!(chunks(3..3, 4).count(), 0);
+    // A zero size still makes progress
+    assert_eq!(chunks(0..2, 0).count(), 2);
+}
+
+#[test]
+fn combinator_joins_every_pair_left_first() {
+    let combinator = Combinator::new(words(&["a", "b"]), words(&["1", "2", "3"]), "-".into());
+    assert_eq!(combinator.keyspace(), 6);
+    let all: Vec<String> = combinator.candidates(0..combinator.keyspace()).collect();
+    assert_eq!(all, ["a-1", "a-2", "a-3", "b-1", "b-2", "b-3"]);
+    assert_eq!(combinator.candidate(6), None);
+
+    let empty = Combinator::new(words(&["a"]), Vec::new(), String::new());
+    assert_eq!(empty.keyspace(), 0);
+    assert_eq!(empty.candidate(0), None);
+}
+
+#[test]
+fn hybrid_puts_the_mask_on_either_side() {
+    let mask: Mask = "?d".parse().unwrap();
+    let append = Hybrid::new(words(&["pw", "ab"]), mask.clone(), MaskPosition::Append);
+    assert_eq!(append.keyspace(), 20);
+    assert_eq!(append.candidate(0).as_deref(), Some("pw0"));
+    assert_eq!(append.candidate(19).as_deref(), Some("ab9"));
+    assert_eq!(append.candidate(20), None);
+
+    let prepend = Hybrid::new(words(&["pw"]), mask, MaskPosition::Prepend);
+    assert_eq!(prepend.candidate(7).as_deref(), Some("7pw"));
+}
+
+#[test]
+fn partitioned_candidates_match_a_single_pass() {
+    let hybrid = Hybrid::new(
+        words(&["x", "y", "z"]),
+        "?l?d".parse().unwrap(),
+        MaskPosition::Append,
+    );
+    let single: Vec<String> = hybrid.candidates(0..hybrid.keyspace()).collect();
+    let split: Vec<String> = partition(hybrid.keyspace(), 7)
+        .into_iter()
+        .flat_map(|range| hybrid.candidates(range).collect::<Vec<_>>())
+        .collect();
+    assert_eq!(single.len(), 3 * 26 * 10);
+    assert_eq!(split, single);
+}
@@ -0,0 +1,109 @@
+//! Markov models and the order their candidates come out in
+
+use std::{collections::HashSet, io::BufReader, sync::Arc};
+
+use hashassin_core::markov::{MarkovError, MarkovModel, MarkovOptions};
+use rand::{rngs::StdRng, SeedableRng};
+
+const CORPUS: &[&str] = &[
+    "password", "password", "password", "passw0rd", "pass", "letmein", "dragon", "monkey",
+    "shadow", "master", "sunshine", "princess", "qwerty", "abc123", "p@ss",
+];
+
+#[test]
+fn ordered_candidates_come_most_likely_first() {
+    for order in 1..=3 {
+        let model = Arc::new(MarkovModel::train(CORPUS, order).unwrap());
+        let options = MarkovOptions {
+            min_len: 1,
+            max_len: 12,
+            threshold: 1e-6,
+        };
+        let candidates: Vec<String> = model.ordered(options).take(2000).collect();
+        assert!(candidates.len() > 10, "order {order}");
+        assert_eq!(candidates[0], "password", "order {order}");
+
+        let probabilities: Vec<f64> = candidates
+            .iter()
+            .map(|candidate| model.probability(candidate))
+            .collect();
+        for (pair, words) in probabilities.windows(2).zip(candidates.windows(2)) {
+            // Equal chances can come out in either order, up to rounding
+            assert!(
+                pair[0] >= pair[1] * (1.0 - 1e-9),
+                "order {order}: {words:?} {pair:?}"
+            );
+        }
+        assert!(probabilities.iter().all(|&p| p >= 1e-6));
+        let unique: HashSet<&String> = candidates.iter().collect();
+        assert_eq!(unique.len(), candidates.len(), "order {order}");
+    }
+}
+
+#[test]
+fn ordered_candidates_respect_the_length_limits() {
+    let model = Arc::new(MarkovModel::train(CORPUS, 2).unwrap());
+    let limited = MarkovOptions {
+        min_len: 5,
+        max_len: 6,
+        threshold: 1e-5,
+    };
+    let candidates: Vec<String> = model.ordered(limited).collect();
+    assert!(!candidates.is_empty());
+    assert!(candidates
+        .iter()
+        .all(|candidate| (5..=6).contains(&candidate.chars().count())));
+    assert!(candidates.iter().any(|candidate| candidate == "dragon"));
+}
+
+#[test]
+fn samples_respect_the_options() {
+    let model = MarkovModel::train(CORPUS, 2).unwrap();
+    let limited = MarkovOptions {
+        min_len: 4,
+        max_len: 8,
+        threshold: 1e-4,
+    };
+    let mut rng = StdRng::seed_from_u64(7);
+    let samples: Vec<String> = (0..500)
+        .filter_map(|_| model.sample(&mut rng, &limited))
+        .collect();
+    assert!(!samples.is_empty());
+    for sample in &samples {
+        assert!((4..=8).contains(&sample.chars().count()), "{sample}");
+        assert!(model.probability(sample) >= 1e-4, "{sample}");
+    }
+}
+
+#[test]
+fn saved_models_load_the_same() {
+    let model = MarkovModel::train(CORPUS, 3).unwrap();
+    let mut saved = Vec::new();
+    model.save(&mut saved).unwrap();
+    let loaded = MarkovModel::load(BufReader::new(saved.as_slice())).unwrap();
+    assert_eq!(loaded.order(), 3);
+    assert_eq!(loaded.max_len(), model.max_len());
+    for word in CORPUS.iter().chain(&["passwor", "zzz"]) {
+        assert_eq!(loaded.probability(word), model.probability(word), "{word}");
+    }
+}
+
+#[test]
+fn bad_orders_and_corpora_are_rejected() {
+    assert!(matches!(
+        MarkovModel::train(CORPUS, 0),
+        Err(MarkovError::InvalidOrder(0))
+    ));
+    assert!(matches!(
+        MarkovModel::train(CORPUS, 4),
+        Err(MarkovError::InvalidOrder(4))
+    ));
+    assert!(matches!(
+        MarkovModel::train(Vec::<String>::new(), 2),
+        Err(MarkovError::EmptyCorpus)
+    ));
+    assert!(matches!(
+        MarkovModel::load(BufReader::new("not a model\n".as_bytes())),
+        Err(MarkovError::InvalidModel { line: 1, .. })
+    ));
+}
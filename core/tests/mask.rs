@@ -0,0 +1,88 @@
+//! Mask parsing and indexing, checked against hashcat's charsets
+
+use hashassin_core::{
+    keyspace::Keyspace,
+    mask::{custom_charset, Mask, MaskError, MaskPosition},
+};
+use num::BigUint;
+
+/// Index of a candidate, computed the other way round from Mask::candidate
+fn index_of(candidate: &str, charsets: &[&str]) -> u128 {
+    candidate
+        .chars()
+        .zip(charsets)
+        .fold(0, |index, (c, charset)| {
+            index * charset.chars().count() as u128
+                + charset.chars().position(|x| x == c).unwrap() as u128
+        })
+}
+
+#[test]
+fn indices_and_candidates_round_trip() {
+    let lower = "abcdefghijklmnopqrstuvwxyz";
+    let charsets = [lower, "0123456789", "-", "0123456789abcdef"];
+    let mask: Mask = "?l?d-?h".parse().unwrap();
+    assert_eq!(mask.len(), 4);
+    assert_eq!(mask.keyspace(), 26 * 10 * 16);
+    for index in 0..mask.keyspace() {
+        let candidate = mask.candidate(index).unwrap();
+        assert_eq!(index_of(&candidate, &charsets), index, "{candidate}");
+    }
+    // The last position changes fastest
+    assert_eq!(mask.candidate(0).as_deref(), Some("a0-0"));
+    assert_eq!(mask.candidate(1).as_deref(), Some("a0-1"));
+    assert_eq!(mask.candidate(16).as_deref(), Some("a1-0"));
+    assert_eq!(mask.candidate(mask.keyspace()), None);
+}
+
+#[test]
+fn large_masks_round_trip_and_saturate() {
+    let all = concat!(
+        "abcdefghijklmnopqrstuvwxyz",
+        "ABCDEFGHIJKLMNOPQRSTUVWXYZ",
+        "0123456789",
+        " !\"#$%&'()*+,-./:;<=>?@[\\]^_`{|}~",
+    );
+    let mask: Mask = "?a".repeat(8).parse().unwrap();
+    assert_eq!(mask.keyspace(), 95_u128.pow(8));
+    for index in [0, 1, 94, 95, 123_456_789_012, 95_u128.pow(8) - 1] {
+        let candidate = mask.candidate(index).unwrap();
+        assert_eq!(index_of(&candidate, &[all; 8]), index, "{candidate}");
+    }
+
+    // 95^20 does not fit in 128 bits, the exact size is still known
+    let huge: Mask = "?a".repeat(20).parse().unwrap();
+    assert_eq!(huge.keyspace(), u128::MAX);
+    assert_eq!(*huge.size(), BigUint::from(95_u32).pow(20));
+}
+
+#[test]
+fn custom_charsets_are_sorted_and_deduplicated() {
+    assert_eq!(
+        custom_charset("?d_0").unwrap(),
+        "0123456789_".chars().collect::<Vec<_>>()
+    );
+    let custom = vec![custom_charset("ab").unwrap(), custom_charset("?u").unwrap()];
+    let mask = Mask::parse("?1?2??", &custom).unwrap();
+    assert_eq!(mask.keyspace(), 2 * 26);
+    assert_eq!(mask.candidate(27).as_deref(), Some("bB?"));
+}
+
+#[test]
+fn malformed_masks_are_rejected() {
+    for mask in ["?", "ab?", "?x", "?3"] {
+        assert!(
+            matches!(Mask::parse(mask, &[]), Err(MaskError::InvalidMask { .. })),
+            "{mask}"
+        );
+    }
+    assert!(custom_charset("").is_err());
+    assert_eq!(
+        "PREPEND".parse::<MaskPosition>().unwrap(),
+        MaskPosition::Prepend
+    );
+    assert!(matches!(
+        "middle".parse::<MaskPosition>(),
+        Err(MaskError::UnknownPosition(_))
+    ));
+}
@@ -0,0 +1,106 @@
+//! Policies no password can meet are refused instead of generating forever
+
+use std::num::NonZeroUsize;
+
+use hashassin_core::{PasswordGeneratorBuilder, PasswordGeneratorError, PasswordPolicy};
+
+fn length(length: usize) -> Option<NonZeroUsize> {
+    NonZeroUsize::new(length)
+}
+
+#[test]
+fn banning_every_character_is_refused() {
+    let policy = PasswordPolicy {
+        banned_words: (' '..='~').map(String::from).collect(),
+        ..Default::default()
+    };
+    let result = PasswordGeneratorBuilder::default()
+        .with_min_char(length(2))
+        .with_max_char(length(2))
+        .with_policy(policy)
+        .build();
+
+    assert!(matches!(
+        result,
+        Err(PasswordGeneratorError::Unsatisfiable { .. })
+    ));
+}
+
+#[test]
+fn banned_characters_are_never_drawn() {
+    let policy = PasswordPolicy {
+        banned_words: vec!["A".to_string()],
+        ..Default::default()
+    };
+    let generator = PasswordGeneratorBuilder::default()
+        .with_min_char(length(8))
+        .with_max_char(length(8))
+        .with_policy(policy)
+        .with_charset(vec!['a', 'b'])
+        .build()
+        .unwrap();
+
+    for _ in 0..100 {
+        assert_eq!(generator.generate().unwrap(), "bbbbbbbb");
+    }
+}
+
+#[test]
+fn repeating_one_character_past_max_repeat_is_refused() {
+    let policy = PasswordPolicy {
+        max_repeat: NonZeroUsize::new(2),
+        ..Default::default()
+    };
+    let result = PasswordGeneratorBuilder::default()
+        .with_min_char(length(3))
+        .with_max_char(length(3))
+        .with_policy(policy)
+        .with_charset(vec!['a'])
+        .build();
+
+    assert!(matches!(
+        result,
+        Err(PasswordGeneratorError::Unsatisfiable { .. })
+    ));
+}
+
+#[test]
+fn rejecting_every_password_gives_up() {
+    let policy = PasswordPolicy {
+        banned_words: ["aa", "ab", "ba", "bb"].map(String::from).to_vec(),
+        ..Default::default()
+    };
+    let generator = PasswordGeneratorBuilder::default()
+        .with_min_char(length(2))
+        .with_max_char(length(2))
+        .with_policy(policy)
+        .with_charset(vec!['a', 'b'])
+        .build()
+        .unwrap();
+
+    assert!(matches!(
+        generator.generate(),
+        Err(PasswordGeneratorError::TooManyRejections { .. })
+    ));
+}
+
+#[test]
+fn class_minimums_longer_than_the_password_are_refused() {
+    let policy = PasswordPolicy {
+        min_upper: 9,
+        ..Default::default()
+    };
+    let result = PasswordGeneratorBuilder::default()
+        .with_min_char(length(5))
+        .with_max_char(length(5))
+        .with_policy(policy)
+        .build();
+
+    assert!(matches!(
+        result,
+        Err(PasswordGeneratorError::PolicyTooLong {
+            required: 9,
+            max: 5
+        })
+    ));
+}
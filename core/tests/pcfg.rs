@@ -0,0 +1,104 @@
+//! PCFG grammars and the order their guesses come out in
+
+use std::{collections::HashSet, io::BufReader, sync::Arc};
+
+use hashassin_core::pcfg::{Pcfg, PcfgError, PcfgOptions};
+
+const CORPUS: &[&str] = &[
+    "monkey12",
+    "monkey12",
+    "dragon12",
+    "dragon99",
+    "monkey!",
+    "shadow1",
+    "abc123",
+    "summer2024!",
+    "hello",
+    "hello",
+    "hello",
+    "letmein1",
+];
+
+fn all_guesses(grammar: &Arc<Pcfg>) -> Vec<String> {
+    let options = PcfgOptions {
+        min_len: 1,
+        max_len: usize::MAX,
+        threshold: 0.0,
+    };
+    grammar.guesses(options).collect()
+}
+
+#[test]
+fn guesses_come_most_likely_first() {
+    let grammar = Arc::new(Pcfg::train(CORPUS).unwrap());
+    let guesses = all_guesses(&grammar);
+    assert_eq!(guesses[0], "hello");
+
+    let probabilities: Vec<f64> = guesses
+        .iter()
+        .map(|guess| grammar.probability(guess))
+        .collect();
+    for (pair, words) in probabilities.windows(2).zip(guesses.windows(2)) {
+        // Equal chances can come out in either order, up to rounding
+        assert!(pair[0] >= pair[1] * (1.0 - 1e-9), "{words:?} {pair:?}");
+    }
+    assert!(probabilities.iter().all(|&p| p > 0.0));
+}
+
+#[test]
+fn every_guess_comes_out_exactly_once() {
+    let grammar = Arc::new(Pcfg::train(CORPUS).unwrap());
+    let guesses = all_guesses(&grammar);
+    let unique: HashSet<&String> = guesses.iter().collect();
+    assert_eq!(unique.len(), guesses.len());
+    // Terminals mix across passwords with the same structure
+    for guess in CORPUS.iter().chain(&["shadow12", "dragon!", "monkey99"]) {
+        assert!(unique.contains(&guess.to_string()), "{guess} missing");
+    }
+    let probabilities: f64 = guesses.iter().map(|guess| grammar.probability(guess)).sum();
+    assert!((probabilities - 1.0).abs() < 1e-9, "{probabilities}");
+}
+
+#[test]
+fn limits_filter_guesses() {
+    let grammar = Arc::new(Pcfg::train(CORPUS).unwrap());
+    let options = PcfgOptions {
+        min_len: 7,
+        max_len: 8,
+        threshold: 0.01,
+    };
+    let guesses: Vec<String> = grammar.guesses(options).collect();
+    assert!(!guesses.is_empty());
+    for guess in &guesses {
+        assert!((7..=8).contains(&guess.chars().count()), "{guess}");
+        assert!(grammar.probability(guess) >= 0.01, "{guess}");
+    }
+}
+
+#[test]
+fn saved_grammars_load_the_same() {
+    let grammar = Pcfg::train(CORPUS).unwrap();
+    let mut saved = Vec::new();
+    grammar.save(&mut saved).unwrap();
+    let loaded = Pcfg::load(BufReader::new(saved.as_slice())).unwrap();
+    assert_eq!(loaded.num_structures(), grammar.num_structures());
+    for password in CORPUS.iter().chain(&["shadow12", "nope!!"]) {
+        assert_eq!(
+            loaded.probability(password),
+            grammar.probability(password),
+            "{password}"
+        );
+    }
+}
+
+#[test]
+fn empty_corpora_and_bad_grammars_are_rejected() {
+    assert!(matches!(
+        Pcfg::train(Vec::<String>::new()),
+        Err(PcfgError::EmptyCorpus)
+    ));
+    assert!(matches!(
+        Pcfg::load(BufReader::new("not a grammar\n".as_bytes())),
+        Err(PcfgError::InvalidGrammar { line: 1, .. })
+    ));
+}
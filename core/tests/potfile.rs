@@ -0,0 +1,198 @@
+//! Potfiles written by hashcat and John, and by hashassin itself
+
+use std::{fs, path::PathBuf};
+
+use hashassin_core::{
+    potfile::{Potfile, PotfileError},
+    targets::{decode_field, encode_field, Target},
+    HashGenerator,
+};
+
+/// A potfile in the temporary directory holding `lines`
+fn potfile(name: &str, lines: &[&str]) -> PathBuf {
+    let path =
+        std::env::temp_dir().join(format!("hashassin-{}-{name}.potfile", std::process::id()));
+    let contents: String = lines.iter().map(|line| format!("{line}\n")).collect();
+    fs::write(&path, contents).unwrap();
+    path
+}
+
+fn target(hash: &str, salt: Option<&[u8]>) -> Target {
+    Target {
+        hash: hex::decode(hash).unwrap().try_into().unwrap(),
+        salt: salt.map(<[u8]>::to_vec),
+    }
+}
+
+/// Checks the entry really is the plaintext of the target
+fn assert_cracks(target: &Target, plain: &str) {
+    let hash = HashGenerator::new(plain.to_string(), "Md5".to_string())
+        .with_salt(target.salt.clone())
+        .hash_password()
+        .unwrap();
+    assert_eq!(hash, target.hash, "{plain:?} does not crack {target}");
+}
+
+#[test]
+fn reads_hashcat_lines() {
+    let path = potfile(
+        "hashcat",
+        &[
+            // -m 0
+            "5f4dcc3b5aa765d61d8327deb882cf99:password",
+            // -m 10, md5($pass.$salt), salts are raw
+            "8223fe8dc0533c6ebbb717e7fda2833c:abc:password",
+            "c832dc1f399657cfaa61a20d7753bfc5:abc:pass:word",
+            // Non printable salts and plaintexts are $HEX[]
+            "b9c2f9f4b8edc98a9e1941574bce0c33:$HEX[00ff]:hunter2",
+        ],
+    );
+    let potfile = Potfile::open(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    let cases = [
+        (target("5f4dcc3b5aa765d61d8327deb882cf99", None), "password"),
+        (
+            target("8223fe8dc0533c6ebbb717e7fda2833c", Some(b"abc")),
+            "password",
+        ),
+        (
+            target("c832dc1f399657cfaa61a20d7753bfc5", Some(b"abc")),
+            "pass:word",
+        ),
+        (
+            target("b9c2f9f4b8edc98a9e1941574bce0c33", Some(&[0x00, 0xff])),
+            "hunter2",
+        ),
+    ];
+    assert_eq!(potfile.len(), cases.len());
+    for (target, plain) in cases {
+        assert_cracks(&target, plain);
+        assert_eq!(potfile.get(&target).as_deref(), Some(plain));
+    }
+}
+
+#[test]
+fn reads_john_lines() {
+    let path = potfile(
+        "john",
+        &[
+            // --format=raw-md5
+            "$dynamic_0$5f4dcc3b5aa765d61d8327deb882cf99:password",
+            // --format=dynamic_1, md5($p.$s)
+            "$dynamic_1$8223fe8dc0533c6ebbb717e7fda2833c$abc:password",
+            "$dynamic_1$0bd833b988887eae1dbcf18d1c909b1a$HEX$613a62:hunter2",
+        ],
+    );
+    let potfile = Potfile::open(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    let cases = [
+        (target("5f4dcc3b5aa765d61d8327deb882cf99", None), "password"),
+        (
+            target("8223fe8dc0533c6ebbb717e7fda2833c", Some(b"abc")),
+            "password",
+        ),
+        (
+            target("0bd833b988887eae1dbcf18d1c909b1a", Some(b"a:b")),
+            "hunter2",
+        ),
+    ];
+    for (target, plain) in cases {
+        assert_cracks(&target, plain);
+        assert_eq!(potfile.get(&target).as_deref(), Some(plain));
+    }
+}
+
+#[test]
+fn ambiguous_lines_are_not_guessed() {
+    // Salted with abc, or the unsalted plaintext abc:password
+    let path = potfile(
+        "ambiguous",
+        &["8223fe8dc0533c6ebbb717e7fda2833c:abc:password"],
+    );
+    let potfile = Potfile::open(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    let unsalted = target("8223fe8dc0533c6ebbb717e7fda2833c", None);
+    assert_eq!(potfile.get(&unsalted), None);
+    let other_salt = target("8223fe8dc0533c6ebbb717e7fda2833c", Some(b"ab"));
+    assert_eq!(potfile.get(&other_salt), None);
+}
+
+#[test]
+fn appended_entries_round_trip() {
+    let path = potfile("round-trip", &[]);
+    let cases = [
+        (
+            target("8223fe8dc0533c6ebbb717e7fda2833c", Some(b"abc")),
+            "password",
+        ),
+        (
+            target("0bd833b988887eae1dbcf18d1c909b1a", Some(b"a:b")),
+            "hunter2",
+        ),
+        (target("7ffe48940e8f1ba04e97d8ecb1acd12e", None), "pé:x"),
+    ];
+    let written = Potfile::open(&path).unwrap();
+    for (target, plain) in &cases {
+        assert_cracks(target, plain);
+        written.append(target, plain).unwrap();
+    }
+    drop(written);
+
+    let lines = fs::read_to_string(&path).unwrap();
+    assert_eq!(
+        lines,
+        "8223fe8dc0533c6ebbb717e7fda2833c:abc:password\n\
+         0bd833b988887eae1dbcf18d1c909b1a:$HEX[613a62]:hunter2\n\
+         7ffe48940e8f1ba04e97d8ecb1acd12e:$HEX[70c3a93a78]\n"
+    );
+    let read = Potfile::open(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    for (target, plain) in &cases {
+        assert_eq!(read.get(target).as_deref(), Some(*plain));
+    }
+}
+
+#[test]
+fn reading_needs_an_existing_potfile_and_never_writes() {
+    let missing =
+        std::env::temp_dir().join(format!("hashassin-{}-missing.potfile", std::process::id()));
+    assert!(matches!(
+        Potfile::read(&missing),
+        Err(PotfileError::Missing(path)) if path == missing
+    ));
+    assert!(!missing.exists());
+
+    let path = potfile("read-only", &["7ffe48940e8f1ba04e97d8ecb1acd12e:x"]);
+    let read = Potfile::read(&path).unwrap();
+    let target = target("8223fe8dc0533c6ebbb717e7fda2833c", Some(b"abc"));
+    assert!(matches!(
+        read.append(&target, "password"),
+        Err(PotfileError::ReadOnly)
+    ));
+    let lines = fs::read_to_string(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(read.len(), 1);
+    assert_eq!(lines, "7ffe48940e8f1ba04e97d8ecb1acd12e:x\n");
+}
+
+#[test]
+fn salts_are_raw_unless_they_need_escaping() {
+    for (salt, field) in [
+        (&b"abc"[..], "abc"),
+        (b"a:b", "$HEX[613a62]"),
+        (b"\x00\xff", "$HEX[00ff]"),
+        (b"$HEX[41]", "$HEX[244845585b34315d]"),
+    ] {
+        assert_eq!(encode_field(salt), field);
+        assert_eq!(decode_field(field).unwrap(), salt);
+
+        let target = Target {
+            hash: [0; 16],
+            salt: Some(salt.to_vec()),
+        };
+        assert_eq!(target.to_string().parse::<Target>().unwrap(), target);
+    }
+}
@@ -0,0 +1,76 @@
+//! PRINCE chains and the order they come out in
+
+use std::sync::Arc;
+
+use hashassin_core::prince::{Prince, PrinceOptions};
+
+fn candidates(words: &[&str], options: PrinceOptions) -> Vec<String> {
+    Arc::new(Prince::new(words, options)).candidates().collect()
+}
+
+#[test]
+fn shortest_first_and_smallest_chain_first() {
+    let options = PrinceOptions {
+        max_len: 3,
+        ..PrinceOptions::default()
+    };
+    // Length 2 has the chain [2] with one candidate before [1, 1] with four
+    assert_eq!(
+        candidates(&["a", "b", "cd"], options),
+        [
+            "a", "b", // length 1
+            "cd", "aa", "ab", "ba", "bb", // length 2
+            "acd", "bcd", "cda", "cdb", // [1, 2] and [2, 1] with two each
+            "aaa", "aab", "aba", "abb", "baa", "bab", "bba", "bbb", // [1, 1, 1] with eight
+        ]
+    );
+}
+
+#[test]
+fn lengths_never_decrease_and_stay_in_bounds() {
+    let options = PrinceOptions {
+        min_len: 3,
+        max_len: 7,
+        ..PrinceOptions::default()
+    };
+    let all = candidates(&["x", "yz", "abc", "four", "hello"], options);
+    assert!(!all.is_empty());
+    let lens: Vec<usize> = all.iter().map(|c| c.chars().count()).collect();
+    assert!(lens.windows(2).all(|pair| pair[0] <= pair[1]));
+    assert_eq!(lens.first(), Some(&3));
+    assert_eq!(lens.last(), Some(&7));
+}
+
+#[test]
+fn element_limits_are_respected() {
+    let words = ["a", "b", "cc"];
+    let singles = PrinceOptions {
+        max_elements: 1,
+        ..PrinceOptions::default()
+    };
+    assert_eq!(candidates(&words, singles), ["a", "b", "cc"]);
+
+    let pairs = PrinceOptions {
+        min_elements: 2,
+        max_elements: 2,
+        max_len: 3,
+        ..PrinceOptions::default()
+    };
+    assert_eq!(
+        candidates(&words, pairs),
+        ["aa", "ab", "ba", "bb", "acc", "bcc", "cca", "ccb"]
+    );
+}
+
+#[test]
+fn empty_repeated_and_long_words_are_dropped() {
+    let options = PrinceOptions {
+        max_elements: 1,
+        max_len: 3,
+        ..PrinceOptions::default()
+    };
+    assert_eq!(
+        candidates(&["", "ab", "ab", "toolong", "é"], options),
+        ["é", "ab"]
+    );
+}
@@ -0,0 +1,50 @@
+//! Pronounceable passwords made of syllables
+
+use std::num::NonZeroUsize;
+
+use hashassin_core::{pronounceable::PronounceableGeneratorBuilder, PasswordGeneratorError};
+
+#[test]
+fn pronounceable_passwords_have_the_requested_shape() {
+    let generator = PronounceableGeneratorBuilder::default()
+        .with_min_char(NonZeroUsize::new(6))
+        .with_max_char(NonZeroUsize::new(10))
+        .with_capitalize(true)
+        .with_digits(2)
+        .build()
+        .unwrap();
+    for _ in 0..200 {
+        let password = generator.generate();
+        assert!((6..=10).contains(&password.len()), "{password}");
+        let (letters, digits) = password.split_at(password.len() - 2);
+        assert!(digits.chars().all(|c| c.is_ascii_digit()), "{password}");
+        let mut letters = letters.chars();
+        assert!(letters.next().unwrap().is_ascii_uppercase(), "{password}");
+        assert!(letters.all(|c| c.is_ascii_lowercase()), "{password}");
+    }
+}
+
+#[test]
+fn pronounceable_limits_are_checked() {
+    let min_max = PronounceableGeneratorBuilder::default()
+        .with_min_char(NonZeroUsize::new(9))
+        .with_max_char(NonZeroUsize::new(4))
+        .build();
+    assert!(matches!(
+        min_max,
+        Err(PasswordGeneratorError::MinMax { min: 9, max: 4 })
+    ));
+    // The digits must leave room for a letter
+    let too_many_digits = PronounceableGeneratorBuilder::default()
+        .with_max_char(NonZeroUsize::new(4))
+        .with_min_char(NonZeroUsize::new(4))
+        .with_digits(4)
+        .build();
+    assert!(matches!(
+        too_many_digits,
+        Err(PasswordGeneratorError::PolicyTooLong {
+            required: 5,
+            max: 4
+        })
+    ));
+}
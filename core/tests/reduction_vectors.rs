@@ -0,0 +1,59 @@
+//! Known answers for every reduction version, so tables stay interoperable
+//! across builds and machines
+
+use std::num::NonZeroUsize;
+
+use hashassin_core::reduction::{Reducer, Reduction, REDUCTION_VERSION};
+
+/// One vector per line: reduction, version, hex charset, password length or
+/// min-max lengths, hex hash, link number and hex password
+const VECTORS: &str = include_str!("reduction_vectors.tsv");
+
+fn utf8(hex: &str) -> String {
+    String::from_utf8(hex::decode(hex).unwrap()).unwrap()
+}
+
+#[test]
+fn reductions_match_known_answers() {
+    let mut checked = 0;
+    for line in VECTORS.lines().filter(|line| !line.starts_with('#')) {
+        let fields: Vec<&str> = line.split('\t').collect();
+        let [reduction, version, charset, password_length, hash, link_number, password] =
+            fields[..]
+        else {
+            panic!("bad vector {line}");
+        };
+        // A single length, or min-max
+        let (min_length, password_length) = password_length
+            .split_once('-')
+            .unwrap_or((password_length, password_length));
+        let reducer = Reducer::new(
+            reduction.parse::<Reduction>().unwrap(),
+            utf8(charset).chars().collect(),
+            password_length.parse::<NonZeroUsize>().unwrap(),
+        )
+        .unwrap()
+        .with_min_length(min_length.parse().unwrap())
+        .unwrap()
+        .with_version(version.parse().unwrap())
+        .unwrap();
+        let hash: [u8; 16] = hex::decode(hash).unwrap().try_into().unwrap();
+
+        let reduced = reducer.reduce(&hash, link_number.parse().unwrap());
+        assert_eq!(reduced, utf8(password), "{line}");
+        // Binary tables store indices, which only go up to 128 bits
+        if reducer.keyspace().bits() <= 128 {
+            let index = reducer.index(&reduced).unwrap();
+            assert_eq!(reducer.password(index), reduced, "{line}");
+        }
+        checked += 1;
+    }
+    assert!(checked > 0);
+}
+
+#[test]
+fn unknown_versions_are_refused() {
+    let reducer = Reducer::ascii(NonZeroUsize::new(4).unwrap()).unwrap();
+    assert!(reducer.clone().with_version(0).is_err());
+    assert!(reducer.with_version(REDUCTION_VERSION + 1).is_err());
+}
@@ -0,0 +1,133 @@
+//! Rule parsing and mangling, checked against hashcat's behavior
+
+use hashassin_core::rules::{Rule, RuleError, RuleSet, MAX_WORD_LEN};
+
+/// Applies a rule written like a rule file line
+fn apply(rule: &str, word: &str) -> Option<String> {
+    rule.parse::<Rule>().unwrap().apply(word)
+}
+
+#[test]
+fn words_are_capped_like_hashcat() {
+    // 4 * 2^6 is exactly the cap
+    assert_eq!(apply(&"d".repeat(6), "abcd").unwrap().len(), MAX_WORD_LEN);
+    assert_eq!(apply(&"d".repeat(7), "abcd"), None);
+    // Would be gigabytes without the cap
+    assert_eq!(apply(&"d".repeat(30), "abcd"), None);
+    // Growing past the cap is dropped even if a later function shrinks it again
+    assert_eq!(apply("dddddddd '4", "abcd"), None);
+    // Multi byte characters count in bytes
+    assert_eq!(apply(":", &"é".repeat(129)), None);
+    assert_eq!(apply(":", &"é".repeat(128)).unwrap().len(), MAX_WORD_LEN);
+}
+
+#[test]
+fn best64_parses_completely() {
+    let lines = include_str!("../data/best64.rule")
+        .lines()
+        .filter(|line| !line.trim().is_empty() && !line.starts_with('#'))
+        .inspect(|line| {
+            line.parse::<Rule>().unwrap();
+        })
+        .count();
+
+    assert_eq!(RuleSet::best64().unwrap().len(), lines);
+}
+
+#[test]
+fn functions_match_hashcat() {
+    // Examples from hashcat's rule documentation where it has them
+    let cases = [
+        (":", "p@ssW0rd", "p@ssW0rd"),
+        ("l", "p@ssW0rd", "p@ssw0rd"),
+        ("u", "p@ssW0rd", "P@SSW0RD"),
+        ("c", "p@ssW0rd", "P@ssw0rd"),
+        ("C", "p@ssW0rd", "p@SSW0RD"),
+        ("t", "p@ssW0rd", "P@SSw0RD"),
+        ("T3", "p@ssW0rd", "p@sSW0rd"),
+        ("r", "p@ssW0rd", "dr0Wss@p"),
+        ("d", "p@ssW0rd", "p@ssW0rdp@ssW0rd"),
+        ("p2", "p@ssW0rd", "p@ssW0rdp@ssW0rdp@ssW0rd"),
+        ("f", "p@ssW0rd", "p@ssW0rddr0Wss@p"),
+        ("{", "p@ssW0rd", "@ssW0rdp"),
+        ("}", "p@ssW0rd", "dp@ssW0r"),
+        ("$1", "p@ssW0rd", "p@ssW0rd1"),
+        ("^1", "p@ssW0rd", "1p@ssW0rd"),
+        ("[", "p@ssW0rd", "@ssW0rd"),
+        ("]", "p@ssW0rd", "p@ssW0r"),
+        ("D3", "p@ssW0rd", "p@sW0rd"),
+        ("x04", "p@ssW0rd", "p@ss"),
+        ("O12", "p@ssW0rd", "psW0rd"),
+        ("i4!", "p@ssW0rd", "p@ss!W0rd"),
+        ("o3$", "p@ssW0rd", "p@s$W0rd"),
+        ("'6", "p@ssW0rd", "p@ssW0"),
+        ("ss$", "p@ssW0rd", "p@$$W0rd"),
+        ("@s", "p@ssW0rd", "p@W0rd"),
+        ("z2", "p@ssW0rd", "ppp@ssW0rd"),
+        ("Z2", "p@ssW0rd", "p@ssW0rddd"),
+        ("q", "p@ssW0rd", "pp@@ssssWW00rrdd"),
+        ("k", "p@ssW0rd", "@pssW0rd"),
+        ("K", "p@ssW0rd", "p@ssW0dr"),
+        ("*34", "p@ssW0rd", "p@sWs0rd"),
+        ("L2", "p@ssW0rd", "p@æsW0rd"),
+        ("R2", "p@ssW0rd", "p@9sW0rd"),
+        ("+2", "p@ssW0rd", "p@tsW0rd"),
+        ("-1", "p@ssW0rd", "p?ssW0rd"),
+        (".1", "p@ssW0rd", "psssW0rd"),
+        (",1", "p@ssW0rd", "ppssW0rd"),
+        ("y2", "p@ssW0rd", "p@p@ssW0rd"),
+        ("Y2", "p@ssW0rd", "p@ssW0rdrd"),
+        ("E", "p@ssW0rd w0rld", "P@ssw0rd W0rld"),
+        ("e-", "pass-word", "Pass-Word"),
+        // Functions chain left to right, spaces only separate them
+        ("c $1 $2", "password", "Password12"),
+        ("$ ", "pass", "pass "),
+        // Positions past the end leave the word alone
+        ("D9", "abc", "abc"),
+        ("T9", "abc", "abc"),
+        ("x25", "abc", "abc"),
+        ("i9!", "abc", "abc"),
+        // Positions go on from 9 with A-Z
+        ("TA", "abcdefghijk", "abcdefghijK"),
+    ];
+    for (rule, word, expected) in cases {
+        assert_eq!(apply(rule, word).as_deref(), Some(expected), "{rule}");
+    }
+}
+
+#[test]
+fn rejections_match_hashcat() {
+    let cases = [
+        ("<7", false),
+        ("<8", true),
+        (">9", false),
+        (">8", true),
+        ("_8", true),
+        ("_7", false),
+        ("!z", true),
+        ("!@", false),
+        ("/@", true),
+        ("/z", false),
+        ("(p", true),
+        ("(d", false),
+        (")d", true),
+        (")p", false),
+        ("=1@", true),
+        ("=1p", false),
+        ("%2s", true),
+        ("%3s", false),
+    ];
+    for (rule, kept) in cases {
+        assert_eq!(apply(rule, "p@ssW0rd").is_some(), kept, "{rule}");
+    }
+}
+
+#[test]
+fn malformed_rules_are_refused() {
+    for rule in ["", " ", "$", "sa", "T", "T?", "x1", "i1", "Q"] {
+        assert!(
+            matches!(rule.parse::<Rule>(), Err(RuleError::InvalidRule { .. })),
+            "{rule:?}"
+        );
+    }
+}
@@ -0,0 +1,93 @@
+//! Checkpoints of stopped attacks
+
+use std::fs;
+
+use hashassin_core::{
+    session::{Session, SessionError, Watermark},
+    targets::Target,
+};
+
+#[test]
+fn watermark_waits_for_earlier_ranges() {
+    let mut watermark = Watermark::new(10);
+
+    // Finished out of order, nothing joins the position yet
+    watermark.complete(30..40);
+    watermark.complete(20..30);
+    assert_eq!(watermark.position(), 10);
+
+    // The gap closes and the position runs through every joined range
+    watermark.complete(10..20);
+    assert_eq!(watermark.position(), 40);
+
+    // Work the restore redid before the position changes nothing
+    watermark.complete(0..10);
+    assert_eq!(watermark.position(), 40);
+
+    watermark.complete(50..60);
+    watermark.complete(40..50);
+    assert_eq!(watermark.position(), 60);
+}
+
+#[test]
+fn sessions_round_trip() {
+    let path = std::env::temp_dir().join(format!("hashassin-{}.session", std::process::id()));
+    let session = Session {
+        args: [
+            "--attack",
+            "dictionary",
+            "--separator",
+            "\t",
+            "--wordlist",
+            "line\nbreak",
+        ]
+        .map(String::from)
+        .to_vec(),
+        position: u128::MAX,
+        remaining: Some(vec![
+            "900150983cd24fb0d6963f7d28e17f72"
+                .parse::<Target>()
+                .unwrap(),
+            Target {
+                hash: [0xab; 16],
+                salt: Some(b"a:b\xff".to_vec()),
+            },
+        ]),
+    };
+    session.save(&path).unwrap();
+    let restored = Session::load(&path);
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(restored.unwrap(), session);
+}
+
+#[test]
+fn sessions_without_targets_round_trip() {
+    let path = std::env::temp_dir().join(format!(
+        "hashassin-{}-untracked.session",
+        std::process::id()
+    ));
+    let session = Session {
+        args: vec!["--attack".to_string(), "association".to_string()],
+        position: 7,
+        remaining: None,
+    };
+    session.save(&path).unwrap();
+    let restored = Session::load(&path);
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(restored.unwrap(), session);
+}
+
+#[test]
+fn other_files_are_not_sessions() {
+    let path = std::env::temp_dir().join(format!("hashassin-{}-bad.session", std::process::id()));
+    fs::write(&path, "position\t3\n").unwrap();
+    let restored = Session::load(&path);
+    fs::remove_file(&path).unwrap();
+
+    assert!(matches!(
+        restored,
+        Err(SessionError::InvalidSession { line: 1, .. })
+    ));
+}
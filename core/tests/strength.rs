@@ -0,0 +1,38 @@
+//! Pattern based strength estimates
+
+use std::time::{Duration, Instant};
+
+use hashassin_core::strength::{estimate_with, Pattern};
+
+#[test]
+fn long_repeats_finish_quickly() {
+    for password in ["a".repeat(100), "ab".repeat(50), "abc".repeat(40)] {
+        let started = Instant::now();
+        let estimate = estimate_with(&password, &[]);
+        assert!(started.elapsed() < Duration::from_secs(1), "{password}");
+        assert!(estimate
+            .patterns
+            .iter()
+            .any(|m| matches!(m.pattern, Pattern::Repeat { .. })));
+    }
+}
+
+#[test]
+fn repeats_use_the_longest_run() {
+    let estimate = estimate_with("abcabcabc", &[]);
+    assert_eq!(estimate.patterns.len(), 1);
+    assert_eq!(
+        estimate.patterns[0].pattern,
+        Pattern::Repeat {
+            block: "abc".to_string(),
+            count: 3
+        }
+    );
+}
+
+#[test]
+fn repeats_are_cheaper_than_random() {
+    let repeated = estimate_with("xk9qxk9qxk9q", &[]);
+    let random = estimate_with("xk9qm2v7plr4", &[]);
+    assert!(repeated.guesses_log10 < random.guesses_log10);
+}
@@ -0,0 +1,91 @@
+//! Reading rainbow table files
+
+use std::{fs, io::Cursor, num::NonZeroUsize};
+
+use hashassin_core::{
+    reduction::{Reduction, REDUCTION_VERSION},
+    table::{write_binary, Chains, Records, TableError, TableFile, TableFormat, TableHeader},
+};
+
+/// Header of a small table over lowercase letters
+fn header(chains: u64, format: TableFormat) -> TableHeader {
+    TableHeader {
+        algorithm: "Md5".to_string(),
+        chain_length: NonZeroUsize::new(10).unwrap(),
+        password_length: NonZeroUsize::new(4).unwrap(),
+        min_password_length: NonZeroUsize::new(4).unwrap(),
+        charset: ('a'..='z').collect(),
+        reduction: Reduction::Charset,
+        reduction_version: REDUCTION_VERSION,
+        table_index: 0,
+        chains,
+        format,
+    }
+}
+
+#[test]
+fn absurd_chain_counts_are_refused() {
+    for format in [TableFormat::Text, TableFormat::Binary] {
+        let mut file = Vec::new();
+        header(u64::MAX, format).write(&mut file).unwrap();
+
+        match TableFile::read(Cursor::new(file)) {
+            Err(TableError::InvalidHeader { reason, .. }) => {
+                assert_eq!(reason, "too many chains")
+            }
+            other => panic!("{format} table was not refused: {other:?}"),
+        }
+    }
+}
+
+/// Records of a binary table, or panics for a text one
+fn records(table: &TableFile) -> &Records {
+    match &table.chains {
+        Chains::Sorted { records, .. } => records,
+        Chains::Text(_) => panic!("binary table read as text"),
+    }
+}
+
+#[test]
+fn lookups_agree_with_a_linear_scan() {
+    let header = header(0, TableFormat::Binary);
+    let reducer = header.reducer().unwrap();
+    let keyspace = header.keyspace().unwrap();
+    // Ends bunched at the bottom with a few far apart at the top throw
+    // interpolation off, so lookups have to fall back to bisecting
+    let ends: Vec<u128> = (0..500)
+        .map(|end| end * 3)
+        .chain((0..12).map(|power| keyspace - (1 << (power + 6))))
+        .chain([keyspace - 1])
+        .collect();
+    let chains = ends
+        .iter()
+        .map(|&end| (reducer.password(end / 2), reducer.password(end)));
+    let mut file = Vec::new();
+    write_binary(&header, chains, &mut file).unwrap();
+
+    let path = std::env::temp_dir().join(format!("hashassin-{}.rt", std::process::id()));
+    fs::write(&path, &file).unwrap();
+    let mapped = TableFile::open(&path);
+    fs::remove_file(&path).unwrap();
+    let (read, mapped) = (TableFile::read(Cursor::new(file)).unwrap(), mapped.unwrap());
+
+    for table in [&read, &mapped] {
+        let records = records(table);
+        assert_eq!(records.len(), ends.len());
+        let scan = |end| {
+            (0..records.len())
+                .find(|&record| records.end(record) == end)
+                .map(|record| records.start(record))
+        };
+        // Every end, the indices either side of it and both ends of the keyspace
+        for end in ends
+            .iter()
+            .flat_map(|&end| [end.saturating_sub(1), end, end + 1])
+            .chain([0, keyspace - 1, keyspace, u128::MAX])
+        {
+            assert_eq!(records.find(end), scan(end), "{end}");
+        }
+        assert_eq!(records.find(ends[7]), Some(ends[7] / 2));
+    }
+}
@@ -0,0 +1,29 @@
+//! Checking candidates against target sets
+
+use hashassin_core::{
+    encoding::Encoding,
+    targets::{Target, TargetSet},
+};
+
+/// MD5 of "abc"
+fn abc() -> Target {
+    "900150983cd24fb0d6963f7d28e17f72".parse().unwrap()
+}
+
+#[test]
+fn unencodable_candidates_are_skipped() {
+    let targets = TargetSet::new([abc()], "Md5".to_string()).with_encoding(Encoding::Latin1);
+
+    assert!(targets.check("héllo").unwrap().is_empty());
+    assert!(targets.check("日本").unwrap().is_empty());
+    assert_eq!(targets.unencodable(), 1);
+    assert_eq!(targets.check("abc").unwrap().len(), 1);
+    assert!(targets.is_done());
+}
+
+#[test]
+fn unsupported_algorithms_still_fail() {
+    let targets = TargetSet::new([abc()], "NotAHash".to_string());
+
+    assert!(targets.check("abc").is_err());
+}